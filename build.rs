@@ -0,0 +1,6 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/solver.proto").expect("failed to compile protos");
+}
@@ -1,13 +1,140 @@
-use core::num::NonZeroU8;
+use core::{num::NonZeroU8, ops::ControlFlow};
 
 use crate::{
-    DecimalSolver, compute_target_anubis, compute_target_goaway,
+    compute_target_anubis, compute_target_goaway, compute_target_mcaptcha,
     message::{CapJSEmitter, DecimalMessage, GoAwayMessage},
-    solver::{SOLVE_TYPE_LT, Solver},
+    scheme::PowScheme,
+    solver::{SOLVE_TYPE_GT, SOLVE_TYPE_LT, Solver, autotune::AnySolver},
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
 };
-use alloc::{string::String, vec::Vec};
 use sha2::Digest;
 
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+/// mCaptcha PoW challenge descriptor.
+pub struct McaptchaChallengeDescriptor {
+    /// The salt. (JSON key: `salt`)
+    pub salt: String,
+    /// The phrase to hash. (JSON key: `string`)
+    #[serde(alias = "phrase")]
+    pub string: String,
+    /// The difficulty factor. (JSON key: `difficulty_factor`)
+    #[serde(alias = "difficulty")]
+    pub difficulty_factor: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Outcome of verifying an mCaptcha proof.
+pub struct McaptchaVerification {
+    /// The top 128 bits of the proof hash, reported by mCaptcha as the `result`.
+    pub result: u128,
+    /// The smallest result accepted for the difficulty factor.
+    pub target: u128,
+}
+
+impl McaptchaVerification {
+    /// Whether the proof meets the difficulty factor.
+    pub fn is_sufficient(&self) -> bool {
+        self.result >= self.target
+    }
+}
+
+impl McaptchaChallengeDescriptor {
+    /// Recompute a proof the way the mCaptcha server does, using 128-bit difficulty targets.
+    pub fn verify(&self, nonce: u64) -> McaptchaVerification {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(self.prefix());
+        hasher.update(nonce.to_string().as_bytes());
+        let hash = hasher.finalize();
+        let difficulty_factor = self.difficulty_factor as u128;
+        McaptchaVerification {
+            result: u128::from_be_bytes(hash[..16].try_into().unwrap()),
+            target: u128::MAX
+                - u128::MAX
+                    .checked_div(difficulty_factor)
+                    .unwrap_or(u128::MAX),
+        }
+    }
+
+    /// Build the hashed prefix of an mCaptcha PoW.
+    pub fn prefix(&self) -> Vec<u8> {
+        let mut prefix = Vec::new();
+        crate::build_mcaptcha_prefix(&mut prefix, &self.string, &self.salt);
+        prefix
+    }
+
+    /// Estimate the workload of an mCaptcha PoW.
+    pub fn estimated_workload(&self) -> u64 {
+        self.difficulty_factor as u64
+    }
+
+    /// Solve an mCaptcha PoW.
+    pub fn solve(&self) -> (Option<(u64, [u32; 8])>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// Solve an mCaptcha PoW with a limit.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<(u64, [u32; 8])>, u64) {
+        self.solve_shard_with_limit(0, 1, limit)
+    }
+
+    /// Solve one of `shards` disjoint slices of the nonce space of an mCaptcha PoW with a limit.
+    ///
+    /// Shard `shard` only visits search banks congruent to `shard` modulo `shards`.
+    pub fn solve_shard_with_limit(
+        &self,
+        shard: u32,
+        shards: u32,
+        limit: u64,
+    ) -> (Option<(u64, [u32; 8])>, u64) {
+        if shard >= shards {
+            return (None, 0);
+        }
+        self.solve_banks_with_limit(shard, shards, limit, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// Solve an mCaptcha PoW with a limit, visiting every `stride`-th search bank starting at `first_bank`.
+    ///
+    /// `checkpoint` is called with the next search bank and the nonces attempted so far
+    /// each time a search bank is exhausted, so a long solve can be resumed later.
+    /// Returning [`ControlFlow::Break`] stops the search.
+    pub fn solve_banks_with_limit(
+        &self,
+        first_bank: u32,
+        stride: u32,
+        limit: u64,
+        checkpoint: impl FnMut(u32, u64) -> ControlFlow<()>,
+    ) -> (Option<(u64, [u32; 8])>, u64) {
+        crate::scheme::solve_banks_with_limit(self, first_bank, stride, limit, checkpoint)
+    }
+}
+
+impl PowScheme for McaptchaChallengeDescriptor {
+    /// The nonce and the `result` reported to mCaptcha.
+    type Proof = (u64, u128);
+
+    const SOLVE_TYPE: u8 = SOLVE_TYPE_GT;
+
+    fn build_prefix(&self, out: &mut Vec<u8>) {
+        crate::build_mcaptcha_prefix(out, &self.string, &self.salt);
+    }
+
+    fn target(&self) -> Option<(u64, u64)> {
+        (self.difficulty_factor != 0)
+            .then(|| (compute_target_mcaptcha(self.difficulty_factor as u64), !0))
+    }
+
+    fn encode_proof(&self, nonce: u64, hash: [u32; 8]) -> (u64, u128) {
+        (nonce, crate::extract128_be(hash))
+    }
+
+    fn is_valid(&self, nonce: u64) -> bool {
+        self.verify(nonce).is_sufficient()
+    }
+}
+
 #[derive(serde::Deserialize, Debug)]
 /// Anubis PoW challenge descriptor.
 pub struct AnubisChallengeDescriptor {
@@ -118,7 +245,7 @@ impl AnubisChallengeDescriptor {
             else {
                 break;
             };
-            let mut solver = crate::DecimalSolver::from(message);
+            let mut solver = AnySolver::for_target(message, SOLVE_TYPE_LT, target, !0);
             solver.set_limit(remaining_limit);
             result = solver.solve::<{ SOLVE_TYPE_LT }>(target, !0);
             attempted_nonces += solver.get_attempted_nonces();
@@ -206,6 +333,628 @@ impl GoAwayConfig {
     }
 }
 
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+/// pow-shield style PoW challenge descriptor, also used by several CDN worker scripts.
+///
+/// Construct: Proof := ASCII_DECIMAL(nonce)
+/// where the hex digest of SHA-256(prefix || ASCII_DECIMAL(nonce)) starts with `difficulty` zeroes.
+pub struct PowShieldChallengeDescriptor {
+    /// The prefix the nonce is appended to. (JSON key: `prefix`)
+    pub prefix: String,
+    /// The number of leading zero hex characters. (JSON key: `difficulty`)
+    pub difficulty: u8,
+}
+
+impl PowShieldChallengeDescriptor {
+    /// If the pow-shield PoW is supported.
+    pub fn supported(&self) -> bool {
+        self.difficulty <= 16
+    }
+
+    /// Estimate the workload of a pow-shield PoW.
+    pub fn estimated_workload(&self) -> u64 {
+        16u64.saturating_pow(self.difficulty as u32)
+    }
+
+    /// Solve a pow-shield PoW.
+    pub fn solve(&self) -> (Option<u64>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// Solve a pow-shield PoW with a limit.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<u64>, u64) {
+        crate::scheme::solve_with_limit(self, limit)
+    }
+}
+
+impl PowScheme for PowShieldChallengeDescriptor {
+    /// The nonce.
+    type Proof = u64;
+
+    const SOLVE_TYPE: u8 = SOLVE_TYPE_LT;
+
+    fn build_prefix(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.prefix.as_bytes());
+    }
+
+    fn target(&self) -> Option<(u64, u64)> {
+        self.supported().then(|| {
+            (
+                1u64.checked_shl(64 - self.difficulty as u32 * 4)
+                    .unwrap_or(u64::MAX),
+                !0,
+            )
+        })
+    }
+
+    fn encode_proof(&self, nonce: u64, _hash: [u32; 8]) -> u64 {
+        nonce
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+/// ALTCHA PoW challenge descriptor.
+pub struct AltchaChallengeDescriptor {
+    /// The hash algorithm, `SHA-256` or `SHA-512`. (JSON key: `algorithm`)
+    pub algorithm: String,
+    /// The hex digest of the salt followed by the secret number. (JSON key: `challenge`)
+    pub challenge: String,
+    /// The largest secret number. (JSON key: `maxnumber`)
+    #[serde(
+        rename = "maxnumber",
+        alias = "maxNumber",
+        default = "altcha_default_max_number"
+    )]
+    pub max_number: u64,
+    /// The salt, including any signed parameters. (JSON key: `salt`)
+    pub salt: String,
+    /// The HMAC signature of the challenge, passed back unchanged. (JSON key: `signature`)
+    pub signature: String,
+}
+
+fn altcha_default_max_number() -> u64 {
+    1_000_000
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+/// ALTCHA PoW solution payload.
+pub struct AltchaPayload {
+    /// The hash algorithm.
+    pub algorithm: String,
+    /// The challenge digest.
+    pub challenge: String,
+    /// The secret number.
+    pub number: u64,
+    /// The salt.
+    pub salt: String,
+    /// The signature.
+    pub signature: String,
+    /// The solve time in milliseconds.
+    pub took: u64,
+}
+
+impl AltchaPayload {
+    /// Encode the payload the way the ALTCHA widget submits it, as base64 encoded JSON.
+    pub fn to_base64(&self) -> String {
+        encode_base64(&serde_json::to_vec(self).expect("payload is serializable"))
+    }
+}
+
+impl AltchaChallengeDescriptor {
+    /// If the ALTCHA PoW is supported.
+    pub fn supported(&self) -> bool {
+        self.algorithm == "SHA-256" || self.algorithm == "SHA-512"
+    }
+
+    /// Estimate the workload of an ALTCHA PoW.
+    pub fn estimated_workload(&self) -> u64 {
+        self.max_number / 2 + 1
+    }
+
+    /// Solve an ALTCHA PoW.
+    pub fn solve(&self) -> (Option<AltchaPayload>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// Solve an ALTCHA PoW with a limit.
+    ///
+    /// The secret number is a plain decimal in `0..=maxnumber` rather than a nonce of our choice,
+    /// so the numbers are searched in order with the salt hashed once instead of with the SIMD solvers.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<AltchaPayload>, u64) {
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let (number, attempted_nonces) = match self.algorithm.as_str() {
+            "SHA-256" => self.search::<sha2::Sha256>(limit),
+            "SHA-512" => self.search::<sha2::Sha512>(limit),
+            _ => (None, 0),
+        };
+        let payload = number.map(|number| AltchaPayload {
+            algorithm: self.algorithm.clone(),
+            challenge: self.challenge.clone(),
+            number,
+            salt: self.salt.clone(),
+            signature: self.signature.clone(),
+            #[cfg(feature = "std")]
+            took: start.elapsed().as_millis() as u64,
+            #[cfg(not(feature = "std"))]
+            took: 0,
+        });
+        (payload, attempted_nonces)
+    }
+
+    fn search<D: Digest + Clone>(&self, limit: u64) -> (Option<u64>, u64) {
+        let Some(expected) = decode_hex(&self.challenge) else {
+            return (None, 0);
+        };
+        if limit == 0 {
+            return (None, 0);
+        }
+        let mut salted = D::new();
+        salted.update(self.salt.as_bytes());
+
+        let mut digits = [0u8; 20];
+        let end = self.max_number.min(limit - 1);
+        for number in 0..=end {
+            let mut hasher = salted.clone();
+            hasher.update(format_decimal(&mut digits, number));
+            if hasher.finalize().as_slice() == expected.as_slice() {
+                return (Some(number), number + 1);
+            }
+        }
+        (None, end + 1)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Friendly Captcha (v1) puzzle, solved with BLAKE2b.
+///
+/// Construct: for each sub-puzzle i, Solution_i := (puzzle || 0* || i || 0 0 0 || LE32(nonce))[120..128]
+/// where the input is 128 bytes and the first 4 bytes of BLAKE2b-256(input) as a little endian
+/// integer are below the threshold of the difficulty.
+pub struct FriendlyCaptchaPuzzle {
+    signature: String,
+    puzzle: String,
+    buffer: Vec<u8>,
+}
+
+impl FriendlyCaptchaPuzzle {
+    /// Parse a `signature.base64_puzzle` string as returned by the puzzle endpoint.
+    pub fn parse(puzzle: &str) -> Option<Self> {
+        let (signature, encoded) = puzzle.split_once('.')?;
+        let buffer = decode_base64(encoded)?;
+        if buffer.len() < 32 || buffer.len() > 120 {
+            return None;
+        }
+        Some(Self {
+            signature: signature.to_string(),
+            puzzle: encoded.to_string(),
+            buffer,
+        })
+    }
+
+    /// Get the decoded puzzle buffer.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Get the number of sub-puzzles to solve.
+    pub fn number_of_solutions(&self) -> u8 {
+        self.buffer[14]
+    }
+
+    /// Get the difficulty of every sub-puzzle.
+    pub fn difficulty(&self) -> u8 {
+        self.buffer[15]
+    }
+
+    /// Get the threshold the hash of a solution must be below, `2^((255.999 - difficulty) / 8)`.
+    pub fn threshold(&self) -> u32 {
+        let exponent = (255.999 - self.difficulty() as f64) / 8.0;
+        let whole = exponent as u32;
+        // 2^fraction by the Taylor series of e^(fraction * ln 2), no libm in no_std
+        let y = (exponent - whole as f64) * core::f64::consts::LN_2;
+        let (mut term, mut fraction) = (1.0, 1.0);
+        for n in 1..20 {
+            term *= y / n as f64;
+            fraction += term;
+        }
+        (fraction * (1u64 << whole) as f64) as u32
+    }
+
+    /// Estimate the workload of a Friendly Captcha puzzle.
+    pub fn estimated_workload(&self) -> u64 {
+        (1u64 << 32) / (self.threshold() as u64).max(1) * self.number_of_solutions() as u64
+    }
+
+    /// The 128 byte input of a sub-puzzle with a zero nonce.
+    fn input(&self, index: u8) -> [u8; 128] {
+        let mut input = [0; 128];
+        input[..self.buffer.len()].copy_from_slice(&self.buffer);
+        input[120] = index;
+        input
+    }
+
+    /// Check the 8 byte solution of a sub-puzzle.
+    pub fn is_valid_solution(&self, index: u8, solution: &[u8; 8]) -> bool {
+        let mut input = self.input(index);
+        if solution[..4] != input[120..124] {
+            return false;
+        }
+        input[124..].copy_from_slice(&solution[4..]);
+        let hash = crate::blake2b::digest(&input, 32);
+        (hash[0] as u32) < self.threshold()
+    }
+
+    /// Solve a Friendly Captcha puzzle.
+    pub fn solve(&self) -> (Option<String>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// Solve a Friendly Captcha puzzle with a limit.
+    ///
+    /// Returns the `signature.puzzle.solutions.diagnostics` string the widget submits.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<String>, u64) {
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let threshold = self.threshold();
+        let mut solutions = Vec::with_capacity(self.number_of_solutions() as usize * 8);
+        let mut attempted_nonces = 0;
+        for index in 0..self.number_of_solutions() {
+            let input = self.input(index);
+            let (nonce, attempted) = search_blake2b(
+                &crate::blake2b::le_words(&input),
+                threshold,
+                limit.saturating_sub(attempted_nonces),
+            );
+            attempted_nonces += attempted;
+            let Some(nonce) = nonce else {
+                return (None, attempted_nonces);
+            };
+            solutions.extend_from_slice(&input[120..124]);
+            solutions.extend_from_slice(&nonce.to_le_bytes());
+        }
+
+        #[cfg(feature = "std")]
+        let elapsed = start.elapsed().as_secs().min(u16::MAX as u64) as u16;
+        #[cfg(not(feature = "std"))]
+        let elapsed = 0u16;
+        // solver ID 2 is the WebAssembly solver
+        let [high, low] = elapsed.to_be_bytes();
+        let diagnostics = [2, high, low];
+        (
+            Some(alloc::format!(
+                "{}.{}.{}.{}",
+                self.signature,
+                self.puzzle,
+                encode_base64(&solutions),
+                encode_base64(&diagnostics)
+            )),
+            attempted_nonces,
+        )
+    }
+}
+
+/// search the nonce in the high half of the last word whose hash is below the threshold
+#[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+fn search_blake2b(words: &[u64; 16], threshold: u32, limit: u64) -> (Option<u32>, u64) {
+    use core::arch::x86_64::*;
+
+    let initial_state = crate::blake2b::initial_state(32);
+    let mut attempted_nonces = 0;
+    unsafe {
+        let mut block: [__m512i; 16] = core::array::from_fn(|i| _mm512_set1_epi64(words[i] as _));
+        let lane_nonces = _mm512_slli_epi64(_mm512_setr_epi64(0, 1, 2, 3, 4, 5, 6, 7), 32);
+        let low_mask = _mm512_set1_epi64(u32::MAX as _);
+        let threshold = _mm512_set1_epi64(threshold as _);
+        for base in (0..=u32::MAX as u64).step_by(8) {
+            if attempted_nonces >= limit {
+                break;
+            }
+            attempted_nonces += 8;
+            block[15] = _mm512_add_epi64(
+                _mm512_set1_epi64((words[15] | base << 32) as _),
+                lane_nonces,
+            );
+            let mut state: [__m512i; 8] =
+                core::array::from_fn(|i| _mm512_set1_epi64(initial_state[i] as _));
+            crate::blake2b::avx512::multiway_compress(&mut state, &block, 128, true);
+            let met = _mm512_cmplt_epu64_mask(_mm512_and_si512(state[0], low_mask), threshold);
+            if met != 0 {
+                return (Some(base as u32 + met.trailing_zeros()), attempted_nonces);
+            }
+        }
+    }
+    (None, attempted_nonces)
+}
+
+/// search the nonce in the high half of the last word whose hash is below the threshold
+#[cfg(not(all(target_arch = "x86_64", target_feature = "avx512f")))]
+fn search_blake2b(words: &[u64; 16], threshold: u32, limit: u64) -> (Option<u32>, u64) {
+    let mut block = *words;
+    let mut attempted_nonces = 0;
+    for nonce in 0..=u32::MAX {
+        if attempted_nonces >= limit {
+            break;
+        }
+        attempted_nonces += 1;
+        block[15] = words[15] | (nonce as u64) << 32;
+        let mut state = crate::blake2b::initial_state(32);
+        crate::blake2b::compress(&mut state, &block, 128, true);
+        if (state[0] as u32) < threshold {
+            return (Some(nonce), attempted_nonces);
+        }
+    }
+    (None, attempted_nonces)
+}
+
+/// The cookie haproxy-protection checks the PoW answer in.
+pub const HAPROXY_PROTECTION_POW_COOKIE: &str = "_basedflare_pow";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// haproxy-protection PoW hash mode.
+pub enum HaproxyProtectionMode {
+    /// SHA-256 of the challenge and the nonce.
+    Sha256,
+    /// Argon2id of the challenge and the nonce, salted with the user key.
+    Argon2 {
+        /// The number of passes. (attribute: `data-time`)
+        time: u32,
+        /// The memory size in KiB. (attribute: `data-kb`)
+        memory_kib: u32,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// haproxy-protection (BasedFlare) bot wall PoW challenge.
+///
+/// Construct: Response := challenge || "#" || signature || "#" || ASCII_DECIMAL(nonce)
+/// where SHA-256(challenge || ASCII_DECIMAL(nonce)), or Argon2id with the user key as the salt
+/// and a 32 byte tag, starts with `difficulty` zero bits.
+pub struct HaproxyProtectionChallenge {
+    /// The user key, used as the Argon2 salt.
+    pub user_key: String,
+    /// The challenge.
+    pub challenge: String,
+    /// The signature of the challenge, passed back unchanged.
+    pub signature: String,
+    /// The required number of leading zero bits. (attribute: `data-diff`)
+    pub difficulty: u32,
+    /// The hash mode. (attribute: `data-mode`)
+    pub mode: HaproxyProtectionMode,
+}
+
+impl HaproxyProtectionChallenge {
+    /// Parse the data attributes of the bot check page.
+    ///
+    /// `pow` is the `userkey#challenge#signature` triple in `data-pow`, `time` and `kb` are only
+    /// read in `argon2` mode.
+    pub fn from_data_attributes(
+        pow: &str,
+        diff: &str,
+        mode: &str,
+        time: Option<&str>,
+        kb: Option<&str>,
+    ) -> Option<Self> {
+        let mut parts = pow.split('#');
+        let (Some(user_key), Some(challenge), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        let mode = match mode {
+            "sha256" => HaproxyProtectionMode::Sha256,
+            "argon2" => HaproxyProtectionMode::Argon2 {
+                time: time?.parse().ok()?,
+                memory_kib: kb?.parse().ok()?,
+            },
+            _ => return None,
+        };
+        Some(Self {
+            user_key: user_key.to_string(),
+            challenge: challenge.to_string(),
+            signature: signature.to_string(),
+            difficulty: diff.parse().ok()?,
+            mode,
+        })
+    }
+
+    /// If the haproxy-protection PoW is supported.
+    pub fn supported(&self) -> bool {
+        self.difficulty <= 64
+    }
+
+    /// Estimate the workload of a haproxy-protection PoW.
+    pub fn estimated_workload(&self) -> u64 {
+        1u64.checked_shl(self.difficulty).unwrap_or(u64::MAX)
+    }
+
+    /// Solve a haproxy-protection PoW.
+    pub fn solve(&self) -> (Option<u64>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// Solve a haproxy-protection PoW with a limit.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<u64>, u64) {
+        match self.argon2_challenge() {
+            Some(challenge) => challenge.solve_with_limit(limit),
+            None => crate::scheme::solve_with_limit(self, limit),
+        }
+    }
+
+    /// The Argon2id search of a supported Argon2 mode PoW.
+    pub fn argon2_challenge(&self) -> Option<crate::argon2::Argon2Challenge> {
+        let HaproxyProtectionMode::Argon2 { time, memory_kib } = self.mode else {
+            return None;
+        };
+        self.supported().then(|| crate::argon2::Argon2Challenge {
+            params: crate::argon2::Params {
+                time,
+                memory_kib,
+                parallelism: 1,
+            },
+            salt: self.user_key.as_bytes().to_vec(),
+            prefix: self.challenge.as_bytes().to_vec(),
+            tag_length: 32,
+            solve_type: SOLVE_TYPE_LT,
+            target: self.leading_zero_target(),
+        })
+    }
+
+    /// The top 64 bits of a hash with `difficulty` leading zero bits are below this.
+    fn leading_zero_target(&self) -> u64 {
+        1u64.checked_shl(64 - self.difficulty).unwrap_or(u64::MAX)
+    }
+
+    /// The `pow_response` value submitted to the bot check endpoint.
+    pub fn response(&self, nonce: u64) -> String {
+        alloc::format!("{}#{}#{}", self.challenge, self.signature, nonce)
+    }
+
+    /// The form body posted to the bot check endpoint.
+    pub fn form_body(&self, nonce: u64) -> String {
+        alloc::format!("pow_response={}", self.response(nonce).replace('#', "%23"))
+    }
+
+    /// The `name=value` pair of the PoW cookie.
+    pub fn cookie(&self, nonce: u64) -> String {
+        alloc::format!("{}={}", HAPROXY_PROTECTION_POW_COOKIE, self.response(nonce))
+    }
+
+    /// The number of leading zero bits of the hash of a nonce.
+    pub fn leading_zero_bits(&self, nonce: u64) -> u32 {
+        let mut digits = [0u8; 20];
+        let digits = format_decimal(&mut digits, nonce);
+        let mut hash = [0u8; 32];
+        match self.mode {
+            HaproxyProtectionMode::Sha256 => {
+                let mut hasher = sha2::Sha256::new();
+                hasher.update(self.challenge.as_bytes());
+                hasher.update(digits);
+                hash.copy_from_slice(&hasher.finalize());
+            }
+            HaproxyProtectionMode::Argon2 { time, memory_kib } => {
+                let mut password = Vec::with_capacity(self.challenge.len() + digits.len());
+                password.extend_from_slice(self.challenge.as_bytes());
+                password.extend_from_slice(digits);
+                crate::argon2::hash(
+                    &crate::argon2::Params {
+                        time,
+                        memory_kib,
+                        parallelism: 1,
+                    },
+                    &password,
+                    self.user_key.as_bytes(),
+                    &mut hash,
+                );
+            }
+        }
+        let mut bits = 0;
+        for byte in hash {
+            bits += byte.leading_zeros();
+            if byte != 0 {
+                break;
+            }
+        }
+        bits
+    }
+}
+
+impl PowScheme for HaproxyProtectionChallenge {
+    /// The nonce.
+    type Proof = u64;
+
+    const SOLVE_TYPE: u8 = SOLVE_TYPE_LT;
+
+    fn build_prefix(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.challenge.as_bytes());
+    }
+
+    /// Only the SHA-256 mode is searched by the decimal nonce solvers.
+    fn target(&self) -> Option<(u64, u64)> {
+        match self.mode {
+            HaproxyProtectionMode::Sha256 if self.supported() => {
+                Some((self.leading_zero_target(), !0))
+            }
+            _ => None,
+        }
+    }
+
+    fn encode_proof(&self, nonce: u64, _hash: [u32; 8]) -> u64 {
+        nonce
+    }
+
+    fn is_valid(&self, nonce: u64) -> bool {
+        self.supported() && self.leading_zero_bits(nonce) >= self.difficulty
+    }
+}
+
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let input = input.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut len = 0;
+    for &c in input {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | value as u32;
+        len += 6;
+        if len >= 8 {
+            len -= 8;
+            out.push((bits >> len) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn format_decimal(buf: &mut [u8; 20], mut value: u64) -> &[u8] {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            return &buf[start..];
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn encode_base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
 #[derive(serde::Deserialize, Debug, Clone, Copy)]
 /// Cap.js PoW challenge rules.
 pub struct CapJsChallengeRules {
@@ -298,15 +1047,18 @@ impl CapJsChallengeDescriptor {
                     let mut targets = [0; 2];
                     emitter.emit(&mut salt_buf, &mut targets, i as u32 + 1);
                     let mask = !0 << (64 - self.rules.difficulty as u64 * 4);
+                    let target = (targets[0] as u64) << 32 | targets[1] as u64;
                     let (message, fixup_prefix) =
                         DecimalMessage::new_f64(&salt_buf, 0).expect("solver is None");
-                    let mut solver = DecimalSolver::from(message);
+                    let mut solver = AnySolver::for_target(
+                        message,
+                        crate::solver::SOLVE_TYPE_MASK,
+                        target,
+                        mask,
+                    );
                     solver.set_limit(limit_per_challenge);
-                    let Some(nonce) = solver
-                        .solve_nonce_only::<{ crate::solver::SOLVE_TYPE_MASK }>(
-                            (targets[0] as u64) << 32 | targets[1] as u64,
-                            mask,
-                        )
+                    let Some(nonce) =
+                        solver.solve_nonce_only::<{ crate::solver::SOLVE_TYPE_MASK }>(target, mask)
                     else {
                         return;
                     };
@@ -355,14 +1107,15 @@ impl CapJsChallengeDescriptor {
             let mut targets = [0; 2];
             emitter.emit(&mut salt_buf, &mut targets, i as u32 + 1);
             let mask = !0 << (64 - self.rules.difficulty as u64 * 4);
+            let target = (targets[0] as u64) << 32 | targets[1] as u64;
             let (message, fixup_prefix) =
                 DecimalMessage::new_f64(&salt_buf, 0).expect("solver is None");
-            let mut solver = DecimalSolver::from(message);
+            let mut solver =
+                AnySolver::for_target(message, crate::solver::SOLVE_TYPE_MASK, target, mask);
             solver.set_limit(limit.saturating_sub(attempted_nonces));
-            let Some(nonce) = solver.solve_nonce_only::<{ crate::solver::SOLVE_TYPE_MASK }>(
-                (targets[0] as u64) << 32 | targets[1] as u64,
-                mask,
-            ) else {
+            let Some(nonce) =
+                solver.solve_nonce_only::<{ crate::solver::SOLVE_TYPE_MASK }>(target, mask)
+            else {
                 return (None, attempted_nonces);
             };
             attempted_nonces += solver.get_attempted_nonces();
@@ -404,3 +1157,454 @@ pub struct CapJsRedeemedToken {
     /// The expiration time.
     pub expires: u64,
 }
+
+#[derive(Debug)]
+/// A challenge of any supported scheme, detected from its raw payload.
+pub enum DetectedChallenge {
+    /// An mCaptcha PoW.
+    Mcaptcha(McaptchaChallengeDescriptor),
+    /// An Anubis PoW.
+    Anubis(AnubisChallengeDescriptor),
+    /// A GoAway "js-pow-sha256" PoW.
+    GoAway(GoAwayConfig),
+    /// An ALTCHA PoW.
+    Altcha(AltchaChallengeDescriptor),
+    /// A Cap.js PoW.
+    CapJs(CapJsChallengeDescriptor),
+    /// A pow-shield style PoW.
+    PowShield(PowShieldChallengeDescriptor),
+    /// A Friendly Captcha puzzle.
+    FriendlyCaptcha(FriendlyCaptchaPuzzle),
+    /// A hashcash stamp without its counter.
+    Hashcash(crate::hashcash::HashcashStamp),
+}
+
+impl DetectedChallenge {
+    /// Detect the scheme of a challenge payload.
+    ///
+    /// JSON objects are told apart by the keys only their scheme uses, anything surrounding the
+    /// outermost braces is ignored. Other payloads are tried as a hashcash stamp, then as a
+    /// Friendly Captcha puzzle.
+    pub fn detect(payload: &str) -> Option<Self> {
+        let payload = payload.trim();
+        if let (Some(start), Some(end)) = (payload.find('{'), payload.rfind('}')) {
+            let value: serde_json::Value = serde_json::from_str(payload.get(start..=end)?).ok()?;
+            return Self::detect_json(value);
+        }
+
+        if let Some(stamp) = crate::hashcash::HashcashStamp::parse(payload) {
+            return Some(Self::Hashcash(stamp));
+        }
+        FriendlyCaptchaPuzzle::parse(payload).map(Self::FriendlyCaptcha)
+    }
+
+    fn detect_json(value: serde_json::Value) -> Option<Self> {
+        let object = value.as_object()?;
+        let has = |key: &str| object.contains_key(key);
+
+        if has("salt") && (has("difficulty_factor") || has("phrase")) {
+            serde_json::from_value(value).ok().map(Self::Mcaptcha)
+        } else if has("challenge") && has("rules") {
+            serde_json::from_value(value).ok().map(Self::Anubis)
+        } else if has("algorithm") && has("signature") {
+            serde_json::from_value(value).ok().map(Self::Altcha)
+        } else if has("token") && object.get("challenge").is_some_and(|c| c.is_object()) {
+            serde_json::from_value(value).ok().map(Self::CapJs)
+        } else if has("challenge") && has("difficulty") {
+            serde_json::from_value(value).ok().map(Self::GoAway)
+        } else if has("prefix") && has("difficulty") {
+            serde_json::from_value(value).ok().map(Self::PowShield)
+        } else if has("salt") && has("string") {
+            serde_json::from_value(value).ok().map(Self::Mcaptcha)
+        } else {
+            None
+        }
+    }
+
+    /// The name of the detected scheme.
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            Self::Mcaptcha(_) => "mcaptcha",
+            Self::Anubis(_) => "anubis",
+            Self::GoAway(_) => "goaway",
+            Self::Altcha(_) => "altcha",
+            Self::CapJs(_) => "capjs",
+            Self::PowShield(_) => "pow-shield",
+            Self::FriendlyCaptcha(_) => "friendly-captcha",
+            Self::Hashcash(_) => "hashcash",
+        }
+    }
+
+    /// If the detected PoW is supported.
+    pub fn supported(&self) -> bool {
+        match self {
+            Self::Anubis(descriptor) => descriptor.supported(),
+            Self::Altcha(descriptor) => descriptor.supported(),
+            Self::PowShield(descriptor) => descriptor.supported(),
+            Self::Hashcash(stamp) => stamp.bits <= 64,
+            _ => true,
+        }
+    }
+
+    /// Estimate the workload of the detected PoW.
+    pub fn estimated_workload(&self) -> u64 {
+        match self {
+            Self::Mcaptcha(descriptor) => descriptor.estimated_workload(),
+            Self::Anubis(descriptor) => descriptor.estimated_workload(),
+            Self::GoAway(config) => config.estimated_workload(),
+            Self::Altcha(descriptor) => descriptor.estimated_workload(),
+            Self::CapJs(descriptor) => descriptor.estimated_workload(),
+            Self::PowShield(descriptor) => descriptor.estimated_workload(),
+            Self::FriendlyCaptcha(puzzle) => puzzle.estimated_workload(),
+            Self::Hashcash(stamp) => stamp.estimated_workload(),
+        }
+    }
+
+    /// Solve the detected PoW.
+    pub fn solve(self) -> (Option<String>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// Solve the detected PoW with a limit.
+    ///
+    /// Returns the proof in the form its verifier takes:
+    /// - mCaptcha: JSON with the `string`, `nonce` and `result`
+    /// - Anubis: JSON with the `nonce` and the hex digest under [`AnubisChallengeDescriptor::hash_result_key`]
+    /// - GoAway: the `__goaway_token`, the challenge followed by the hex nonce
+    /// - ALTCHA: the base64 encoded payload
+    /// - Cap.js: the JSON body of the redeem request
+    /// - pow-shield: the decimal nonce
+    /// - Friendly Captcha: the solution string
+    /// - hashcash: the minted stamp
+    pub fn solve_with_limit(self, limit: u64) -> (Option<String>, u64) {
+        match self {
+            Self::Mcaptcha(descriptor) => {
+                let (proof, attempted_nonces) = crate::scheme::solve_with_limit(&descriptor, limit);
+                (
+                    proof.map(|(nonce, result)| {
+                        serde_json::json!({
+                            "string": descriptor.string,
+                            "nonce": nonce,
+                            "result": result.to_string(),
+                        })
+                        .to_string()
+                    }),
+                    attempted_nonces,
+                )
+            }
+            Self::Anubis(descriptor) => {
+                let (result, attempted_nonces) = descriptor.solve_with_limit(limit);
+                (
+                    result.map(|(nonce, hash)| {
+                        let mut hex = [0u8; 64];
+                        crate::encode_hex(&mut hex, hash);
+                        let mut proof = serde_json::Map::new();
+                        proof.insert("nonce".into(), nonce.into());
+                        proof.insert(
+                            descriptor.hash_result_key().into(),
+                            core::str::from_utf8(&hex).expect("hex is ASCII").into(),
+                        );
+                        serde_json::Value::Object(proof).to_string()
+                    }),
+                    attempted_nonces,
+                )
+            }
+            Self::GoAway(config) => {
+                let (result, attempted_nonces) = config.solve_with_limit(limit);
+                (
+                    result.map(|(nonce, _)| alloc::format!("{}{:016x}", config.challenge, nonce)),
+                    attempted_nonces,
+                )
+            }
+            Self::Altcha(descriptor) => {
+                let (payload, attempted_nonces) = descriptor.solve_with_limit(limit);
+                (payload.map(|payload| payload.to_base64()), attempted_nonces)
+            }
+            Self::CapJs(descriptor) => {
+                let (response, attempted_nonces) = descriptor.solve_with_limit(limit);
+                (
+                    response.map(|response| {
+                        serde_json::to_string(&response).expect("response is serializable")
+                    }),
+                    attempted_nonces,
+                )
+            }
+            Self::PowShield(descriptor) => {
+                let (nonce, attempted_nonces) = descriptor.solve_with_limit(limit);
+                (nonce.map(|nonce| nonce.to_string()), attempted_nonces)
+            }
+            Self::FriendlyCaptcha(puzzle) => puzzle.solve_with_limit(limit),
+            Self::Hashcash(stamp) => stamp.mint_with_limit(limit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mcaptcha_verify() {
+        let descriptor = McaptchaChallengeDescriptor {
+            salt: "x".repeat(32),
+            string: "hello".into(),
+            difficulty_factor: 50_000,
+        };
+        let (Some((nonce, result)), _) = descriptor.solve() else {
+            panic!("solver failed");
+        };
+        let verification = descriptor.verify(nonce);
+        assert_eq!(verification.result, crate::extract128_be(result));
+        assert!(verification.is_sufficient());
+
+        let config = pow_sha256::Config {
+            salt: descriptor.salt.clone(),
+        };
+        let proof = pow_sha256::PoWBuilder::default()
+            .nonce(nonce)
+            .result(verification.result.to_string())
+            .build()
+            .unwrap();
+        assert!(config.is_valid_proof(&proof, &descriptor.string));
+        assert!(config.is_sufficient_difficulty(&proof, descriptor.difficulty_factor));
+
+        let wrong = descriptor.verify(nonce + 1);
+        assert_ne!(wrong.result, verification.result);
+        assert!(descriptor.is_valid(nonce));
+        assert!(!descriptor.is_valid(nonce + 1));
+
+        let (proof, _) = crate::scheme::solve_with_limit(&descriptor, u64::MAX);
+        assert_eq!(proof, Some((nonce, verification.result)));
+    }
+
+    #[test]
+    fn test_pow_shield_solve() {
+        let challenge: PowShieldChallengeDescriptor =
+            serde_json::from_str(r#"{"prefix":"8f2c1a7e","difficulty":4}"#).unwrap();
+        assert!(challenge.supported());
+        assert_eq!(challenge.estimated_workload(), 65536);
+        let (Some(nonce), attempted) = challenge.solve() else {
+            panic!("solver failed");
+        };
+        assert!(attempted > 0);
+        assert!(challenge.is_valid(nonce));
+        let hash = sha2::Sha256::digest(format!("8f2c1a7e{}", nonce));
+        assert_eq!(hash[..2], [0, 0]);
+
+        let unsupported = PowShieldChallengeDescriptor {
+            prefix: String::new(),
+            difficulty: 17,
+        };
+        assert_eq!(unsupported.solve(), (None, 0));
+    }
+
+    #[test]
+    fn test_altcha_solve() {
+        let salt = "46ab0d9ba1d6c5ef8d7a1ae6?expires=1700000000";
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(format!("{}{}", salt, 12345));
+        let challenge: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let descriptor: AltchaChallengeDescriptor = serde_json::from_value(serde_json::json!({
+            "algorithm": "SHA-256",
+            "challenge": challenge,
+            "maxnumber": 50_000,
+            "salt": salt,
+            "signature": "abcd",
+        }))
+        .unwrap();
+        assert!(descriptor.supported());
+
+        let (Some(payload), attempted) = descriptor.solve() else {
+            panic!("solver failed");
+        };
+        assert_eq!(payload.number, 12345);
+        assert_eq!(attempted, 12346);
+        assert_eq!(payload.signature, "abcd");
+
+        assert!(descriptor.solve_with_limit(12345).0.is_none());
+        let out_of_range = AltchaChallengeDescriptor {
+            max_number: 10_000,
+            ..descriptor
+        };
+        assert_eq!(out_of_range.solve().1, 10_001);
+        assert!(out_of_range.solve().0.is_none());
+    }
+
+    #[test]
+    fn test_friendly_captcha_solve() {
+        let mut buffer = [0u8; 32];
+        buffer[..4].copy_from_slice(&1_700_000_000u32.to_be_bytes());
+        buffer[14] = 3;
+        buffer[15] = 120;
+        let puzzle =
+            FriendlyCaptchaPuzzle::parse(&format!("signature.{}", encode_base64(&buffer))).unwrap();
+        assert_eq!(puzzle.number_of_solutions(), 3);
+        assert_eq!(puzzle.threshold(), 131060);
+        assert!(FriendlyCaptchaPuzzle::parse("signature").is_none());
+        assert!(FriendlyCaptchaPuzzle::parse("signature.AAAA").is_none());
+
+        let (Some(response), attempted) = puzzle.solve() else {
+            panic!("solver failed");
+        };
+        assert!(attempted > 0);
+        let parts: Vec<_> = response.split('.').collect();
+        assert_eq!(parts[..2], ["signature", &encode_base64(&buffer)]);
+        let solutions = decode_base64(parts[2]).unwrap();
+        assert_eq!(solutions.len(), 3 * 8);
+        for (index, solution) in solutions.chunks_exact(8).enumerate() {
+            assert!(puzzle.is_valid_solution(index as u8, solution.try_into().unwrap()));
+        }
+        assert!(!puzzle.is_valid_solution(0, &[0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]));
+        assert_eq!(decode_base64(parts[3]).unwrap()[0], 2);
+        assert!(puzzle.solve_with_limit(1).0.is_none());
+    }
+
+    #[test]
+    fn test_haproxy_protection_solve() {
+        let sha256 = HaproxyProtectionChallenge::from_data_attributes(
+            "userkey#0123456789abcdef#signature",
+            "16",
+            "sha256",
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(sha256.mode, HaproxyProtectionMode::Sha256);
+        let (Some(nonce), attempted) = sha256.solve() else {
+            panic!("solver failed");
+        };
+        assert!(attempted > 0);
+        assert!(sha256.is_valid(nonce));
+        let hash = sha2::Sha256::digest(format!("0123456789abcdef{}", nonce));
+        assert_eq!(hash[..2], [0, 0]);
+        assert_eq!(
+            sha256.form_body(nonce),
+            format!("pow_response=0123456789abcdef%23signature%23{}", nonce)
+        );
+        assert_eq!(
+            sha256.cookie(nonce),
+            format!("_basedflare_pow=0123456789abcdef#signature#{}", nonce)
+        );
+
+        let argon2 = HaproxyProtectionChallenge::from_data_attributes(
+            "userkey#0123456789abcdef#signature",
+            "4",
+            "argon2",
+            Some("1"),
+            Some("8"),
+        )
+        .unwrap();
+        let (Some(nonce), _) = argon2.solve() else {
+            panic!("solver failed");
+        };
+        assert!(argon2.leading_zero_bits(nonce) >= 4);
+        assert!(argon2.solve_with_limit(0).0.is_none());
+
+        assert!(
+            HaproxyProtectionChallenge::from_data_attributes("a#b", "1", "sha256", None, None)
+                .is_none()
+        );
+        assert!(
+            HaproxyProtectionChallenge::from_data_attributes("a#b#c", "1", "argon2", None, None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_detect_challenge() {
+        let cases = [
+            (
+                r#"{"string":"hello","salt":"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx","difficulty_factor":5000}"#,
+                "mcaptcha",
+            ),
+            (
+                r#"{"challenge":"abcd","rules":{"algorithm":"fast","difficulty":2}}"#,
+                "anubis",
+            ),
+            (
+                r#"{"challenge":{"id":"x","randomData":"abcd"},"rules":{"algorithm":"slow","difficulty":2}}"#,
+                "anubis",
+            ),
+            (
+                r#"{"algorithm":"SHA-256","challenge":"ab","maxnumber":10,"salt":"s","signature":"x"}"#,
+                "altcha",
+            ),
+            (
+                r#"{"challenge":{"c":2,"s":32,"d":2},"token":"abcd"}"#,
+                "capjs",
+            ),
+            (r#"{"challenge":"ab","difficulty":8}"#, "goaway"),
+            (r#"{"prefix":"hello","difficulty":2}"#, "pow-shield"),
+            (
+                r#"const challenge = {"prefix":"hello","difficulty":2};"#,
+                "pow-shield",
+            ),
+            ("1:8:251016:example.com::c2FsdA:", "hashcash"),
+            (
+                "signature.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+                "friendly-captcha",
+            ),
+        ];
+        for (payload, scheme) in cases {
+            let challenge = DetectedChallenge::detect(payload)
+                .unwrap_or_else(|| panic!("failed to detect {}", payload));
+            assert_eq!(challenge.scheme(), scheme, "{}", payload);
+        }
+        assert!(DetectedChallenge::detect(r#"{"hello":"world"}"#).is_none());
+        assert!(DetectedChallenge::detect("hello").is_none());
+
+        let (Some(nonce), _) = DetectedChallenge::detect(r#"{"prefix":"hello","difficulty":2}"#)
+            .unwrap()
+            .solve()
+        else {
+            panic!("solver failed");
+        };
+        let hash = sha2::Sha256::digest(format!("hello{}", nonce).as_bytes());
+        assert_eq!(hash[0], 0);
+
+        let (Some(stamp), _) = DetectedChallenge::detect("1:12:251016:example.com::c2FsdA")
+            .unwrap()
+            .solve()
+        else {
+            panic!("solver failed");
+        };
+        assert!(stamp.starts_with("1:12:251016:example.com::c2FsdA:"));
+        assert!(crate::hashcash::is_valid(&stamp));
+
+        let (Some(proof), _) = DetectedChallenge::detect(
+            r#"{"string":"hello","salt":"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx","difficulty_factor":5000}"#,
+        )
+        .unwrap()
+        .solve() else {
+            panic!("solver failed");
+        };
+        let proof: serde_json::Value = serde_json::from_str(&proof).unwrap();
+        let descriptor = McaptchaChallengeDescriptor {
+            salt: "x".repeat(32),
+            string: "hello".into(),
+            difficulty_factor: 5000,
+        };
+        let verification = descriptor.verify(proof["nonce"].as_u64().unwrap());
+        assert!(verification.is_sufficient());
+        assert_eq!(
+            proof["result"].as_str().unwrap(),
+            verification.result.to_string()
+        );
+    }
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"h"), "aA==");
+        assert_eq!(encode_base64(b"he"), "aGU=");
+        assert_eq!(encode_base64(b"hello"), "aGVsbG8=");
+        assert_eq!(encode_base64(b"hello!"), "aGVsbG8h");
+        for input in [&b""[..], b"h", b"he", b"hello", b"hello!"] {
+            assert_eq!(decode_base64(&encode_base64(input)).as_deref(), Some(input));
+        }
+    }
+}
@@ -0,0 +1,516 @@
+//! Argon2id (RFC 9106, version 0x13) on top of the BLAKE2b core.
+//!
+//! With the `std` feature the lanes of every slice are filled on their own threads.
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    blake2b,
+    solver::{SOLVE_TYPE_GT, SOLVE_TYPE_LT},
+};
+
+/// the number of 64-bit words in a memory block
+const BLOCK_WORDS: usize = 128;
+
+/// the number of segments per lane and pass
+const SYNC_POINTS: usize = 4;
+
+/// the Argon2 version implemented
+const VERSION: u32 = 0x13;
+
+/// the Argon2id type
+const TYPE_ID: u32 = 2;
+
+type Block = [u64; BLOCK_WORDS];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Argon2id cost parameters
+pub struct Params {
+    /// the number of passes over memory
+    pub time: u32,
+    /// the memory size in KiB
+    pub memory_kib: u32,
+    /// the number of lanes
+    pub parallelism: u32,
+}
+
+impl Params {
+    /// the number of 1 KiB blocks actually used
+    pub fn blocks(&self) -> usize {
+        let lanes = self.parallelism.max(1) as usize;
+        let memory = (self.memory_kib as usize).max(2 * SYNC_POINTS * lanes);
+        memory / (SYNC_POINTS * lanes) * SYNC_POINTS * lanes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An Argon2id PoW whose proof is a decimal nonce appended to a fixed password prefix.
+///
+/// Construct: Proof := ASCII_DECIMAL(nonce)
+/// where the top 64 bits of Argon2id(prefix || ASCII_DECIMAL(nonce), salt) satisfy the target.
+pub struct Argon2Challenge {
+    /// the cost parameters
+    pub params: Params,
+    /// the salt
+    pub salt: Vec<u8>,
+    /// the password prefix the nonce is appended to
+    pub prefix: Vec<u8>,
+    /// the tag length in bytes, at least 8
+    pub tag_length: usize,
+    /// how the top 64 bits of the tag are compared against the target,
+    /// [`SOLVE_TYPE_LT`] or [`SOLVE_TYPE_GT`]
+    pub solve_type: u8,
+    /// the target of the top 64 bits of the tag
+    pub target: u64,
+}
+
+impl Argon2Challenge {
+    /// solve the challenge
+    pub fn solve(&self) -> (Option<u64>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// solve the challenge with a limit
+    ///
+    /// Every attempt is memory hard, so nonces are tried one at a time with the memory reused.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<u64>, u64) {
+        if self.tag_length < 8 {
+            return (None, 0);
+        }
+        let mut memory = Vec::new();
+        let mut tag = vec![0u8; self.tag_length];
+        let mut attempted_nonces = 0;
+        for nonce in 0..limit {
+            attempted_nonces += 1;
+            self.hash_nonce(nonce, &mut memory, &mut tag);
+            if self.meets_target(&tag) {
+                return (Some(nonce), attempted_nonces);
+            }
+        }
+        (None, attempted_nonces)
+    }
+
+    /// check a nonce against the challenge
+    pub fn is_valid(&self, nonce: u64) -> bool {
+        if self.tag_length < 8 {
+            return false;
+        }
+        let mut tag = vec![0u8; self.tag_length];
+        self.hash_nonce(nonce, &mut Vec::new(), &mut tag);
+        self.meets_target(&tag)
+    }
+
+    fn hash_nonce(&self, nonce: u64, memory: &mut Vec<Block>, tag: &mut [u8]) {
+        let mut password = self.prefix.clone();
+        password.extend_from_slice(alloc::string::ToString::to_string(&nonce).as_bytes());
+        hash_into(&self.params, &password, &self.salt, &[], &[], memory, tag);
+    }
+
+    fn meets_target(&self, tag: &[u8]) -> bool {
+        let value = u64::from_be_bytes(tag[..8].try_into().unwrap());
+        match self.solve_type {
+            SOLVE_TYPE_LT => value < self.target,
+            SOLVE_TYPE_GT => value > self.target,
+            _ => false,
+        }
+    }
+}
+
+/// compute the Argon2id tag of a password and salt, the tag length is the length of `out`
+pub fn hash(params: &Params, password: &[u8], salt: &[u8], out: &mut [u8]) {
+    hash_into(params, password, salt, &[], &[], &mut Vec::new(), out)
+}
+
+/// compute the Argon2id tag with a secret key and associated data, reusing `memory`
+fn hash_into(
+    params: &Params,
+    password: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    associated_data: &[u8],
+    memory: &mut Vec<Block>,
+    out: &mut [u8],
+) {
+    let lanes = params.parallelism.max(1) as usize;
+    let time = params.time.max(1) as usize;
+    let blocks = params.blocks();
+    let lane_length = blocks / lanes;
+    let segment_length = lane_length / SYNC_POINTS;
+
+    let mut h0 = Vec::with_capacity(64 + password.len() + salt.len());
+    for word in [
+        lanes as u32,
+        out.len() as u32,
+        params.memory_kib,
+        time as u32,
+        VERSION,
+        TYPE_ID,
+    ] {
+        h0.extend_from_slice(&word.to_le_bytes());
+    }
+    for field in [password, salt, secret, associated_data] {
+        h0.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        h0.extend_from_slice(field);
+    }
+    let h0 = digest_bytes(&h0, 64);
+
+    // every block is written before it is read, so stale contents need no clearing
+    memory.resize(blocks, [0; BLOCK_WORDS]);
+    for lane in 0..lanes {
+        for column in 0..2 {
+            let mut input = [0u8; 72];
+            input[..64].copy_from_slice(&h0[..64]);
+            input[64..68].copy_from_slice(&(column as u32).to_le_bytes());
+            input[68..].copy_from_slice(&(lane as u32).to_le_bytes());
+            let mut bytes = [0u8; 1024];
+            variable_hash(&input, &mut bytes);
+            for (word, chunk) in memory[lane * lane_length + column]
+                .iter_mut()
+                .zip(bytes.chunks_exact(8))
+            {
+                *word = u64::from_le_bytes(chunk.try_into().unwrap());
+            }
+        }
+    }
+
+    let geometry = Geometry {
+        lanes,
+        lane_length,
+        segment_length,
+        blocks,
+        time,
+    };
+    let shared = SharedMemory(memory.as_mut_ptr());
+    for pass in 0..time {
+        for slice in 0..SYNC_POINTS {
+            fill_slice(shared, pass, slice, geometry);
+        }
+    }
+
+    let mut last = memory[lane_length - 1];
+    for lane in 1..lanes {
+        for (word, other) in last
+            .iter_mut()
+            .zip(&memory[lane * lane_length + lane_length - 1])
+        {
+            *word ^= other;
+        }
+    }
+    let bytes: Vec<u8> = last.iter().flat_map(|word| word.to_le_bytes()).collect();
+    variable_hash(&bytes, out);
+}
+
+/// the memory shared by the lanes of a slice
+///
+/// Within a slice every lane writes only its own segment and reads only blocks outside the
+/// segments being written by other lanes, so lanes never touch a block another lane writes.
+#[derive(Clone, Copy)]
+struct SharedMemory(*mut Block);
+
+// SAFETY: see the access pattern above, the segments written in a slice are disjoint
+unsafe impl Send for SharedMemory {}
+unsafe impl Sync for SharedMemory {}
+
+impl SharedMemory {
+    /// SAFETY: no other lane may be writing the block
+    unsafe fn block(&self, index: usize) -> &Block {
+        unsafe { &*self.0.add(index) }
+    }
+
+    /// SAFETY: the block must be in the segment of the calling lane
+    unsafe fn write(&self, index: usize, block: Block) {
+        unsafe { self.0.add(index).write(block) }
+    }
+}
+
+/// fill the segments of every lane in a slice, one thread per lane
+#[cfg(feature = "std")]
+fn fill_slice(memory: SharedMemory, pass: usize, slice: usize, geometry: Geometry) {
+    if geometry.lanes == 1 {
+        fill_segment(
+            memory,
+            Position {
+                pass,
+                lane: 0,
+                slice,
+            },
+            geometry,
+        );
+        return;
+    }
+    std::thread::scope(|scope| {
+        for lane in 0..geometry.lanes {
+            scope.spawn(move || fill_segment(memory, Position { pass, lane, slice }, geometry));
+        }
+    });
+}
+
+/// fill the segments of every lane in a slice
+#[cfg(not(feature = "std"))]
+fn fill_slice(memory: SharedMemory, pass: usize, slice: usize, geometry: Geometry) {
+    for lane in 0..geometry.lanes {
+        fill_segment(memory, Position { pass, lane, slice }, geometry);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    pass: usize,
+    lane: usize,
+    slice: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Geometry {
+    lanes: usize,
+    lane_length: usize,
+    segment_length: usize,
+    blocks: usize,
+    time: usize,
+}
+
+fn fill_segment(memory: SharedMemory, position: Position, geometry: Geometry) {
+    let Position { pass, lane, slice } = position;
+    let Geometry {
+        lanes,
+        lane_length,
+        segment_length,
+        blocks,
+        time,
+    } = geometry;
+    // Argon2id uses data independent addressing for the first half of the first pass
+    let data_independent = pass == 0 && slice < SYNC_POINTS / 2;
+
+    let mut input: Block = [0; BLOCK_WORDS];
+    let mut addresses: Block = [0; BLOCK_WORDS];
+    if data_independent {
+        input[..6].copy_from_slice(&[
+            pass as u64,
+            lane as u64,
+            slice as u64,
+            blocks as u64,
+            time as u64,
+            TYPE_ID as u64,
+        ]);
+    }
+
+    let first = if pass == 0 && slice == 0 { 2 } else { 0 };
+    if data_independent && first != 0 {
+        next_addresses(&mut input, &mut addresses);
+    }
+
+    for index in first..segment_length {
+        let column = slice * segment_length + index;
+        let current = lane * lane_length + column;
+        let previous = if column == 0 {
+            current + lane_length - 1
+        } else {
+            current - 1
+        };
+
+        let pseudo_random = if data_independent {
+            if index % BLOCK_WORDS == 0 {
+                next_addresses(&mut input, &mut addresses);
+            }
+            addresses[index % BLOCK_WORDS]
+        } else {
+            // SAFETY: the previous block is in this segment or was written in an earlier slice
+            unsafe { memory.block(previous)[0] }
+        };
+
+        let ref_lane = if pass == 0 && slice == 0 {
+            lane
+        } else {
+            (pseudo_random >> 32) as usize % lanes
+        };
+        let same_lane = ref_lane == lane;
+        let reference_area = match (pass, same_lane) {
+            (0, true) => slice * segment_length + index - 1,
+            (0, false) => slice * segment_length - (index == 0) as usize,
+            (_, true) => lane_length - segment_length + index - 1,
+            (_, false) => lane_length - segment_length - (index == 0) as usize,
+        } as u64;
+        let x = (pseudo_random & 0xffffffff).pow(2) >> 32;
+        let y = (reference_area * x) >> 32;
+        let relative = reference_area - 1 - y;
+        let start = if pass == 0 || slice == SYNC_POINTS - 1 {
+            0
+        } else {
+            (slice + 1) * segment_length
+        };
+        let ref_column = (start + relative as usize) % lane_length;
+        let reference = ref_lane * lane_length + ref_column;
+
+        // SAFETY: the reference area excludes the segments other lanes are writing
+        let mut block = unsafe { compress(memory.block(previous), memory.block(reference)) };
+        if pass != 0 {
+            // SAFETY: the current block is in this segment
+            for (word, old) in block.iter_mut().zip(unsafe { memory.block(current) }) {
+                *word ^= old;
+            }
+        }
+        // SAFETY: the current block is in this segment
+        unsafe { memory.write(current, block) };
+    }
+}
+
+/// advance the address generator of data independent addressing
+fn next_addresses(input: &mut Block, addresses: &mut Block) {
+    input[6] += 1;
+    let zero = [0; BLOCK_WORDS];
+    *addresses = compress(&zero, &compress(&zero, input));
+}
+
+/// the compression function G of two blocks
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r: Block = core::array::from_fn(|i| x[i] ^ y[i]);
+    let saved = r;
+    for row in 0..8 {
+        let indices: [usize; 16] = core::array::from_fn(|i| row * 16 + i);
+        permute(&mut r, indices);
+    }
+    for column in 0..8 {
+        let indices: [usize; 16] = core::array::from_fn(|i| (i / 2) * 16 + column * 2 + i % 2);
+        permute(&mut r, indices);
+    }
+    for (word, saved) in r.iter_mut().zip(saved) {
+        *word ^= saved;
+    }
+    r
+}
+
+/// the BLAKE2b round function with multiplications applied to 16 words of a block
+#[inline(always)]
+fn permute(block: &mut Block, v: [usize; 16]) {
+    gb(block, v[0], v[4], v[8], v[12]);
+    gb(block, v[1], v[5], v[9], v[13]);
+    gb(block, v[2], v[6], v[10], v[14]);
+    gb(block, v[3], v[7], v[11], v[15]);
+    gb(block, v[0], v[5], v[10], v[15]);
+    gb(block, v[1], v[6], v[11], v[12]);
+    gb(block, v[2], v[7], v[8], v[13]);
+    gb(block, v[3], v[4], v[9], v[14]);
+}
+
+#[inline(always)]
+fn gb(v: &mut Block, a: usize, b: usize, c: usize, d: usize) {
+    let blamka = |x: u64, y: u64| {
+        x.wrapping_add(y).wrapping_add(
+            2u64.wrapping_mul(x & 0xffffffff)
+                .wrapping_mul(y & 0xffffffff),
+        )
+    };
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+fn digest_bytes(message: &[u8], out_len: usize) -> [u8; 64] {
+    let state = blake2b::digest(message, out_len);
+    let mut out = [0u8; 64];
+    for (chunk, word) in out.chunks_exact_mut(8).zip(state) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+/// the variable length hash function H'
+fn variable_hash(input: &[u8], out: &mut [u8]) {
+    let mut message = Vec::with_capacity(4 + input.len());
+    message.extend_from_slice(&(out.len() as u32).to_le_bytes());
+    message.extend_from_slice(input);
+    if out.len() <= 64 {
+        out.copy_from_slice(&digest_bytes(&message, out.len())[..out.len()]);
+        return;
+    }
+
+    let mut v = digest_bytes(&message, 64);
+    let mut offset = 0;
+    while out.len() - offset > 64 {
+        out[offset..offset + 32].copy_from_slice(&v[..32]);
+        offset += 32;
+        if out.len() - offset > 64 {
+            v = digest_bytes(&v, 64);
+        }
+    }
+    let remaining = out.len() - offset;
+    out[offset..].copy_from_slice(&digest_bytes(&v, remaining)[..remaining]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_argon2id_rfc9106() {
+        // Test vector from RFC 9106 section 5.3
+        let params = Params {
+            time: 3,
+            memory_kib: 32,
+            parallelism: 4,
+        };
+        let mut tag = [0u8; 32];
+        hash_into(
+            &params,
+            &[0x01; 32],
+            &[0x02; 16],
+            &[0x03; 8],
+            &[0x04; 12],
+            &mut Vec::new(),
+            &mut tag,
+        );
+        assert_eq!(
+            tag,
+            [
+                0x0d, 0x64, 0x0d, 0xf5, 0x8d, 0x78, 0x76, 0x6c, 0x08, 0xc0, 0x37, 0xa3, 0x4a, 0x8b,
+                0x53, 0xc9, 0xd0, 0x1e, 0xf0, 0x45, 0x2d, 0x75, 0xb6, 0x5e, 0xb5, 0x25, 0x20, 0xe9,
+                0x6b, 0x01, 0xe6, 0x59,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_argon2_challenge_solve() {
+        for (solve_type, target) in [
+            (SOLVE_TYPE_LT, 1 << 60),
+            (SOLVE_TYPE_GT, u64::MAX - (1 << 60)),
+        ] {
+            let challenge = Argon2Challenge {
+                params: Params {
+                    time: 1,
+                    memory_kib: 64,
+                    parallelism: 2,
+                },
+                salt: b"somesalt".to_vec(),
+                prefix: b"challenge".to_vec(),
+                tag_length: 32,
+                solve_type,
+                target,
+            };
+            let (Some(nonce), attempted) = challenge.solve() else {
+                panic!("solver failed");
+            };
+            assert_eq!(attempted, nonce + 1);
+            // the solver reuses memory between nonces, the check allocates afresh
+            assert!(challenge.is_valid(nonce));
+            let mut tag = [0u8; 32];
+            hash(
+                &challenge.params,
+                format!("challenge{}", nonce).as_bytes(),
+                b"somesalt",
+                &mut tag,
+            );
+            let value = u64::from_be_bytes(tag[..8].try_into().unwrap());
+            assert!(if solve_type == SOLVE_TYPE_LT {
+                value < target
+            } else {
+                value > target
+            });
+            assert!(challenge.solve_with_limit(0).0.is_none());
+        }
+    }
+}
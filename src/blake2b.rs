@@ -0,0 +1,144 @@
+#[cfg(all(target_arch = "x86_64", any(doc, target_feature = "avx512f")))]
+pub mod avx512;
+
+// Initialization vector for BLAKE2b, shared with SHA-512
+pub(crate) const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+// Message word permutations, rounds 10 and 11 reuse the first two
+pub(crate) const SIGMA: [[usize; 16]; 12] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+];
+
+/// the initial state for an unkeyed hash of `out_len` bytes
+pub(crate) const fn initial_state(out_len: usize) -> [u64; 8] {
+    let mut state = IV;
+    state[0] ^= 0x01010000 ^ out_len as u64;
+    state
+}
+
+/// A reference software implementation of BLAKE2b compression function
+///
+/// `counter` is the number of message bytes including this block, `last` marks the final block.
+#[inline(always)]
+pub(crate) const fn compress(state: &mut [u64; 8], block: &[u64; 16], counter: u128, last: bool) {
+    let mut v = [0u64; 16];
+    let mut i = 0;
+    while i < 8 {
+        v[i] = state[i];
+        v[i + 8] = IV[i];
+        i += 1;
+    }
+    v[12] ^= counter as u64;
+    v[13] ^= (counter >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+
+    let mut round = 0;
+    while round < 12 {
+        let s = &SIGMA[round];
+        g(&mut v, 0, 4, 8, 12, block[s[0]], block[s[1]]);
+        g(&mut v, 1, 5, 9, 13, block[s[2]], block[s[3]]);
+        g(&mut v, 2, 6, 10, 14, block[s[4]], block[s[5]]);
+        g(&mut v, 3, 7, 11, 15, block[s[6]], block[s[7]]);
+        g(&mut v, 0, 5, 10, 15, block[s[8]], block[s[9]]);
+        g(&mut v, 1, 6, 11, 12, block[s[10]], block[s[11]]);
+        g(&mut v, 2, 7, 8, 13, block[s[12]], block[s[13]]);
+        g(&mut v, 3, 4, 9, 14, block[s[14]], block[s[15]]);
+        round += 1;
+    }
+
+    let mut i = 0;
+    while i < 8 {
+        state[i] ^= v[i] ^ v[i + 8];
+        i += 1;
+    }
+}
+
+#[inline(always)]
+const fn g(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// the little endian message words of a block
+pub(crate) fn le_words(block: &[u8; 128]) -> [u64; 16] {
+    core::array::from_fn(|i| u64::from_le_bytes(block[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+/// hash a whole message with the reference implementation, the first `out_len` bytes of the state are the digest
+pub(crate) fn digest(message: &[u8], out_len: usize) -> [u64; 8] {
+    let mut state = initial_state(out_len);
+    let mut offset = 0;
+    while message.len() - offset > 128 {
+        let block = message[offset..offset + 128].try_into().unwrap();
+        offset += 128;
+        compress(&mut state, &le_words(block), offset as u128, false);
+    }
+    let mut block = [0u8; 128];
+    block[..message.len() - offset].copy_from_slice(&message[offset..]);
+    compress(&mut state, &le_words(&block), message.len() as u128, true);
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(state: [u64; 8], out_len: usize) -> String {
+        state
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .take(out_len)
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    #[test]
+    fn test_blake2b_digest() {
+        // Test vectors from RFC 7693 and the reference implementation
+        assert_eq!(
+            hex(digest(b"abc", 64), 64),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+        assert_eq!(
+            hex(digest(b"", 32), 32),
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+        assert_eq!(
+            hex(digest(&[b'a'; 128], 32), 32),
+            "ae2aa48507885c4c950fb809b2076f959cde9f8ea6da260d9a3587df33dac450"
+        );
+        assert_eq!(
+            hex(digest(&[b'a'; 300], 32), 32),
+            "3c1292de00a518e36823f9ff908ac2da46be38718c018713403461df077e15f6"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Multi-way BLAKE2b implementation for AVX-512.
+use core::arch::x86_64::*;
+
+use super::*;
+
+#[cfg_attr(
+    all(not(debug_assertions), not(test), target_feature = "avx512f"),
+    inline(always)
+)]
+/// Do an 8-way BLAKE2b compression function, one message per 64-bit lane
+///
+/// `counter` is the number of message bytes including this block, `last` marks the final block.
+pub(crate) fn multiway_compress(
+    state: &mut [__m512i; 8],
+    block: &[__m512i; 16],
+    counter: u128,
+    last: bool,
+) {
+    unsafe {
+        let mut v: [__m512i; 16] = core::array::from_fn(|i| match i {
+            0..8 => state[i],
+            12 => _mm512_set1_epi64((IV[4] ^ counter as u64) as _),
+            13 => _mm512_set1_epi64((IV[5] ^ (counter >> 64) as u64) as _),
+            14 if last => _mm512_set1_epi64(!IV[6] as _),
+            _ => _mm512_set1_epi64(IV[i - 8] as _),
+        });
+
+        for s in &SIGMA {
+            g(&mut v, [0, 4, 8, 12], block[s[0]], block[s[1]]);
+            g(&mut v, [1, 5, 9, 13], block[s[2]], block[s[3]]);
+            g(&mut v, [2, 6, 10, 14], block[s[4]], block[s[5]]);
+            g(&mut v, [3, 7, 11, 15], block[s[6]], block[s[7]]);
+            g(&mut v, [0, 5, 10, 15], block[s[8]], block[s[9]]);
+            g(&mut v, [1, 6, 11, 12], block[s[10]], block[s[11]]);
+            g(&mut v, [2, 7, 8, 13], block[s[12]], block[s[13]]);
+            g(&mut v, [3, 4, 9, 14], block[s[14]], block[s[15]]);
+        }
+
+        for i in 0..8 {
+            state[i] = _mm512_ternarylogic_epi64(state[i], v[i], v[i + 8], 0x96);
+        }
+    }
+}
+
+#[inline(always)]
+unsafe fn g(v: &mut [__m512i; 16], [a, b, c, d]: [usize; 4], x: __m512i, y: __m512i) {
+    unsafe {
+        v[a] = _mm512_add_epi64(_mm512_add_epi64(v[a], v[b]), x);
+        v[d] = _mm512_ror_epi64(_mm512_xor_si512(v[d], v[a]), 32);
+        v[c] = _mm512_add_epi64(v[c], v[d]);
+        v[b] = _mm512_ror_epi64(_mm512_xor_si512(v[b], v[c]), 24);
+        v[a] = _mm512_add_epi64(_mm512_add_epi64(v[a], v[b]), y);
+        v[d] = _mm512_ror_epi64(_mm512_xor_si512(v[d], v[a]), 16);
+        v[c] = _mm512_add_epi64(v[c], v[d]);
+        v[b] = _mm512_ror_epi64(_mm512_xor_si512(v[b], v[c]), 63);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_compress_equivalence() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let mut states: [[u64; 8]; 8] =
+            core::array::from_fn(|_| core::array::from_fn(|_| rng.random()));
+        let blocks: [[u64; 16]; 8] =
+            core::array::from_fn(|_| core::array::from_fn(|_| rng.random()));
+        let lanes = |words: &dyn Fn(usize) -> u64| unsafe {
+            let words: [u64; 8] = core::array::from_fn(words);
+            _mm512_loadu_si512(words.as_ptr() as *const _)
+        };
+        let mut state_avx512: [__m512i; 8] =
+            core::array::from_fn(|i| lanes(&|lane| states[lane][i]));
+        let block_avx512: [__m512i; 16] = core::array::from_fn(|i| lanes(&|lane| blocks[lane][i]));
+
+        for (last, counter) in [(false, 128), (true, 300)] {
+            for i in 0..8 {
+                compress(&mut states[i], &blocks[i], counter, last);
+            }
+            multiway_compress(&mut state_avx512, &block_avx512, counter, last);
+
+            let mut output_state_simd_soa = [[0u64; 8]; 8];
+            for i in 0..8 {
+                unsafe {
+                    _mm512_storeu_si512(
+                        output_state_simd_soa[i].as_mut_ptr() as *mut _,
+                        state_avx512[i],
+                    );
+                }
+            }
+            let output_state_simd: [[u64; 8]; 8] =
+                core::array::from_fn(|i| core::array::from_fn(|j| output_state_simd_soa[j][i]));
+            assert_eq!(states, output_state_simd);
+        }
+    }
+}
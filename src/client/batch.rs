@@ -0,0 +1,88 @@
+//! Bounded-concurrency processing of many challenges.
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use reqwest::Client;
+use tokio_stream::{Stream, StreamExt};
+
+use super::{Challenge, Solution, SolveError, SolveOptions};
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Aggregate outcome of [`solve_all`]
+pub struct BatchStats {
+    /// challenges solved
+    pub solved: u64,
+    /// challenges that failed
+    pub failed: u64,
+    /// wall time from the first challenge to the last result
+    pub elapsed: Duration,
+}
+
+impl BatchStats {
+    /// solved challenges per second
+    pub fn throughput(&self) -> f64 {
+        self.solved as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// fraction of challenges that failed
+    pub fn error_rate(&self) -> f64 {
+        self.failed as f64 / (self.solved + self.failed).max(1) as f64
+    }
+}
+
+/// Solve every challenge of a stream with at most `concurrency` challenges in flight.
+///
+/// A challenge counts against the limit from its first request to its last response,
+/// so the limit spans both the HTTP round trips and the CPU solve.
+/// `on_result` is called with the position of the challenge in the stream and its outcome,
+/// in completion order.
+pub async fn solve_all(
+    pool: &Arc<rayon::ThreadPool>,
+    client: &Client,
+    challenges: impl Stream<Item = Challenge>,
+    concurrency: usize,
+    options: &SolveOptions,
+    mut on_result: impl FnMut(usize, Result<Solution, SolveError>),
+) -> BatchStats {
+    assert!(concurrency > 0, "concurrency must be positive");
+    let mut stats = BatchStats::default();
+    let mut record = |joined: Result<
+        (usize, Result<Solution, SolveError>),
+        tokio::task::JoinError,
+    >| match joined {
+        Ok((index, result)) => {
+            match result {
+                Ok(_) => stats.solved += 1,
+                Err(_) => stats.failed += 1,
+            }
+            on_result(index, result);
+        }
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    };
+
+    let start = Instant::now();
+    let mut set = tokio::task::JoinSet::new();
+    let mut challenges = std::pin::pin!(challenges);
+    let mut index = 0;
+    while let Some(challenge) = challenges.next().await {
+        while set.len() >= concurrency {
+            record(set.join_next().await.unwrap());
+        }
+        let pool = pool.clone();
+        let client = client.clone();
+        let options = options.clone();
+        set.spawn(async move {
+            let result = super::solve_challenge(&pool, &client, &challenge, &options).await;
+            (index, result)
+        });
+        index += 1;
+    }
+    while let Some(joined) = set.join_next().await {
+        record(joined);
+    }
+
+    stats.elapsed = start.elapsed();
+    stats
+}
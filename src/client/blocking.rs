@@ -0,0 +1,249 @@
+//! Blocking wrappers around the async client.
+//!
+//! The client owns a private single-worker runtime, so callers do not need to set one up.
+//! Like `reqwest::blocking`, it must not be used from within an async context.
+use std::sync::Arc;
+
+use reqwest::cookie::{CookieStore, Jar};
+
+use crate::adapter::{CapJsResponse, SolveCapJsResponseMeta};
+
+use super::{
+    Challenge, McaptchaHealth, McaptchaWidget, Solution, SolveError, SolveOptions,
+    batch::BatchStats,
+};
+
+/// Blocking end-to-end PoW client
+pub struct Client {
+    runtime: tokio::runtime::Runtime,
+    client: reqwest::Client,
+    pool: Arc<rayon::ThreadPool>,
+    cookie_jar: Option<Arc<Jar>>,
+}
+
+impl Client {
+    /// creates a new client solving on one thread per available CPU
+    pub fn new() -> Self {
+        let jar = Arc::new(Jar::default());
+        Self::with_pool(
+            super::with_cookie_jar(super::browser_client_builder(), jar.clone())
+                .build()
+                .unwrap(),
+            rayon::ThreadPoolBuilder::new().build().unwrap(),
+        )
+        .with_cookie_jar(jar)
+    }
+
+    /// creates a new client sending all traffic through `proxy`, see [`super::with_proxy`]
+    pub fn with_proxy(proxy: &str) -> Result<Self, SolveError> {
+        let jar = Arc::new(Jar::default());
+        let builder = super::with_cookie_jar(super::browser_client_builder(), jar.clone());
+        Ok(Self::with_pool(
+            super::with_proxy(builder, proxy)?.build()?,
+            rayon::ThreadPoolBuilder::new().build().unwrap(),
+        )
+        .with_cookie_jar(jar))
+    }
+
+    /// creates a new client from an HTTP client and a solver pool
+    ///
+    /// The cookies of `client` are not accessible through [`Client::cookies`] unless its jar is attached
+    /// with [`Client::with_cookie_jar`].
+    pub fn with_pool(client: reqwest::Client, pool: rayon::ThreadPool) -> Self {
+        // the anubis and go-away solvers use `block_in_place`, which needs a multi-threaded runtime
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        Self {
+            runtime,
+            client,
+            pool: Arc::new(pool),
+            cookie_jar: None,
+        }
+    }
+
+    /// attaches the cookie jar the HTTP client was built with, see [`super::with_cookie_jar`]
+    pub fn with_cookie_jar(mut self, jar: Arc<Jar>) -> Self {
+        self.cookie_jar = Some(jar);
+        self
+    }
+
+    /// the `Cookie` header the client would send to `url`, carrying the session of earlier solves
+    pub fn cookies(&self, url: &str) -> Result<Option<String>, SolveError> {
+        let url = url::Url::parse(url)?;
+        Ok(self
+            .cookie_jar
+            .as_ref()
+            .and_then(|jar| jar.cookies(&url))
+            .and_then(|header| header.to_str().ok().map(str::to_string)))
+    }
+
+    /// fetches a resource with the session of earlier solves, for example the page a token unlocks
+    pub fn get(&self, url: &str) -> Result<String, SolveError> {
+        self.runtime.block_on(async {
+            Ok(self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?)
+        })
+    }
+
+    /// solves any supported challenge end to end, see [`super::solve_challenge`]
+    pub fn solve_challenge(
+        &self,
+        challenge: &Challenge,
+        options: &SolveOptions,
+    ) -> Result<Solution, SolveError> {
+        self.runtime.block_on(super::solve_challenge(
+            &self.pool,
+            &self.client,
+            challenge,
+            options,
+        ))
+    }
+
+    /// solves many challenges with bounded concurrency, see [`super::batch::solve_all`]
+    pub fn solve_all(
+        &self,
+        challenges: impl IntoIterator<Item = Challenge>,
+        concurrency: usize,
+        options: &SolveOptions,
+        on_result: impl FnMut(usize, Result<Solution, SolveError>),
+    ) -> BatchStats {
+        self.runtime.block_on(super::batch::solve_all(
+            &self.pool,
+            &self.client,
+            tokio_stream::iter(challenges),
+            concurrency,
+            options,
+            on_result,
+        ))
+    }
+
+    /// solves an mCaptcha site key, see [`super::solve_mcaptcha_budgeted`]
+    pub fn solve_mcaptcha(
+        &self,
+        base_url: &str,
+        site_key: &str,
+        limit: u64,
+    ) -> Result<String, SolveError> {
+        self.runtime.block_on(super::solve_mcaptcha_budgeted(
+            &self.pool,
+            &self.client,
+            base_url,
+            site_key,
+            limit,
+        ))
+    }
+
+    /// validates an mCaptcha verification token, see [`super::siteverify_mcaptcha`]
+    pub fn siteverify_mcaptcha(
+        &self,
+        base_url: &str,
+        site_key: &str,
+        secret: &str,
+        token: &str,
+    ) -> Result<bool, SolveError> {
+        self.runtime.block_on(super::siteverify_mcaptcha(
+            &self.client,
+            base_url,
+            site_key,
+            secret,
+            token,
+        ))
+    }
+
+    /// checks the health of an mCaptcha instance, see [`super::mcaptcha_health`]
+    pub fn mcaptcha_health(&self, base_url: &str) -> Result<McaptchaHealth, SolveError> {
+        self.runtime
+            .block_on(super::mcaptcha_health(&self.client, base_url))
+    }
+
+    /// polls the difficulty factor of a site key, see [`super::poll_mcaptcha_difficulty`]
+    pub fn poll_mcaptcha_difficulty(
+        &self,
+        base_url: &str,
+        site_key: &str,
+    ) -> Result<u32, SolveError> {
+        self.runtime.block_on(super::poll_mcaptcha_difficulty(
+            &self.client,
+            base_url,
+            site_key,
+        ))
+    }
+
+    /// solves the mCaptcha widget embedded in a page, see [`super::solve_mcaptcha_url`]
+    pub fn solve_mcaptcha_url(
+        &self,
+        page_url: &str,
+    ) -> Result<(McaptchaWidget, String), SolveError> {
+        self.runtime.block_on(super::solve_mcaptcha_url(
+            &self.pool,
+            &self.client,
+            page_url,
+        ))
+    }
+
+    /// solves a Cap.js site key, see [`super::solve_capjs`]
+    pub fn solve_capjs(
+        &self,
+        base_url: &str,
+        site_key: &str,
+    ) -> Result<(CapJsResponse, SolveCapJsResponseMeta), SolveError> {
+        self.runtime.block_on(super::solve_capjs(
+            &self.pool,
+            &self.client,
+            base_url,
+            site_key,
+        ))
+    }
+
+    /// solves an Anubis PoW, see [`super::solve_anubis`]
+    pub fn solve_anubis(&self, base_url: &str) -> Result<String, SolveError> {
+        self.runtime
+            .block_on(super::solve_anubis(&self.client, base_url))
+    }
+
+    /// solves a go-away "js-pow-sha256" PoW, see [`super::solve_goaway_js_pow_sha256`]
+    pub fn solve_goaway_js_pow_sha256(&self, base_url: &str) -> Result<String, SolveError> {
+        self.runtime
+            .block_on(super::solve_goaway_js_pow_sha256(&self.client, base_url))
+    }
+
+    /// solves an ALTCHA PoW, see [`super::solve_altcha`]
+    pub fn solve_altcha(&self, challenge_url: &str) -> Result<String, SolveError> {
+        self.runtime
+            .block_on(super::solve_altcha(&self.client, challenge_url))
+    }
+
+    /// solves a Friendly Captcha puzzle, see [`super::solve_friendly_captcha`]
+    pub fn solve_friendly_captcha(
+        &self,
+        puzzle_url: &str,
+        site_key: &str,
+    ) -> Result<String, SolveError> {
+        self.runtime.block_on(super::solve_friendly_captcha(
+            &self.client,
+            puzzle_url,
+            site_key,
+        ))
+    }
+
+    /// solves a haproxy-protection bot check, see [`super::solve_haproxy_protection`]
+    pub fn solve_haproxy_protection(&self, url: &str) -> Result<String, SolveError> {
+        self.runtime
+            .block_on(super::solve_haproxy_protection(&self.client, url))
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
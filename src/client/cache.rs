@@ -0,0 +1,101 @@
+//! Reuse of still-valid verification tokens.
+//!
+//! mCaptcha does not report how long a token stays valid, so the validity window is configured
+//! per cache and should not exceed the one of the instance.
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::Client;
+
+use super::SolveError;
+
+/// Verification tokens keyed by instance and site key, valid for a fixed window after solving
+pub struct TokenCache {
+    ttl: Duration,
+    tokens: Mutex<HashMap<(String, String), (String, Instant)>>,
+}
+
+impl TokenCache {
+    /// creates an empty cache whose tokens expire `ttl` after they were inserted
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// the validity window of cached tokens
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// returns the cached token of a site key if it has not expired
+    pub fn get(&self, base_url: &str, site_key: &str) -> Option<String> {
+        let tokens = self.tokens.lock().unwrap();
+        tokens
+            .get(&(base_url.to_string(), site_key.to_string()))
+            .filter(|(_, expires)| Instant::now() < *expires)
+            .map(|(token, _)| token.clone())
+    }
+
+    /// caches a freshly issued token, dropping expired ones
+    pub fn insert(&self, base_url: &str, site_key: &str, token: String) {
+        let now = Instant::now();
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, (_, expires)| now < *expires);
+        tokens.insert(
+            (base_url.to_string(), site_key.to_string()),
+            (token, now + self.ttl),
+        );
+    }
+
+    /// forgets the token of a site key, for example after the server consumed it
+    pub fn invalidate(&self, base_url: &str, site_key: &str) {
+        self.tokens
+            .lock()
+            .unwrap()
+            .remove(&(base_url.to_string(), site_key.to_string()));
+    }
+
+    /// returns the cached token of a site key, or solves a new one, see [`super::solve_mcaptcha_budgeted`]
+    ///
+    /// Concurrent misses for the same site key each solve, the last token to finish is kept.
+    pub async fn get_or_solve(
+        &self,
+        pool: &rayon::ThreadPool,
+        client: &Client,
+        base_url: &str,
+        site_key: &str,
+        limit: u64,
+    ) -> Result<String, SolveError> {
+        if let Some(token) = self.get(base_url, site_key) {
+            return Ok(token);
+        }
+        let token = super::solve_mcaptcha_budgeted(pool, client, base_url, site_key, limit).await?;
+        self.insert(base_url, site_key, token.clone());
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_expiry() {
+        let cache = TokenCache::new(Duration::from_secs(60));
+        assert_eq!(cache.get("http://a", "x"), None);
+        cache.insert("http://a", "x", "token".to_string());
+        assert_eq!(cache.get("http://a", "x").as_deref(), Some("token"));
+        assert_eq!(cache.get("http://b", "x"), None);
+        cache.invalidate("http://a", "x");
+        assert_eq!(cache.get("http://a", "x"), None);
+
+        let expired = TokenCache::new(Duration::ZERO);
+        expired.insert("http://a", "x", "token".to_string());
+        assert_eq!(expired.get("http://a", "x"), None);
+    }
+}
@@ -0,0 +1,942 @@
+//! Open-loop load generation against an mCaptcha instance.
+//!
+//! Cycles of config fetch, solve and verify are started at the arrival rate of a [`LoadProfile`],
+//! regardless of how fast earlier cycles complete. Latencies are measured from the time a cycle was scheduled
+//! to start, so a slowing server shows up as queueing delay instead of silently lowering the
+//! offered load (coordinated omission).
+//!
+//! Latencies of every phase are recorded into HDR histograms with 3 significant digits.
+//! The difficulty of every received PoW configuration is kept with the offered rate at the time,
+//! to characterize how the instance adapts difficulty to load.
+use std::{sync::Arc, time::Duration};
+
+use hdrhistogram::Histogram;
+use reqwest::Client;
+use tokio::time::Instant;
+
+use super::{McaptchaApiError, McaptchaWidget, SolveError};
+
+/// How often progress is reported
+pub const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Version of the schema of [`LoadReport::to_json`] and [`LoadReport::write_summary_csv`],
+/// incremented when a field is renamed or removed or its meaning changes
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// The largest recorded latency, longer ones are clamped
+pub const MAX_LATENCY: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A phase of a load test cycle
+pub enum Phase {
+    /// fetching the PoW configuration
+    Config,
+    /// solving the PoW on the pool, including the time waiting for a worker
+    Solve,
+    /// submitting the work and receiving the token
+    Verify,
+    /// the whole cycle, from its scheduled start to the token
+    Total,
+}
+
+impl Phase {
+    /// every phase, in cycle order
+    pub const ALL: [Phase; 4] = [Phase::Config, Phase::Solve, Phase::Verify, Phase::Total];
+
+    /// the name of the phase
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Config => "config",
+            Phase::Solve => "solve",
+            Phase::Verify => "verify",
+            Phase::Total => "total",
+        }
+    }
+}
+
+impl std::str::FromStr for Phase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Phase::ALL
+            .into_iter()
+            .find(|phase| phase.name() == s)
+            .ok_or_else(|| format!("invalid phase: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Cause of a failed cycle
+pub enum ErrorKind {
+    /// the instance answered 429
+    RateLimited,
+    /// the instance did not know the site key
+    InvalidSiteKey,
+    /// the work was rejected
+    VerificationFailed,
+    /// the instance answered with a 5xx status
+    ServerError,
+    /// the connection failed or the response could not be read
+    Http,
+    /// any other error
+    Other,
+}
+
+impl ErrorKind {
+    /// every error kind
+    pub const ALL: [ErrorKind; 6] = [
+        ErrorKind::RateLimited,
+        ErrorKind::InvalidSiteKey,
+        ErrorKind::VerificationFailed,
+        ErrorKind::ServerError,
+        ErrorKind::Http,
+        ErrorKind::Other,
+    ];
+
+    /// the name of the error kind
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::RateLimited => "rate_limited",
+            ErrorKind::InvalidSiteKey => "invalid_site_key",
+            ErrorKind::VerificationFailed => "verification_failed",
+            ErrorKind::ServerError => "server_error",
+            ErrorKind::Http => "http",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    /// classifies the error of a cycle
+    pub fn of(error: &SolveError) -> Self {
+        match error {
+            SolveError::McaptchaApi(McaptchaApiError::RateLimited(_)) => ErrorKind::RateLimited,
+            SolveError::McaptchaApi(McaptchaApiError::InvalidSiteKey(_)) => {
+                ErrorKind::InvalidSiteKey
+            }
+            SolveError::McaptchaApi(McaptchaApiError::VerificationFailed(_)) => {
+                ErrorKind::VerificationFailed
+            }
+            SolveError::McaptchaApi(McaptchaApiError::Internal(..)) => ErrorKind::ServerError,
+            SolveError::Reqwest(_) | SolveError::Json(_) => ErrorKind::Http,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A stage of a load profile, the arrival rate changes linearly from `from_rate` to `to_rate`
+pub struct Stage {
+    /// how long the stage lasts
+    pub duration: Duration,
+    /// cycles started per second at the beginning of the stage
+    pub from_rate: f64,
+    /// cycles started per second at the end of the stage
+    pub to_rate: f64,
+    /// cycles started during the stage are not reported
+    pub warmup: bool,
+}
+
+impl Stage {
+    /// the expected number of arrivals during the stage
+    fn arrivals(&self) -> f64 {
+        (self.from_rate + self.to_rate) / 2.0 * self.duration.as_secs_f64()
+    }
+
+    /// the offset at which `k` arrivals happened since the start of the stage
+    fn offset_of(&self, k: f64) -> f64 {
+        let slope = (self.to_rate - self.from_rate) / self.duration.as_secs_f64();
+        if slope.abs() < f64::EPSILON {
+            return k / self.from_rate;
+        }
+        // solve from_rate * t + slope * t^2 / 2 = k
+        ((self.from_rate * self.from_rate + 2.0 * slope * k).sqrt() - self.from_rate) / slope
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Arrival rate over time of a load test
+///
+/// Parsed from comma separated stages of the form `[warmup:]<seconds>s@<rate>[..<rate>]`,
+/// for example `warmup:10s@5,60s@5..100` (warm-up, then linear ramp), `30s@10,30s@20,30s@40` (steps)
+/// or `60s@10,5s@500,60s@10` (spike).
+pub struct LoadProfile {
+    /// the stages, in order
+    pub stages: Vec<Stage>,
+}
+
+impl LoadProfile {
+    /// a constant arrival rate
+    pub fn constant(rate: f64, duration: Duration) -> Self {
+        Self {
+            stages: vec![Stage {
+                duration,
+                from_rate: rate,
+                to_rate: rate,
+                warmup: false,
+            }],
+        }
+    }
+
+    /// the total duration of the profile
+    pub fn duration(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.duration).sum()
+    }
+
+    /// the total duration of the warm-up stages
+    pub fn warmup(&self) -> Duration {
+        self.stages
+            .iter()
+            .filter(|stage| stage.warmup)
+            .map(|stage| stage.duration)
+            .sum()
+    }
+
+    /// the target arrival rate at `offset`
+    pub fn rate_at(&self, offset: Duration) -> f64 {
+        let mut start = Duration::ZERO;
+        for stage in &self.stages {
+            if offset < start + stage.duration {
+                let progress = (offset - start).as_secs_f64() / stage.duration.as_secs_f64();
+                return stage.from_rate + (stage.to_rate - stage.from_rate) * progress;
+            }
+            start += stage.duration;
+        }
+        0.0
+    }
+
+    /// the scheduled offset of the `n`-th arrival and whether it is reported,
+    /// `None` once the profile is over
+    pub fn arrival(&self, n: u64) -> Option<(Duration, bool)> {
+        let mut k = n as f64;
+        let mut start = Duration::ZERO;
+        for stage in &self.stages {
+            let arrivals = stage.arrivals();
+            if k < arrivals {
+                let offset = Duration::from_secs_f64(stage.offset_of(k));
+                return Some((start + offset.min(stage.duration), !stage.warmup));
+            }
+            k -= arrivals;
+            start += stage.duration;
+        }
+        None
+    }
+}
+
+impl std::str::FromStr for LoadProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_rate = |rate: &str| match rate.trim().parse::<f64>() {
+            Ok(rate) if rate.is_finite() && rate >= 0.0 => Ok(rate),
+            _ => Err(format!("invalid rate: {}", rate)),
+        };
+        let stages = s
+            .split(',')
+            .map(|stage| {
+                let stage = stage.trim();
+                let (warmup, stage) = match stage.strip_prefix("warmup:") {
+                    Some(stage) => (true, stage),
+                    None => (false, stage),
+                };
+                let (duration, rate) = stage
+                    .split_once('@')
+                    .ok_or_else(|| format!("expected <seconds>s@<rate>: {}", stage))?;
+                let duration = match duration.trim().strip_suffix('s').map(str::parse::<f64>) {
+                    Some(Ok(secs)) if secs.is_finite() && secs > 0.0 => {
+                        Duration::from_secs_f64(secs)
+                    }
+                    _ => return Err(format!("invalid duration: {}", duration)),
+                };
+                let (from_rate, to_rate) = match rate.split_once("..") {
+                    Some((from, to)) => (parse_rate(from)?, parse_rate(to)?),
+                    None => (parse_rate(rate)?, parse_rate(rate)?),
+                };
+                Ok(Stage {
+                    duration,
+                    from_rate,
+                    to_rate,
+                    warmup,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { stages })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Open-loop load test configuration
+pub struct LoadConfig {
+    /// the mCaptcha instances and site keys to rotate cycles across
+    pub targets: Vec<McaptchaWidget>,
+    /// the arrival rate over time
+    pub profile: LoadProfile,
+    /// cycles allowed in flight, arrivals beyond it are dropped
+    pub max_in_flight: usize,
+}
+
+#[derive(Debug, Clone)]
+/// Outcome of a load test
+pub struct LoadReport {
+    /// cycles scheduled
+    pub issued: u64,
+    /// cycles that obtained a token
+    pub succeeded: u64,
+    /// cycles that failed
+    pub failed: u64,
+    /// cycles not started because `max_in_flight` was reached
+    pub dropped: u64,
+    /// cycles currently in flight, including warm-up cycles
+    pub in_flight: usize,
+    /// time since the first scheduled cycle
+    pub elapsed: Duration,
+    /// the part of `elapsed` spent in warm-up stages
+    pub warmup: Duration,
+    /// the target arrival rate at the last tick
+    pub target_rate: f64,
+    /// the difficulty factor of the most recent PoW configuration
+    pub difficulty: Option<u32>,
+    /// latency histograms of succeeded cycles in microseconds, indexed like [`Phase::ALL`]
+    pub histograms: [Histogram<u64>; 4],
+    /// every PoW configuration received, including from warm-up and failed cycles, in completion order
+    pub timeline: Vec<DifficultySample>,
+    /// outcomes per target, indexed like [`LoadConfig::targets`]
+    pub per_target: Vec<TargetStats>,
+    /// failed cycles by cause, indexed like [`ErrorKind::ALL`]
+    pub errors: [u64; 6],
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Outcome of the measured cycles sent to one target
+pub struct TargetStats {
+    /// cycles that obtained a token
+    pub succeeded: u64,
+    /// cycles that failed
+    pub failed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A PoW configuration received during a load test, relating the difficulty to the offered load
+pub struct DifficultySample {
+    /// time from the start of the test to the config response
+    pub at: Duration,
+    /// the target arrival rate when the cycle was scheduled
+    pub offered_rate: f64,
+    /// the difficulty factor
+    pub difficulty: u32,
+    /// time spent solving, `None` if the cycle failed before the solve completed
+    pub solve: Option<Duration>,
+    /// the cycle was scheduled during a warm-up stage
+    pub warmup: bool,
+}
+
+impl LoadReport {
+    fn new(targets: usize) -> Self {
+        let histogram = || {
+            Histogram::new_with_bounds(1, MAX_LATENCY.as_micros() as u64, 3)
+                .expect("valid histogram bounds")
+        };
+        Self {
+            issued: 0,
+            succeeded: 0,
+            failed: 0,
+            dropped: 0,
+            in_flight: 0,
+            elapsed: Duration::ZERO,
+            warmup: Duration::ZERO,
+            target_rate: 0.0,
+            difficulty: None,
+            histograms: [histogram(), histogram(), histogram(), histogram()],
+            timeline: Vec::new(),
+            per_target: vec![TargetStats::default(); targets],
+            errors: [0; 6],
+        }
+    }
+
+    fn record(&mut self, phase: Phase, latency: Duration) {
+        self.histograms[phase as usize].saturating_record(latency.as_micros() as u64);
+    }
+
+    /// the latency histogram of a phase, in microseconds
+    pub fn histogram(&self, phase: Phase) -> &Histogram<u64> {
+        &self.histograms[phase as usize]
+    }
+
+    /// succeeded cycles per second outside of warm-up
+    pub fn throughput(&self) -> f64 {
+        self.succeeded as f64 / self.elapsed.saturating_sub(self.warmup).as_secs_f64()
+    }
+
+    /// fraction of issued cycles that failed or were dropped
+    pub fn error_rate(&self) -> f64 {
+        (self.failed + self.dropped) as f64 / self.issued.max(1) as f64
+    }
+
+    /// latency quantile of a phase of succeeded cycles
+    pub fn percentile(&self, phase: Phase, q: f64) -> Option<Duration> {
+        let histogram = self.histogram(phase);
+        (!histogram.is_empty()).then(|| Duration::from_micros(histogram.value_at_quantile(q)))
+    }
+
+    /// failed cycles of a cause
+    pub fn errors(&self, kind: ErrorKind) -> u64 {
+        self.errors[kind as usize]
+    }
+
+    /// the report as JSON, see [`REPORT_SCHEMA_VERSION`] for the schema
+    pub fn to_json(&self, config: &LoadConfig) -> serde_json::Value {
+        let ms = |d: Option<Duration>| d.map(|d| d.as_secs_f64() * 1000.0);
+        let phases: serde_json::Map<_, _> = Phase::ALL
+            .into_iter()
+            .map(|phase| {
+                let histogram = self.histogram(phase);
+                let stats = serde_json::json!({
+                    "count": histogram.len(),
+                    "mean_ms": (!histogram.is_empty()).then(|| histogram.mean() / 1000.0),
+                    "p50_ms": ms(self.percentile(phase, 0.5)),
+                    "p90_ms": ms(self.percentile(phase, 0.9)),
+                    "p99_ms": ms(self.percentile(phase, 0.99)),
+                    "p999_ms": ms(self.percentile(phase, 0.999)),
+                    "max_ms": ms(self.percentile(phase, 1.0)),
+                });
+                (phase.name().to_string(), stats)
+            })
+            .collect();
+        let errors: serde_json::Map<_, _> = ErrorKind::ALL
+            .into_iter()
+            .map(|kind| {
+                (
+                    kind.name().to_string(),
+                    serde_json::Value::from(self.errors(kind)),
+                )
+            })
+            .collect();
+        let targets: Vec<_> = config
+            .targets
+            .iter()
+            .zip(&self.per_target)
+            .map(|(target, stats)| {
+                serde_json::json!({
+                    "base_url": target.base_url,
+                    "site_key": target.site_key,
+                    "succeeded": stats.succeeded,
+                    "failed": stats.failed,
+                })
+            })
+            .collect();
+        let timeline: Vec<_> = self
+            .timeline
+            .iter()
+            .map(|sample| {
+                serde_json::json!({
+                    "elapsed_s": sample.at.as_secs_f64(),
+                    "offered_rps": sample.offered_rate,
+                    "difficulty": sample.difficulty,
+                    "solve_ms": ms(sample.solve),
+                    "warmup": sample.warmup,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "schema_version": REPORT_SCHEMA_VERSION,
+            "issued": self.issued,
+            "succeeded": self.succeeded,
+            "failed": self.failed,
+            "dropped": self.dropped,
+            "elapsed_s": self.elapsed.as_secs_f64(),
+            "warmup_s": self.warmup.as_secs_f64(),
+            "throughput": self.throughput(),
+            "error_rate": self.error_rate(),
+            "errors": errors,
+            "phases": phases,
+            "targets": targets,
+            "timeline": timeline,
+        })
+    }
+
+    /// writes the scalar metrics of the report as `metric,value` CSV rows in a fixed order,
+    /// so runs can be compared line by line; empty values mean no data
+    ///
+    /// Latencies are named `<phase>.<p50|p90|p99|p999|max>_ms` and errors `errors.<kind>`.
+    pub fn write_summary_csv(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        writeln!(writer, "metric,value")?;
+        writeln!(writer, "schema_version,{}", REPORT_SCHEMA_VERSION)?;
+        writeln!(writer, "issued,{}", self.issued)?;
+        writeln!(writer, "succeeded,{}", self.succeeded)?;
+        writeln!(writer, "failed,{}", self.failed)?;
+        writeln!(writer, "dropped,{}", self.dropped)?;
+        writeln!(writer, "elapsed_s,{:.3}", self.elapsed.as_secs_f64())?;
+        writeln!(writer, "warmup_s,{:.3}", self.warmup.as_secs_f64())?;
+        writeln!(writer, "throughput,{:.3}", self.throughput())?;
+        writeln!(writer, "error_rate,{:.6}", self.error_rate())?;
+        for kind in ErrorKind::ALL {
+            writeln!(writer, "errors.{},{}", kind.name(), self.errors(kind))?;
+        }
+        for phase in Phase::ALL {
+            for (name, q) in [
+                ("p50", 0.5),
+                ("p90", 0.9),
+                ("p99", 0.99),
+                ("p999", 0.999),
+                ("max", 1.0),
+            ] {
+                write!(writer, "{}.{}_ms,", phase.name(), name)?;
+                if let Some(latency) = self.percentile(phase, q) {
+                    write!(writer, "{:.3}", latency.as_secs_f64() * 1000.0)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// writes [`LoadReport::timeline`] as CSV with the columns
+    /// `elapsed_s,offered_rps,difficulty,solve_ms,warmup`, `solve_ms` is empty for failed solves
+    pub fn write_timeline_csv(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        writeln!(writer, "elapsed_s,offered_rps,difficulty,solve_ms,warmup")?;
+        for sample in &self.timeline {
+            write!(
+                writer,
+                "{:.3},{:.3},{},",
+                sample.at.as_secs_f64(),
+                sample.offered_rate,
+                sample.difficulty
+            )?;
+            if let Some(solve) = sample.solve {
+                write!(writer, "{:.3}", solve.as_secs_f64() * 1000.0)?;
+            }
+            writeln!(writer, ",{}", sample.warmup)?;
+        }
+        Ok(())
+    }
+}
+
+/// what a cycle observed before it completed or failed
+#[derive(Default)]
+struct Observed {
+    config: Option<(Instant, u32)>,
+    solve: Option<Duration>,
+}
+
+/// the target the cycle ran against, whether it is measured, the rate it was offered at, its latency,
+/// what it observed and its phase durations
+type Outcome = (
+    usize,
+    bool,
+    f64,
+    Duration,
+    Observed,
+    Result<[Duration; 3], SolveError>,
+);
+
+/// one config, solve and verify cycle, returns the duration of each phase
+async fn cycle(
+    pool: &rayon::ThreadPool,
+    client: &Client,
+    base_url: &str,
+    site_key: &str,
+    observed: &mut Observed,
+) -> Result<[Duration; 3], SolveError> {
+    let begin = Instant::now();
+    let config = super::fetch_mcaptcha_config(client, base_url, site_key, &mut 0).await?;
+    let fetched = Instant::now();
+    observed.config = Some((fetched, config.difficulty_factor));
+    let work = super::solve_mcaptcha_config_on_pool(pool, config, site_key, u64::MAX).await?;
+    let solved = Instant::now();
+    observed.solve = Some(solved - fetched);
+    super::submit_mcaptcha_work(client, base_url, &work, &mut 0).await?;
+    Ok([fetched - begin, solved - fetched, solved.elapsed()])
+}
+
+/// Run an open-loop load test, calling `on_tick` with the running totals every [`TICK_INTERVAL`].
+///
+/// Cycles rotate round-robin across the targets of `config`, and across `clients`, for example
+/// one per source address (see [`super::with_local_address`]) to emulate a distributed client population.
+/// Cycles still in flight when the profile ends are awaited.
+pub async fn run_open_loop(
+    pool: &Arc<rayon::ThreadPool>,
+    clients: &[Client],
+    config: &LoadConfig,
+    mut on_tick: impl FnMut(&LoadReport),
+) -> LoadReport {
+    assert!(!clients.is_empty(), "no clients to send cycles from");
+    assert!(!config.targets.is_empty(), "no targets to send cycles to");
+    let profile = &config.profile;
+    let start = Instant::now();
+    let mut report = LoadReport::new(config.targets.len());
+    let mut set: tokio::task::JoinSet<Outcome> = tokio::task::JoinSet::new();
+    let mut next_tick = start + TICK_INTERVAL;
+    let mut arrivals = 0;
+    let mut next = profile.arrival(0);
+    while next.is_some() || !set.is_empty() {
+        let (offset, measured) = next.unwrap_or_default();
+        let scheduled = start + offset;
+        tokio::select! {
+            biased;
+            Some(joined) = set.join_next() => {
+                let (target, measured, offered_rate, latency, observed, result) =
+                    joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+                if let Some((fetched, difficulty)) = observed.config {
+                    report.difficulty = Some(difficulty);
+                    report.timeline.push(DifficultySample {
+                        at: fetched - start,
+                        offered_rate,
+                        difficulty,
+                        solve: observed.solve,
+                        warmup: !measured,
+                    });
+                }
+                match (measured, result) {
+                    (false, _) => {}
+                    (true, Ok([config, solve, verify])) => {
+                        report.succeeded += 1;
+                        report.per_target[target].succeeded += 1;
+                        report.record(Phase::Config, config);
+                        report.record(Phase::Solve, solve);
+                        report.record(Phase::Verify, verify);
+                        report.record(Phase::Total, latency);
+                    }
+                    (true, Err(e)) => {
+                        report.failed += 1;
+                        report.per_target[target].failed += 1;
+                        report.errors[ErrorKind::of(&e) as usize] += 1;
+                    }
+                }
+            }
+            _ = tokio::time::sleep_until(scheduled), if next.is_some() => {
+                // every client eventually sends to every target
+                let target = (arrivals % config.targets.len() as u64) as usize;
+                let round = arrivals / config.targets.len() as u64;
+                let client = clients[(round % clients.len() as u64) as usize].clone();
+                arrivals += 1;
+                next = profile.arrival(arrivals);
+                report.issued += measured as u64;
+                if set.len() >= config.max_in_flight {
+                    report.dropped += measured as u64;
+                } else {
+                    let pool = pool.clone();
+                    let McaptchaWidget { base_url, site_key } = config.targets[target].clone();
+                    let offered_rate = profile.rate_at(offset);
+                    set.spawn(async move {
+                        let mut observed = Observed::default();
+                        let result = cycle(&pool, &client, &base_url, &site_key, &mut observed).await;
+                        (target, measured, offered_rate, scheduled.elapsed(), observed, result)
+                    });
+                }
+            }
+            _ = tokio::time::sleep_until(next_tick) => {
+                next_tick += TICK_INTERVAL;
+                let elapsed = start.elapsed();
+                report.in_flight = set.len();
+                report.elapsed = elapsed;
+                report.target_rate = profile.rate_at(elapsed);
+                on_tick(&report);
+            }
+        }
+    }
+
+    report.in_flight = 0;
+    report.elapsed = start.elapsed();
+    report.warmup = profile.warmup();
+    report.target_rate = 0.0;
+    report
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A load test metric a [`Threshold`] applies to
+pub enum Metric {
+    /// a latency quantile of a phase, in milliseconds
+    Latency {
+        /// the phase
+        phase: Phase,
+        /// the quantile
+        quantile: f64,
+    },
+    /// the fraction of cycles that failed or were dropped
+    ErrorRate,
+    /// succeeded cycles per second
+    Throughput,
+}
+
+impl Metric {
+    /// the observed value, `None` if no cycle succeeded
+    pub fn observe(self, report: &LoadReport) -> Option<f64> {
+        match self {
+            Metric::Latency { phase, quantile } => report
+                .percentile(phase, quantile)
+                .map(|latency| latency.as_secs_f64() * 1000.0),
+            Metric::ErrorRate => (report.issued > 0).then(|| report.error_rate()),
+            Metric::Throughput => Some(report.throughput()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A service level assertion on a load test
+///
+/// Parsed from `<metric> <op> <value>`, where the metric is `p50`, `p90`, `p99`, `p999` or `max`
+/// of an optional phase (`p99(verify)`, the total cycle by default), `error_rate` or `throughput`,
+/// the operator is one of `<`, `<=`, `>`, `>=` and the value is a duration (`200ms`, `1.5s`),
+/// a percentage (`0.1%`) or a plain number, for example `p99(verify) < 200ms` or `error_rate < 0.1%`.
+pub struct Threshold {
+    /// the metric
+    pub metric: Metric,
+    /// whether the observed value must be below `value`
+    pub below: bool,
+    /// whether the observed value may equal `value`
+    pub inclusive: bool,
+    /// the bound, in milliseconds for latencies and as a fraction for the error rate
+    pub value: f64,
+    source: String,
+}
+
+impl Threshold {
+    /// checks the threshold, returning the observed value and whether it holds
+    ///
+    /// A latency threshold does not hold if no cycle succeeded.
+    pub fn check(&self, report: &LoadReport) -> (Option<f64>, bool) {
+        let observed = self.metric.observe(report);
+        let holds = observed.is_some_and(|observed| match (self.below, self.inclusive) {
+            (true, true) => observed <= self.value,
+            (true, false) => observed < self.value,
+            (false, true) => observed >= self.value,
+            (false, false) => observed > self.value,
+        });
+        (observed, holds)
+    }
+}
+
+impl std::fmt::Display for Threshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl std::str::FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, op) = ["<=", ">=", "<", ">"]
+            .into_iter()
+            .find_map(|op| s.find(op).map(|index| (index, op)))
+            .ok_or_else(|| format!("expected <metric> <op> <value>: {}", s))?;
+        let (metric, value) = (s[..index].trim(), s[index + op.len()..].trim());
+
+        let metric = match metric {
+            "error_rate" => Metric::ErrorRate,
+            "throughput" => Metric::Throughput,
+            _ => {
+                let (quantile, phase) = match metric.split_once('(') {
+                    Some((quantile, phase)) => (
+                        quantile,
+                        phase
+                            .strip_suffix(')')
+                            .ok_or_else(|| format!("invalid metric: {}", metric))?
+                            .trim()
+                            .parse()?,
+                    ),
+                    None => (metric, Phase::Total),
+                };
+                let quantile = match quantile.trim() {
+                    "p50" => 0.5,
+                    "p90" => 0.9,
+                    "p99" => 0.99,
+                    "p999" => 0.999,
+                    "max" => 1.0,
+                    _ => return Err(format!("invalid metric: {}", metric)),
+                };
+                Metric::Latency { phase, quantile }
+            }
+        };
+
+        let number = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("invalid value: {}", value))
+        };
+        let value = match metric {
+            Metric::Latency { .. } => match value {
+                _ if value.ends_with("ms") => number(&value[..value.len() - 2])?,
+                _ if value.ends_with("us") => number(&value[..value.len() - 2])? / 1000.0,
+                _ if value.ends_with('s') => number(&value[..value.len() - 1])? * 1000.0,
+                _ => return Err(format!("latency needs a unit (us, ms or s): {}", value)),
+            },
+            _ => match value.strip_suffix('%') {
+                Some(percent) => number(percent)? / 100.0,
+                None => number(value)?,
+            },
+        };
+
+        Ok(Self {
+            metric,
+            below: op.starts_with('<'),
+            inclusive: op.ends_with('='),
+            value,
+            source: s.trim().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_parse() {
+        let profile: LoadProfile = "warmup:10s@5, 60s@5..100,0.5s@20".parse().unwrap();
+        assert_eq!(profile.stages.len(), 3);
+        assert!(profile.stages[0].warmup);
+        assert_eq!(profile.stages[1].from_rate, 5.0);
+        assert_eq!(profile.stages[1].to_rate, 100.0);
+        assert_eq!(profile.stages[2].duration, Duration::from_millis(500));
+        assert_eq!(profile.duration(), Duration::from_millis(70_500));
+        assert_eq!(profile.warmup(), Duration::from_secs(10));
+
+        for invalid in ["", "10@5", "10s", "0s@5", "10s@-1", "10s@5..x"] {
+            assert!(invalid.parse::<LoadProfile>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_profile_arrivals() {
+        let constant = LoadProfile::constant(4.0, Duration::from_secs(10));
+        assert_eq!(constant.arrival(0), Some((Duration::ZERO, true)));
+        assert_eq!(
+            constant.arrival(6),
+            Some((Duration::from_millis(1500), true))
+        );
+        assert_eq!(constant.arrival(40), None);
+
+        // 0 to 10/s over 10 s is 50 arrivals, the n-th at sqrt(2n)
+        let ramp: LoadProfile = "warmup:1s@10,10s@0..10".parse().unwrap();
+        assert_eq!(ramp.arrival(9), Some((Duration::from_millis(900), false)));
+        let (offset, measured) = ramp.arrival(10 + 32).unwrap();
+        assert!(measured);
+        assert!((offset.as_secs_f64() - 9.0).abs() < 1e-6, "{:?}", offset);
+        assert_eq!((0..).take_while(|&n| ramp.arrival(n).is_some()).count(), 60);
+
+        let offsets: Vec<_> = (0..60).map(|n| ramp.arrival(n).unwrap().0).collect();
+        assert!(offsets.windows(2).all(|w| w[0] <= w[1]));
+        assert!((ramp.rate_at(Duration::from_secs(6)) - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_threshold_parse() {
+        let threshold: Threshold = "p99(verify) < 200ms".parse().unwrap();
+        assert_eq!(
+            threshold.metric,
+            Metric::Latency {
+                phase: Phase::Verify,
+                quantile: 0.99
+            }
+        );
+        assert!(threshold.below && !threshold.inclusive);
+        assert_eq!(threshold.value, 200.0);
+        assert_eq!(threshold.to_string(), "p99(verify) < 200ms");
+
+        let threshold: Threshold = "p999<=1.5s".parse().unwrap();
+        assert_eq!(
+            threshold.metric,
+            Metric::Latency {
+                phase: Phase::Total,
+                quantile: 0.999
+            }
+        );
+        assert!(threshold.below && threshold.inclusive);
+        assert_eq!(threshold.value, 1500.0);
+
+        let threshold: Threshold = "error_rate < 0.1%".parse().unwrap();
+        assert_eq!(threshold.metric, Metric::ErrorRate);
+        assert!((threshold.value - 0.001).abs() < 1e-12);
+
+        let threshold: Threshold = "throughput >= 50".parse().unwrap();
+        assert_eq!(threshold.metric, Metric::Throughput);
+        assert!(!threshold.below && threshold.inclusive);
+
+        for invalid in [
+            "p99",
+            "p98 < 1ms",
+            "p99(queue) < 1ms",
+            "p99 < 200",
+            "error_rate < x",
+        ] {
+            assert!(invalid.parse::<Threshold>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_timeline_csv() {
+        let mut report = LoadReport::new(1);
+        report.timeline.push(DifficultySample {
+            at: Duration::from_millis(1500),
+            offered_rate: 10.0,
+            difficulty: 50_000,
+            solve: Some(Duration::from_micros(2500)),
+            warmup: true,
+        });
+        report.timeline.push(DifficultySample {
+            at: Duration::from_secs(2),
+            offered_rate: 12.5,
+            difficulty: 100_000,
+            solve: None,
+            warmup: false,
+        });
+        let mut csv = Vec::new();
+        report.write_timeline_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "elapsed_s,offered_rps,difficulty,solve_ms,warmup\n\
+             1.500,10.000,50000,2.500,true\n\
+             2.000,12.500,100000,,false\n"
+        );
+    }
+
+    #[test]
+    fn test_report_export() {
+        let config = LoadConfig {
+            targets: vec![McaptchaWidget {
+                base_url: "http://a".to_string(),
+                site_key: "x".to_string(),
+            }],
+            profile: LoadProfile::constant(1.0, Duration::from_secs(2)),
+            max_in_flight: 1,
+        };
+        let mut report = LoadReport::new(1);
+        report.issued = 2;
+        report.succeeded = 1;
+        report.failed = 1;
+        report.per_target[0].succeeded = 1;
+        report.per_target[0].failed = 1;
+        report.elapsed = Duration::from_secs(2);
+        report.errors[ErrorKind::RateLimited as usize] = 1;
+        report.record(Phase::Total, Duration::from_millis(20));
+
+        let json = report.to_json(&config);
+        assert_eq!(json["schema_version"], REPORT_SCHEMA_VERSION);
+        assert_eq!(json["errors"]["rate_limited"], 1);
+        assert_eq!(json["errors"]["http"], 0);
+        assert_eq!(json["phases"]["total"]["count"], 1);
+        assert!((json["phases"]["total"]["p50_ms"].as_f64().unwrap() - 20.0).abs() < 0.1);
+        assert!(json["phases"]["verify"]["p50_ms"].is_null());
+        assert_eq!(json["targets"][0]["site_key"], "x");
+        assert_eq!(json["targets"][0]["failed"], 1);
+
+        let mut csv = Vec::new();
+        report.write_summary_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], "metric,value");
+        assert_eq!(
+            lines.len(),
+            1 + 9 + ErrorKind::ALL.len() + Phase::ALL.len() * 5
+        );
+        assert!(lines.contains(&"errors.rate_limited,1"));
+        assert!(lines.contains(&"verify.p99_ms,"));
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.starts_with("total.p50_ms,20."))
+        );
+    }
+}
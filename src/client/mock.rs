@@ -0,0 +1,354 @@
+//! In-process mock mCaptcha server for integration tests of client code.
+//!
+//! Implements the config, verify, siteverify and health endpoints with real proof verification,
+//! a configurable difficulty factor and injected failures. State is kept in memory and
+//! dropped with the server.
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+};
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+
+use crate::adapter::McaptchaChallengeDescriptor;
+
+#[derive(Debug, Clone)]
+/// Configuration of a [`MockServer`]
+pub struct MockConfig {
+    /// the only site key the server knows, others are answered like an unknown captcha
+    pub site_key: String,
+    /// the account secret expected by siteverify
+    pub secret: String,
+    /// the salt of every challenge
+    pub salt: String,
+    /// the initial difficulty factor, see [`MockServer::set_difficulty`]
+    pub difficulty: u32,
+    /// answer every n-th request to any endpoint with `failure_status` instead of handling it
+    pub fail_every: Option<u64>,
+    /// the status of injected failures, 429 is answered with a `Retry-After` header
+    pub failure_status: StatusCode,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            site_key: "x".to_string(),
+            secret: "secret".to_string(),
+            salt: "x".repeat(33),
+            difficulty: 50_000,
+            fail_every: None,
+            failure_status: StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Requests handled by a [`MockServer`]
+pub struct MockStats {
+    /// challenges issued
+    pub issued: u64,
+    /// proofs accepted
+    pub verified: u64,
+    /// proofs rejected
+    pub rejected: u64,
+    /// tokens validated by siteverify
+    pub siteverified: u64,
+    /// requests answered with an injected failure
+    pub injected_failures: u64,
+}
+
+struct MockState {
+    config: MockConfig,
+    difficulty: AtomicU32,
+    requests: AtomicU64,
+    next_id: AtomicU64,
+    /// issued phrases and the difficulty they were issued with
+    challenges: Mutex<HashMap<String, u32>>,
+    tokens: Mutex<HashSet<String>>,
+    stats: Mutex<MockStats>,
+}
+
+impl MockState {
+    /// answers the request with an injected failure if it is due
+    fn inject_failure(&self) -> Option<Response> {
+        let n = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        let every = self.config.fail_every?;
+        if !n.is_multiple_of(every) {
+            return None;
+        }
+        self.stats.lock().unwrap().injected_failures += 1;
+        let body = Json(serde_json::json!({ "error": "injected failure" }));
+        Some(match self.config.failure_status {
+            StatusCode::TOO_MANY_REQUESTS => {
+                (StatusCode::TOO_MANY_REQUESTS, [("Retry-After", "1")], body).into_response()
+            }
+            status => (status, body).into_response(),
+        })
+    }
+
+    fn next_id(&self) -> String {
+        format!("{:032x}", self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct ConfigRequest {
+    key: String,
+}
+
+async fn config_api(
+    State(state): State<Arc<MockState>>,
+    Json(request): Json<ConfigRequest>,
+) -> Response {
+    if let Some(failure) = state.inject_failure() {
+        return failure;
+    }
+    if request.key != state.config.site_key {
+        return error(StatusCode::NOT_FOUND, "Captcha not found");
+    }
+    let string = state.next_id();
+    let difficulty_factor = state.difficulty.load(Ordering::Relaxed);
+    state
+        .challenges
+        .lock()
+        .unwrap()
+        .insert(string.clone(), difficulty_factor);
+    state.stats.lock().unwrap().issued += 1;
+    Json(McaptchaChallengeDescriptor {
+        salt: state.config.salt.clone(),
+        string,
+        difficulty_factor,
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct VerifyRequest {
+    string: String,
+    result: String,
+    nonce: u64,
+    key: String,
+}
+
+async fn verify_api(
+    State(state): State<Arc<MockState>>,
+    Json(work): Json<VerifyRequest>,
+) -> Response {
+    if let Some(failure) = state.inject_failure() {
+        return failure;
+    }
+    if work.key != state.config.site_key {
+        return error(StatusCode::NOT_FOUND, "Captcha not found");
+    }
+    // a challenge can only be redeemed once, like mCaptcha
+    let Some(difficulty_factor) = state.challenges.lock().unwrap().remove(&work.string) else {
+        state.stats.lock().unwrap().rejected += 1;
+        return error(StatusCode::BAD_REQUEST, "String not found");
+    };
+    let verification = McaptchaChallengeDescriptor {
+        salt: state.config.salt.clone(),
+        string: work.string,
+        difficulty_factor,
+    }
+    .verify(work.nonce);
+    if !verification.is_sufficient() || verification.result.to_string() != work.result {
+        state.stats.lock().unwrap().rejected += 1;
+        return error(StatusCode::BAD_REQUEST, "Invalid PoW");
+    }
+    let token = state.next_id();
+    state.tokens.lock().unwrap().insert(token.clone());
+    state.stats.lock().unwrap().verified += 1;
+    Json(serde_json::json!({ "token": token })).into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct SiteverifyRequest {
+    token: String,
+    key: String,
+    secret: String,
+}
+
+async fn siteverify_api(
+    State(state): State<Arc<MockState>>,
+    Json(request): Json<SiteverifyRequest>,
+) -> Response {
+    if let Some(failure) = state.inject_failure() {
+        return failure;
+    }
+    if request.secret != state.config.secret {
+        return error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    let valid =
+        request.key == state.config.site_key && state.tokens.lock().unwrap().remove(&request.token);
+    state.stats.lock().unwrap().siteverified += valid as u64;
+    Json(serde_json::json!({ "valid": valid })).into_response()
+}
+
+async fn health_api(State(state): State<Arc<MockState>>) -> Response {
+    if let Some(failure) = state.inject_failure() {
+        return failure;
+    }
+    Json(serde_json::json!({ "db": true, "redis": null })).into_response()
+}
+
+/// A mock mCaptcha instance listening on a local port, shut down when dropped
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// binds an ephemeral port on the loopback interface and serves on the current tokio runtime
+    pub async fn start(config: MockConfig) -> std::io::Result<Self> {
+        let state = Arc::new(MockState {
+            difficulty: AtomicU32::new(config.difficulty),
+            config,
+            requests: AtomicU64::new(0),
+            next_id: AtomicU64::new(0),
+            challenges: Mutex::new(HashMap::new()),
+            tokens: Mutex::new(HashSet::new()),
+            stats: Mutex::new(MockStats::default()),
+        });
+        let app = Router::new()
+            .route("/api/v1/pow/config", post(config_api))
+            .route("/api/v1/pow/verify", post(verify_api))
+            .route("/api/v1/pow/siteverify", post(siteverify_api))
+            .route("/api/v1/meta/health", get(health_api))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let (shutdown, stopped) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    stopped.await.ok();
+                })
+                .await
+                .ok();
+        });
+        Ok(Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// the base URL to pass to the client, without a trailing slash
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// the address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// changes the difficulty factor of challenges issued from now on
+    pub fn set_difficulty(&self, difficulty: u32) {
+        self.state.difficulty.store(difficulty, Ordering::Relaxed);
+    }
+
+    /// the requests handled so far
+    pub fn stats(&self) -> MockStats {
+        *self.state.stats.lock().unwrap()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{McaptchaApiError, SolveError};
+
+    #[tokio::test]
+    async fn test_mock_round_trip() {
+        let server = MockServer::start(MockConfig::default()).await.unwrap();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let client = reqwest::Client::new();
+        let base_url = server.base_url();
+
+        let token =
+            crate::client::solve_mcaptcha_budgeted(&pool, &client, &base_url, "x", u64::MAX)
+                .await
+                .unwrap();
+        let siteverify = |token: String| {
+            let (client, base_url) = (client.clone(), base_url.clone());
+            async move {
+                crate::client::siteverify_mcaptcha(&client, &base_url, "x", "secret", &token).await
+            }
+        };
+        assert!(siteverify(token.clone()).await.unwrap());
+        assert!(!siteverify(token).await.unwrap(), "tokens are single use");
+
+        let unknown = crate::client::fetch_mcaptcha_challenge(&client, &base_url, "y").await;
+        assert!(matches!(
+            unknown,
+            Err(SolveError::McaptchaApi(McaptchaApiError::InvalidSiteKey(_)))
+        ));
+        assert!(
+            crate::client::mcaptcha_health(&client, &base_url)
+                .await
+                .unwrap()
+                .is_healthy()
+        );
+        assert_eq!(
+            server.stats(),
+            MockStats {
+                issued: 1,
+                verified: 1,
+                siteverified: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_failure_injection() {
+        let server = MockServer::start(MockConfig {
+            fail_every: Some(2),
+            failure_status: StatusCode::TOO_MANY_REQUESTS,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let client = reqwest::Client::new();
+        let base_url = server.base_url();
+
+        let fetch = || crate::client::fetch_mcaptcha_challenge(&client, &base_url, "x");
+        assert!(fetch().await.is_ok());
+        assert!(matches!(
+            fetch().await,
+            Err(SolveError::McaptchaApi(McaptchaApiError::RateLimited(
+                Some(_)
+            )))
+        ));
+        assert!(fetch().await.is_ok());
+        assert_eq!(server.stats().injected_failures, 1);
+    }
+}
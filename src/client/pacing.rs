@@ -0,0 +1,69 @@
+//! Client-side request pacing with a token bucket.
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+#[derive(Debug)]
+/// Token bucket limiting the request rate of everything sharing it.
+///
+/// Waiters are served in arrival order, so a burst of callers is spread out instead of
+/// racing for each refilled token.
+pub struct Pacer {
+    rate: f64,
+    burst: f64,
+    bucket: tokio::sync::Mutex<(f64, Instant)>,
+}
+
+impl Pacer {
+    /// allows `rate` requests per second on average and bursts of up to `burst` requests, starting full
+    pub fn new(rate: f64, burst: u32) -> Self {
+        assert!(rate > 0.0, "rate must be positive");
+        assert!(burst > 0, "burst must be positive");
+        Self {
+            rate,
+            burst: burst as f64,
+            bucket: tokio::sync::Mutex::new((burst as f64, Instant::now())),
+        }
+    }
+
+    /// the average rate in requests per second
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// waits until `requests` requests may be sent, at most the burst size at once
+    pub async fn acquire(&self, requests: u32) {
+        let requests = (requests as f64).min(self.burst);
+        // holding the lock while sleeping queues later callers behind this one
+        let mut bucket = self.bucket.lock().await;
+        let (tokens, refilled) = &mut *bucket;
+        let now = Instant::now();
+        *tokens = (*tokens + (now - *refilled).as_secs_f64() * self.rate).min(self.burst);
+        *refilled = now;
+        if *tokens < requests {
+            let wait = Duration::from_secs_f64((requests - *tokens) / self.rate);
+            tokio::time::sleep(wait).await;
+            *tokens = requests;
+            *refilled = now + wait;
+        }
+        *tokens -= requests;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pacer_rate() {
+        let pacer = Pacer::new(100.0, 2);
+        let start = Instant::now();
+        // the first two pass on the initial burst, the next four wait 10ms each
+        for _ in 0..6 {
+            pacer.acquire(1).await;
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(38), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+}
@@ -0,0 +1,199 @@
+//! The decimal counter search shared by the leading zero schemes.
+//!
+//! Construct: nonce := padding || LANE_ID || 8 DECIMAL DIGITS, where the padding moves the nonce into a
+//! block of its own when the prefix leaves too little room, so every attempt is a single compression of
+//! the final block. The lane ID is the only part that differs between SIMD lanes, the digits are counted
+//! up in place and a set of lane IDs is tried at once for every count.
+use alloc::vec::Vec;
+
+/// the number of decimal digits after the lane ID
+pub(crate) const COUNTER_DIGITS: usize = 8;
+
+/// A big-endian message word
+pub(crate) trait Word: Copy {
+    /// the number of bytes of the word
+    const BYTES: usize;
+
+    /// read a word from its big-endian bytes
+    fn from_be_slice(bytes: &[u8]) -> Self;
+}
+
+impl Word for u32 {
+    const BYTES: usize = 4;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        u32::from_be_bytes(bytes.try_into().unwrap())
+    }
+}
+
+impl Word for u64 {
+    const BYTES: usize = 8;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        u64::from_be_bytes(bytes.try_into().unwrap())
+    }
+}
+
+/// the big-endian words of a block
+pub(crate) fn be_words<W: Word>(block: &[u8]) -> [W; 16] {
+    core::array::from_fn(|i| W::from_be_slice(&block[i * W::BYTES..(i + 1) * W::BYTES]))
+}
+
+/// A hash searched by [`search`], trying a set of lane IDs at once
+pub(crate) trait LaneKernel {
+    /// the message word
+    type Word: Word;
+    /// the chaining value
+    type State: Copy;
+
+    /// the initial chaining value
+    const IV: Self::State;
+    /// the number of bytes of the message length ending the padding
+    const LENGTH_BYTES: usize;
+    /// the number of bytes of a lane ID
+    const LANE_ID_BYTES: usize;
+    /// the number of lanes searched at once
+    const LANES: usize;
+    /// the number of lane ID sets, each covering one lane ID per lane
+    const LANE_ID_SETS: u32;
+    /// the byte the nonce is padded to a new block with
+    const PADDING: u8;
+
+    /// compress a block of the prefix
+    fn digest_block(state: &mut Self::State, block: &[Self::Word; 16]);
+
+    /// write the lane ID of a lane in a set
+    fn write_lane_id(set: u32, lane: usize, out: &mut [u8]);
+
+    /// try every lane ID of a set at `lane_index` of the final block, returning the first lane meeting the target
+    fn search_lanes(
+        &self,
+        midstate: &Self::State,
+        block: &[u8],
+        lane_index: usize,
+        set: u32,
+    ) -> Option<usize>;
+}
+
+/// write the two digit decimal lane ID of a lane in a set, covering `10..100` with `lanes` IDs per set
+pub(crate) fn write_decimal_lane_id(lanes: usize, set: u32, lane: usize, out: &mut [u8]) {
+    let id = 10 + set as usize * lanes + lane;
+    out.copy_from_slice(&[b'0' + (id / 10) as u8, b'0' + (id % 10) as u8]);
+}
+
+/// the final block of a lane, with the lane ID written at `lane_index`
+pub(crate) fn lane_block<K: LaneKernel>(
+    block: &[u8],
+    lane_index: usize,
+    set: u32,
+    lane: usize,
+) -> [K::Word; 16] {
+    let mut bytes = [0u8; 128];
+    let bytes = &mut bytes[..block.len()];
+    bytes.copy_from_slice(block);
+    K::write_lane_id(
+        set,
+        lane,
+        &mut bytes[lane_index..lane_index + K::LANE_ID_BYTES],
+    );
+    be_words(bytes)
+}
+
+/// search a nonce for the prefix, returning the nonce and the attempted nonces
+pub(crate) fn search<K: LaneKernel>(
+    kernel: &K,
+    prefix: &[u8],
+    limit: u64,
+) -> (Option<Vec<u8>>, u64) {
+    let block_bytes = 16 * K::Word::BYTES;
+
+    let mut nonce = Vec::new();
+    // priority 0: if the nonce and padding do not fit behind the prefix, pad to a new block
+    let tail_len = prefix.len() % block_bytes;
+    if tail_len + K::LANE_ID_BYTES + COUNTER_DIGITS + 1 + K::LENGTH_BYTES > block_bytes {
+        nonce.resize(block_bytes - tail_len, K::PADDING);
+    }
+    let message_len = prefix.len() + nonce.len();
+
+    let mut midstate = K::IV;
+    let mut chunks = prefix.chunks_exact(block_bytes);
+    for chunk in &mut chunks {
+        K::digest_block(&mut midstate, &be_words(chunk));
+    }
+    let mut tail = chunks.remainder().to_vec();
+    tail.extend_from_slice(&nonce);
+    if tail.len() == block_bytes {
+        K::digest_block(&mut midstate, &be_words(&tail));
+        tail.clear();
+    }
+
+    let mut block = alloc::vec![0u8; block_bytes];
+    block[..tail.len()].copy_from_slice(&tail);
+    let lane_index = tail.len();
+    let digits = lane_index + K::LANE_ID_BYTES..lane_index + K::LANE_ID_BYTES + COUNTER_DIGITS;
+    block[digits.end] = 0x80;
+    let bit_len = ((message_len + K::LANE_ID_BYTES + COUNTER_DIGITS) as u128 * 8).to_be_bytes();
+    block[block_bytes - K::LENGTH_BYTES..].copy_from_slice(&bit_len[16 - K::LENGTH_BYTES..]);
+
+    let mut attempted_nonces = 0;
+    for set in 0..K::LANE_ID_SETS {
+        block[digits.clone()].fill(b'0');
+        loop {
+            if attempted_nonces >= limit {
+                return (None, attempted_nonces);
+            }
+            attempted_nonces += K::LANES as u64;
+            if let Some(lane) = kernel.search_lanes(&midstate, &block, lane_index, set) {
+                K::write_lane_id(set, lane, &mut block[lane_index..digits.start]);
+                nonce.extend_from_slice(&block[lane_index..digits.end]);
+                return (Some(nonce), attempted_nonces);
+            }
+
+            // increment the digits in place
+            let mut carry = true;
+            for digit in block[digits.clone()].iter_mut().rev() {
+                if *digit == b'9' {
+                    *digit = b'0';
+                } else {
+                    *digit += 1;
+                    carry = false;
+                    break;
+                }
+            }
+            if carry {
+                break;
+            }
+        }
+    }
+    (None, attempted_nonces)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloc::string::String;
+
+    /// solve 16 bit challenges with prefixes on both sides of the padding boundary of a `block_bytes` block
+    ///
+    /// `solve` solves a challenge whose variable part is `len` bytes long and returns the proof,
+    /// `leading_zero_bits` counts the leading zero bits of the hash of a proof.
+    pub(crate) fn test_leading_zero_solver(
+        block_bytes: usize,
+        mut solve: impl FnMut(usize, u32) -> (Option<String>, u64),
+        mut leading_zero_bits: impl FnMut(usize, &str) -> u32,
+    ) {
+        for len in [
+            0,
+            10,
+            block_bytes - 28,
+            block_bytes - 18,
+            block_bytes - 1,
+            block_bytes + 36,
+        ] {
+            let (Some(proof), attempted) = solve(len, 16) else {
+                panic!("solver failed for prefix of length {}", len);
+            };
+            assert!(attempted > 0);
+            assert!(leading_zero_bits(len, &proof) >= 16, "{}", proof);
+        }
+    }
+}
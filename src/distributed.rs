@@ -0,0 +1,330 @@
+//! Distributed solving of a single challenge over plain TCP.
+//!
+//! This is independent of the HTTP daemon. Workers connect to a coordinator and announce how many
+//! threads they run, the coordinator then assigns every worker thread a disjoint set of search banks
+//! (thread `i` of `n` visits banks `i, i + n, i + 2n, ...`). The first worker to find a proof wins,
+//! the coordinator verifies it and tells everyone else to stop.
+//!
+//! Messages are newline-delimited JSON objects tagged by `type`:
+//!
+//! - worker → coordinator: `hello {threads}`, `progress {attempted_nonces}`,
+//!   `solved {nonce, result, attempted_nonces}`, `exhausted {attempted_nonces}`
+//! - coordinator → worker: `job {challenge, first_bank, stride, threads}`, `cancel`
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    ops::ControlFlow,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::adapter::McaptchaChallengeDescriptor;
+
+/// How often workers report progress
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// A message sent by a worker
+pub enum WorkerMessage {
+    /// Announces the number of solver threads
+    Hello {
+        /// The number of solver threads
+        threads: u32,
+    },
+    /// Periodic progress report
+    Progress {
+        /// The nonces attempted so far
+        attempted_nonces: u64,
+    },
+    /// A proof was found
+    Solved {
+        /// The nonce
+        nonce: u64,
+        /// The mCaptcha result string
+        result: String,
+        /// The nonces attempted so far
+        attempted_nonces: u64,
+    },
+    /// The assigned keyspace was searched without finding a proof, or the job was cancelled
+    Exhausted {
+        /// The nonces attempted so far
+        attempted_nonces: u64,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// A message sent by the coordinator
+pub enum CoordinatorMessage {
+    /// Assigns a slice of the keyspace
+    Job {
+        /// The challenge
+        challenge: McaptchaChallengeDescriptor,
+        /// The search bank of the first worker thread
+        first_bank: u32,
+        /// The total number of threads in the cluster
+        stride: u32,
+        /// The number of threads this worker should run
+        threads: u32,
+    },
+    /// Stop searching
+    Cancel,
+}
+
+fn send<T: serde::Serialize>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    stream.flush()
+}
+
+fn recv<T: serde::de::DeserializeOwned>(reader: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+#[derive(Debug, Clone)]
+/// A verified proof found by the cluster
+pub struct ClusterProof {
+    /// The nonce
+    pub nonce: u64,
+    /// The mCaptcha result string
+    pub result: String,
+    /// The address of the worker that found the proof
+    pub worker: std::net::SocketAddr,
+    /// The nonces attempted by all workers
+    pub attempted_nonces: u64,
+    /// The time from dispatch to proof
+    pub elapsed: Duration,
+}
+
+/// Accepts `workers` connections on `listener`, shards `challenge` across them and returns the first valid proof.
+///
+/// `on_progress` is called with the total attempted nonces every time a worker reports progress.
+/// Returns `None` if every worker exhausted its keyspace or disconnected.
+pub fn coordinate(
+    listener: &TcpListener,
+    workers: usize,
+    challenge: &McaptchaChallengeDescriptor,
+    mut on_progress: impl FnMut(u64),
+) -> io::Result<Option<ClusterProof>> {
+    let mut connections = Vec::with_capacity(workers);
+    let mut stride = 0u32;
+    while connections.len() < workers {
+        let (stream, addr) = listener.accept()?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        match recv(&mut reader)? {
+            Some(WorkerMessage::Hello { threads }) if threads > 0 => {
+                connections.push((addr, stream, reader, stride, threads));
+                stride = stride
+                    .checked_add(threads)
+                    .ok_or_else(|| io::Error::other("too many worker threads"))?;
+            }
+            _ => continue,
+        }
+    }
+
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    let mut writers = Vec::with_capacity(workers);
+    for (index, (addr, mut stream, mut reader, first_bank, threads)) in
+        connections.into_iter().enumerate()
+    {
+        send(
+            &mut stream,
+            &CoordinatorMessage::Job {
+                challenge: challenge.clone(),
+                first_bank,
+                stride,
+                threads,
+            },
+        )?;
+        writers.push(stream);
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            loop {
+                let message = recv::<WorkerMessage>(&mut reader).ok().flatten();
+                let done = !matches!(message, Some(WorkerMessage::Progress { .. }));
+                if tx.send((index, addr, message)).is_err() || done {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let mut attempted = vec![0u64; workers];
+    let mut proof = None;
+    for (index, addr, message) in rx {
+        match message {
+            Some(WorkerMessage::Progress { attempted_nonces }) => {
+                attempted[index] = attempted_nonces;
+                on_progress(attempted.iter().sum());
+            }
+            Some(WorkerMessage::Solved {
+                nonce,
+                attempted_nonces,
+                ..
+            }) => {
+                attempted[index] = attempted_nonces;
+                // the worker's own result is not trusted, it is recomputed with the proof
+                let verification = challenge.verify(nonce);
+                if verification.is_sufficient() {
+                    proof = Some(ClusterProof {
+                        nonce,
+                        result: verification.result.to_string(),
+                        worker: addr,
+                        attempted_nonces: 0,
+                        elapsed: start.elapsed(),
+                    });
+                    break;
+                }
+            }
+            Some(WorkerMessage::Exhausted { attempted_nonces }) => {
+                attempted[index] = attempted_nonces;
+            }
+            Some(WorkerMessage::Hello { .. }) | None => {}
+        }
+    }
+
+    for mut writer in writers {
+        send(&mut writer, &CoordinatorMessage::Cancel).ok();
+    }
+
+    Ok(proof.map(|proof| ClusterProof {
+        attempted_nonces: attempted.iter().sum(),
+        ..proof
+    }))
+}
+
+/// Connects to a coordinator, solves the assigned slice of the keyspace on `threads` threads and reports back.
+pub fn work(coordinator: impl ToSocketAddrs, threads: u32) -> io::Result<()> {
+    assert!(threads > 0, "threads must be positive");
+    let mut stream = TcpStream::connect(coordinator)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    send(&mut stream, &WorkerMessage::Hello { threads })?;
+
+    let Some(CoordinatorMessage::Job {
+        challenge,
+        first_bank,
+        stride,
+        threads,
+    }) = recv(&mut reader)?
+    else {
+        return Ok(());
+    };
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let cancel = cancel.clone();
+        std::thread::spawn(move || {
+            // any message after the job, or a closed connection, stops the search
+            recv::<CoordinatorMessage>(&mut reader).ok();
+            cancel.store(true, Ordering::Relaxed);
+        });
+    }
+
+    let attempted = Arc::new(AtomicU64::new(0));
+    let (tx, rx) = mpsc::channel();
+    for thread in 0..threads {
+        let challenge = challenge.clone();
+        let cancel = cancel.clone();
+        let attempted = attempted.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let mut reported = 0;
+            let (result, total) = challenge.solve_banks_with_limit(
+                first_bank + thread,
+                stride,
+                u64::MAX,
+                |_, so_far| {
+                    attempted.fetch_add(so_far - reported, Ordering::Relaxed);
+                    reported = so_far;
+                    match cancel.load(Ordering::Relaxed) {
+                        true => ControlFlow::Break(()),
+                        false => ControlFlow::Continue(()),
+                    }
+                },
+            );
+            attempted.fetch_add(total - reported, Ordering::Relaxed);
+            tx.send(result).ok();
+        });
+    }
+    drop(tx);
+
+    let mut remaining = threads;
+    loop {
+        match rx.recv_timeout(PROGRESS_INTERVAL) {
+            Ok(Some((nonce, result))) => {
+                cancel.store(true, Ordering::Relaxed);
+                return send(
+                    &mut stream,
+                    &WorkerMessage::Solved {
+                        nonce,
+                        result: crate::extract128_be(result).to_string(),
+                        attempted_nonces: attempted.load(Ordering::Relaxed),
+                    },
+                );
+            }
+            Ok(None) => {
+                remaining -= 1;
+                if remaining == 0 {
+                    if cancel.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    return send(
+                        &mut stream,
+                        &WorkerMessage::Exhausted {
+                            attempted_nonces: attempted.load(Ordering::Relaxed),
+                        },
+                    );
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) if !cancel.load(Ordering::Relaxed) => send(
+                &mut stream,
+                &WorkerMessage::Progress {
+                    attempted_nonces: attempted.load(Ordering::Relaxed),
+                },
+            )?,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback_cluster() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // the losing worker only notices the cancellation at its next search bank, so it is not joined
+        for threads in 1..=2 {
+            std::thread::spawn(move || work(addr, threads));
+        }
+
+        let challenge = McaptchaChallengeDescriptor {
+            salt: "x".repeat(32),
+            string: "distributed".into(),
+            difficulty_factor: 1_000_000,
+        };
+        let proof = coordinate(&listener, 2, &challenge, |_| {})
+            .unwrap()
+            .expect("no proof found");
+        assert!(challenge.verify(proof.nonce).is_sufficient());
+        assert_eq!(
+            challenge.verify(proof.nonce).result.to_string(),
+            proof.result
+        );
+    }
+}
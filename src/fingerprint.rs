@@ -0,0 +1,121 @@
+//! Scoring how likely a submitted decimal nonce was found by this crate rather than by a sequential solver.
+//!
+//! The decimal solvers append a fixed padding to the prefix, starting with a '1' followed by the search bank digits,
+//! and search a 9 digit mutating part after it. The padding only depends on the prefix length and the search bank,
+//! so it can be rebuilt from the prefix and the nonce alone. The official solvers count up from 0 instead,
+//! so their nonces rarely get much larger than the difficulty.
+//!
+//! The lane ID digits at the start of the mutating part differ between backends and are not scored.
+//! Nonces stretched for IEEE 754 double precision floats are not recognized.
+use alloc::string::ToString;
+
+use crate::message::DecimalMessage;
+
+/// the number of values of the mutating part, which never has a leading zero when unpadded
+const MUTATING_SPACE: f64 = 900_000_000.0;
+
+/// The fingerprint of a submitted proof
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProofFingerprint {
+    /// The search bank whose padding matches the digits before the mutating part, if any
+    pub search_bank: Option<u32>,
+    /// The natural log of the probability that a solver counting up from 0 submits exactly this nonce
+    pub sequential_log_likelihood: f64,
+    /// The probability that the proof was produced by this crate, assuming an even prior
+    pub score: f64,
+}
+
+/// Rebuild the search bank from the digits before the last 9, if they are the padding this crate emits for the prefix
+fn search_bank(prefix: &[u8], nonce: u64) -> Option<u32> {
+    let digits = nonce.to_string();
+    let padding = digits.as_bytes().get(..digits.len().checked_sub(9)?)?;
+    let search_bank = match padding.split_first() {
+        None => 0,
+        Some((b'1', bank_digits)) => bank_digits.iter().rev().try_fold(0u32, |bank, digit| {
+            bank.checked_mul(10)?.checked_add((digit - b'0') as u32)
+        })?,
+        Some(_) => return None,
+    };
+    let nonce_addend = match DecimalMessage::new(prefix, search_bank)? {
+        DecimalMessage::SingleBlock(message) => message.nonce_addend,
+        DecimalMessage::DoubleBlock(message) => message.nonce_addend,
+    };
+    (nonce_addend == nonce - nonce % 1_000_000_000).then_some(search_bank)
+}
+
+/// Score a nonce submitted for a prefix at a difficulty, the expected number of attempts per solution
+/// (the mCaptcha difficulty factor, or 16 to the power of the Anubis difficulty).
+pub fn analyze(prefix: &[u8], nonce: u64, difficulty: u64) -> ProofFingerprint {
+    let search_bank = search_bank(prefix, nonce);
+
+    // a sequential solver stops at the first solution, so the nonce is geometrically distributed
+    let sequential_log_likelihood = match difficulty {
+        0 | 1 if nonce == 0 => 0.0,
+        0 | 1 => f64::NEG_INFINITY,
+        _ => {
+            let p = 1.0 / difficulty as f64;
+            p.ln() + nonce as f64 * (-p).ln_1p()
+        }
+    };
+    // this crate picks any of the values of the mutating part with about equal probability
+    let crate_log_likelihood = match search_bank {
+        Some(_) => -MUTATING_SPACE.ln(),
+        None => f64::NEG_INFINITY,
+    };
+    let score = if crate_log_likelihood == f64::NEG_INFINITY {
+        0.0
+    } else {
+        1.0 / (1.0 + (sequential_log_likelihood - crate_log_likelihood).exp())
+    };
+
+    ProofFingerprint {
+        search_bank,
+        sequential_log_likelihood,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecimalSolver, compute_target_mcaptcha, solver::Solver};
+
+    #[test]
+    fn test_analyze() {
+        const DIFFICULTY: u64 = 50_000;
+
+        for prefix_len in [0, 16, 33, 49, 60, 100] {
+            let prefix = vec![b'x'; prefix_len];
+            let mut solved = 0;
+            for search_bank in [0, 3, 42] {
+                let Some(message) = DecimalMessage::new(&prefix, search_bank) else {
+                    continue;
+                };
+                let mut solver = DecimalSolver::from(message);
+                let (nonce, _) = solver
+                    .solve::<{ crate::solver::SOLVE_TYPE_GT }>(
+                        compute_target_mcaptcha(DIFFICULTY),
+                        !0,
+                    )
+                    .expect("solver failed");
+                let fingerprint = analyze(&prefix, nonce, DIFFICULTY);
+                assert_eq!(fingerprint.search_bank, Some(search_bank), "{}", nonce);
+                assert!(fingerprint.score > 0.99, "{:?}", fingerprint);
+                solved += 1;
+            }
+            assert!(solved > 0);
+
+            // what a sequential solver would submit
+            for nonce in [0, 1, 12_345, 99_999_999] {
+                let fingerprint = analyze(&prefix, nonce, DIFFICULTY);
+                assert_eq!(fingerprint.search_bank, None);
+                assert_eq!(fingerprint.score, 0.0);
+            }
+        }
+
+        // an unpadded nonce is plausible for a sequential solver when the difficulty is as large
+        let fingerprint = analyze(&[b'x'; 40], 123_456_789, 100_000_000);
+        assert_eq!(fingerprint.search_bank, Some(0));
+        assert!(fingerprint.score < 0.5, "{:?}", fingerprint);
+    }
+}
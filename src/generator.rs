@@ -0,0 +1,218 @@
+use core::ops::RangeInclusive;
+
+use alloc::{string::String, vec::Vec};
+
+use crate::adapter::McaptchaChallengeDescriptor;
+
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Difficulty levels of mCaptcha's default difficulty strategy (average, peak sustainable, "broke my site").
+pub const MCAPTCHA_DEFAULT_LEVELS: &[u32] = &[50_000, 3_000_000, 5_000_000];
+
+#[derive(Debug, Clone)]
+/// How difficulty factors are drawn.
+pub enum DifficultyDistribution {
+    /// Always the same difficulty factor.
+    Fixed(u32),
+    /// Uniformly distributed over the range.
+    Uniform(RangeInclusive<u32>),
+    /// Log-uniformly distributed over the range, so every order of magnitude is equally likely.
+    LogUniform(RangeInclusive<u32>),
+    /// One of the given levels, chosen uniformly.
+    Levels(Vec<u32>),
+}
+
+#[derive(Debug, Clone)]
+/// Synthetic mCaptcha challenge generator configuration.
+pub struct GeneratorConfig {
+    /// Salt length in bytes.
+    pub salt_len: RangeInclusive<usize>,
+    /// Phrase length in bytes.
+    pub phrase_len: RangeInclusive<usize>,
+    /// Difficulty factor distribution.
+    pub difficulty: DifficultyDistribution,
+    /// Draw the salt once and reuse it for every challenge, like a single mCaptcha deployment.
+    pub fixed_salt: bool,
+}
+
+impl GeneratorConfig {
+    /// Mirrors a default mCaptcha deployment: one site-wide salt, 32-character phrases
+    /// and the default difficulty levels.
+    pub fn mcaptcha_default() -> Self {
+        Self {
+            salt_len: 32..=64,
+            phrase_len: 32..=32,
+            difficulty: DifficultyDistribution::Levels(MCAPTCHA_DEFAULT_LEVELS.to_vec()),
+            fixed_salt: true,
+        }
+    }
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            salt_len: 1..=64,
+            phrase_len: 1..=64,
+            difficulty: DifficultyDistribution::LogUniform(1_000..=10_000_000),
+            fixed_salt: false,
+        }
+    }
+}
+
+/// SplitMix64
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in `0..n`
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// uniform in `lo..=hi`
+    fn between(&mut self, lo: u64, hi: u64) -> u64 {
+        if hi <= lo {
+            return lo;
+        }
+        match (hi - lo).checked_add(1) {
+            Some(span) => lo + self.below(span),
+            None => self.next_u64(),
+        }
+    }
+
+    fn alphanumeric(&mut self, len: &RangeInclusive<usize>) -> String {
+        let len = self.between(*len.start() as u64, *len.end() as u64) as usize;
+        (0..len)
+            .map(|_| ALPHANUMERIC[self.below(ALPHANUMERIC.len() as u64) as usize] as char)
+            .collect()
+    }
+}
+
+/// A seeded, reproducible generator of synthetic mCaptcha challenges.
+///
+/// Salts and phrases are alphanumeric. The sequence only depends on the seed and the configuration,
+/// it is meant for benchmarking and fuzzing, not for issuing real challenges.
+pub struct ChallengeGenerator {
+    config: GeneratorConfig,
+    rng: Rng,
+    salt: Option<String>,
+}
+
+impl ChallengeGenerator {
+    /// creates a new generator
+    pub fn new(config: GeneratorConfig, seed: u64) -> Self {
+        assert!(
+            !config.salt_len.is_empty() && !config.phrase_len.is_empty(),
+            "empty length range"
+        );
+        if let DifficultyDistribution::Levels(levels) = &config.difficulty {
+            assert!(!levels.is_empty(), "no difficulty levels");
+        }
+        let mut rng = Rng(seed);
+        let salt = config
+            .fixed_salt
+            .then(|| rng.alphanumeric(&config.salt_len));
+        Self { config, rng, salt }
+    }
+
+    fn difficulty(&mut self) -> u32 {
+        match &self.config.difficulty {
+            DifficultyDistribution::Fixed(d) => *d,
+            DifficultyDistribution::Uniform(range) => {
+                self.rng.between(*range.start() as u64, *range.end() as u64) as u32
+            }
+            DifficultyDistribution::LogUniform(range) => {
+                let lo = (*range.start()).max(1) as f64;
+                let hi = (*range.end()).max(1) as f64;
+                let unit = (self.rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+                let d = (lo.ln() + unit * (hi.ln() - lo.ln())).exp();
+                (d as u32).clamp(*range.start(), *range.end())
+            }
+            DifficultyDistribution::Levels(levels) => {
+                levels[self.rng.below(levels.len() as u64) as usize]
+            }
+        }
+    }
+}
+
+impl Iterator for ChallengeGenerator {
+    type Item = McaptchaChallengeDescriptor;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let salt = match &self.salt {
+            Some(salt) => salt.clone(),
+            None => self.rng.alphanumeric(&self.config.salt_len),
+        };
+        let string = self.rng.alphanumeric(&self.config.phrase_len);
+        Some(McaptchaChallengeDescriptor {
+            salt,
+            string,
+            difficulty_factor: self.difficulty(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generator_reproducible() {
+        let a: Vec<_> = ChallengeGenerator::new(GeneratorConfig::default(), 42)
+            .take(16)
+            .collect();
+        let b: Vec<_> = ChallengeGenerator::new(GeneratorConfig::default(), 42)
+            .take(16)
+            .collect();
+        for (a, b) in a.iter().zip(&b) {
+            assert_eq!(a.salt, b.salt);
+            assert_eq!(a.string, b.string);
+            assert_eq!(a.difficulty_factor, b.difficulty_factor);
+        }
+    }
+
+    #[test]
+    fn test_generator_ranges() {
+        let config = GeneratorConfig {
+            salt_len: 3..=5,
+            phrase_len: 0..=70,
+            difficulty: DifficultyDistribution::LogUniform(1_000..=1_000_000),
+            fixed_salt: false,
+        };
+        for challenge in ChallengeGenerator::new(config, 7).take(1000) {
+            assert!((3..=5).contains(&challenge.salt.len()));
+            assert!(challenge.string.len() <= 70);
+            assert!(challenge.string.bytes().all(|b| b.is_ascii_alphanumeric()));
+            assert!((1_000..=1_000_000).contains(&challenge.difficulty_factor));
+        }
+
+        let mut generator = ChallengeGenerator::new(GeneratorConfig::mcaptcha_default(), 7);
+        let first = generator.next().unwrap();
+        for challenge in generator.take(100) {
+            assert_eq!(challenge.salt, first.salt);
+            assert_eq!(challenge.string.len(), 32);
+            assert!(MCAPTCHA_DEFAULT_LEVELS.contains(&challenge.difficulty_factor));
+        }
+    }
+
+    #[test]
+    fn test_generated_challenges_solve() {
+        let config = GeneratorConfig {
+            difficulty: DifficultyDistribution::Fixed(10_000),
+            ..GeneratorConfig::default()
+        };
+        for challenge in ChallengeGenerator::new(config, 1).take(8) {
+            let (Some((nonce, _)), _) = challenge.solve() else {
+                panic!("solver failed for {:?}", challenge);
+            };
+            assert!(challenge.verify(nonce).is_sufficient());
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Hashcash stamp minting.
+//!
+//! Stamps use the version 1 format `1:bits:date:resource:ext:rand:counter` and are valid when
+//! the SHA-1 digest of the whole stamp starts with `bits` zero bits.
+//!
+//! Construct: counter := padding || LANE_ID || 8 DECIMAL DIGITS, where the padding moves the
+//! counter into a block of its own when the prefix leaves too little room, so every attempt is a
+//! single compression of the final block.
+use alloc::{format, string::String};
+
+use crate::{
+    decimal_counter::{self, LaneKernel},
+    sha1,
+};
+
+/// the lane IDs, one per SIMD lane
+const LANE_IDS: &[u8; 16] = b"ABCDEFGHIJKLMNOP";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A hashcash stamp without its counter.
+pub struct HashcashStamp {
+    /// the required number of leading zero bits
+    pub bits: u32,
+    /// the date in `YYMMDD` (or `YYMMDDhhmm[ss]`) form
+    pub date: String,
+    /// the resource the stamp is minted for, usually an email address
+    pub resource: String,
+    /// the extension field, usually empty
+    pub ext: String,
+    /// the random string making the stamp unique
+    pub rand: String,
+}
+
+impl HashcashStamp {
+    /// parse a version 1 stamp without its counter, `1:bits:date:resource:ext:rand` with an optional trailing `:`
+    pub fn parse(stamp: &str) -> Option<Self> {
+        let mut fields = stamp.strip_suffix(':').unwrap_or(stamp).split(':');
+        if fields.next()? != "1" {
+            return None;
+        }
+        let bits = fields.next()?.parse().ok()?;
+        let date = fields.next()?;
+        let resource = fields.next()?;
+        let ext = fields.next()?;
+        let rand = fields.next()?;
+        if fields.next().is_some() || date.is_empty() || !date.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(Self {
+            bits,
+            date: date.into(),
+            resource: resource.into(),
+            ext: ext.into(),
+            rand: rand.into(),
+        })
+    }
+
+    /// the stamp up to and including the separator before the counter
+    pub fn prefix(&self) -> String {
+        format!(
+            "1:{}:{}:{}:{}:{}:",
+            self.bits, self.date, self.resource, self.ext, self.rand
+        )
+    }
+
+    /// estimate the workload of minting the stamp
+    pub fn estimated_workload(&self) -> u64 {
+        1u64.checked_shl(self.bits).unwrap_or(u64::MAX)
+    }
+
+    /// mint the stamp
+    pub fn mint(&self) -> (Option<String>, u64) {
+        self.mint_with_limit(u64::MAX)
+    }
+
+    /// mint the stamp with a limit
+    ///
+    /// Returns None if the limit or the counter space is exhausted, or more than 64 bits are required.
+    pub fn mint_with_limit(&self, limit: u64) -> (Option<String>, u64) {
+        if self.bits > 64 {
+            return (None, 0);
+        }
+        let kernel = LeadingZeroes {
+            masks: [
+                match self.bits {
+                    0 => 0,
+                    bits @ 1..32 => !0 << (32 - bits),
+                    _ => !0,
+                },
+                match self.bits {
+                    0..=32 => 0,
+                    bits => !0 << (64 - bits),
+                },
+            ],
+        };
+        let prefix = self.prefix();
+        let (counter, attempted_nonces) =
+            decimal_counter::search(&kernel, prefix.as_bytes(), limit);
+        (
+            counter.map(|counter| {
+                let mut stamp = prefix;
+                stamp.push_str(core::str::from_utf8(&counter).expect("counter is ASCII"));
+                stamp
+            }),
+            attempted_nonces,
+        )
+    }
+}
+
+/// the number of leading zero bits of the SHA-1 digest of a stamp
+pub fn leading_zero_bits(stamp: &str) -> u32 {
+    let digest = sha1::digest(stamp.as_bytes());
+    let mut bits = 0;
+    for word in digest {
+        bits += word.leading_zeros();
+        if word != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// check a stamp against the number of bits it claims
+pub fn is_valid(stamp: &str) -> bool {
+    let Some(bits) = stamp
+        .strip_prefix("1:")
+        .and_then(|rest| rest.split(':').next())
+        .and_then(|bits| bits.parse().ok())
+    else {
+        return false;
+    };
+    leading_zero_bits(stamp) >= bits
+}
+
+/// the first two words of the digest must be zero under the masks
+struct LeadingZeroes {
+    masks: [u32; 2],
+}
+
+impl LaneKernel for LeadingZeroes {
+    type Word = u32;
+    type State = [u32; 5];
+
+    const IV: [u32; 5] = sha1::IV;
+    const LENGTH_BYTES: usize = 8;
+    const LANE_ID_BYTES: usize = 1;
+    const LANES: usize = LANE_IDS.len();
+    const LANE_ID_SETS: u32 = 1;
+    const PADDING: u8 = b'0';
+
+    fn digest_block(state: &mut [u32; 5], block: &[u32; 16]) {
+        sha1::digest_block(state, block)
+    }
+
+    fn write_lane_id(_set: u32, lane: usize, out: &mut [u8]) {
+        out[0] = LANE_IDS[lane];
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+    fn search_lanes(
+        &self,
+        midstate: &[u32; 5],
+        block: &[u8],
+        lane_index: usize,
+        _set: u32,
+    ) -> Option<usize> {
+        use core::arch::x86_64::*;
+
+        let masks = self.masks;
+        let block: [u32; 16] = decimal_counter::be_words(block);
+        unsafe {
+            let lane_shift = (3 - lane_index % 4) * 8;
+            let lane_ids: [u32; 16] = core::array::from_fn(|i| (LANE_IDS[i] as u32) << lane_shift);
+            let mut state: [__m512i; 5] =
+                core::array::from_fn(|i| _mm512_set1_epi32(midstate[i] as _));
+            let mut block: [__m512i; 16] =
+                core::array::from_fn(|i| _mm512_set1_epi32(block[i] as _));
+            block[lane_index / 4] = _mm512_or_si512(
+                block[lane_index / 4],
+                _mm512_loadu_si512(lane_ids.as_ptr() as *const _),
+            );
+
+            sha1::avx512::multiway_arx(&mut state, &mut block);
+
+            let a = _mm512_add_epi32(state[0], _mm512_set1_epi32(midstate[0] as _));
+            let b = _mm512_add_epi32(state[1], _mm512_set1_epi32(midstate[1] as _));
+            let met = _mm512_testn_epi32_mask(a, _mm512_set1_epi32(masks[0] as _))
+                & _mm512_testn_epi32_mask(b, _mm512_set1_epi32(masks[1] as _));
+            (met != 0).then(|| met.trailing_zeros() as usize)
+        }
+    }
+
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "avx512f")))]
+    fn search_lanes(
+        &self,
+        midstate: &[u32; 5],
+        block: &[u8],
+        lane_index: usize,
+        set: u32,
+    ) -> Option<usize> {
+        let masks = self.masks;
+        (0..Self::LANES).find(|&lane| {
+            let mut state = *midstate;
+            sha1::digest_block(
+                &mut state,
+                &decimal_counter::lane_block::<Self>(block, lane_index, set, lane),
+            );
+            state[0] & masks[0] == 0 && state[1] & masks[1] == 0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_mint_stamp() {
+        let stamp = |len, bits| HashcashStamp {
+            bits,
+            date: "251016".to_string(),
+            resource: "x".repeat(len),
+            ext: String::new(),
+            rand: "c2FsdA".to_string(),
+        };
+        decimal_counter::tests::test_leading_zero_solver(
+            64,
+            |len, bits| {
+                let (minted, attempted) = stamp(len, bits).mint();
+                if let Some(minted) = &minted {
+                    assert!(minted.starts_with(&stamp(len, bits).prefix()));
+                    assert!(is_valid(minted), "{}", minted);
+                }
+                (minted, attempted)
+            },
+            |_, minted| leading_zero_bits(minted),
+        );
+    }
+
+    #[test]
+    fn test_stamp_validation() {
+        // the example stamp from hashcash.org
+        assert!(is_valid(
+            "1:20:1303030600:adam@cypherspace.org::McMybZIhxKXu57jd:ckvi"
+        ));
+        assert_eq!(
+            leading_zero_bits("1:20:1303030600:adam@cypherspace.org::McMybZIhxKXu57jd:ckvi"),
+            20
+        );
+        assert!(!is_valid(
+            "1:20:1303030600:adam@cypherspace.org::McMybZIhxKXu57jd:ckvj"
+        ));
+        assert!(!is_valid("0:20:garbage"));
+    }
+
+    #[test]
+    fn test_parse_stamp() {
+        let stamp = HashcashStamp::parse("1:20:1303030600:adam@cypherspace.org::McMybZIhxKXu57jd:")
+            .expect("stamp is valid");
+        assert_eq!(stamp.bits, 20);
+        assert_eq!(stamp.resource, "adam@cypherspace.org");
+        assert_eq!(
+            stamp.prefix(),
+            "1:20:1303030600:adam@cypherspace.org::McMybZIhxKXu57jd:"
+        );
+        assert!(
+            HashcashStamp::parse("1:20:1303030600:adam@cypherspace.org::McMybZIhxKXu57jd:ckvi")
+                .is_none()
+        );
+        assert!(HashcashStamp::parse("0:20:1303030600:x::y").is_none());
+    }
+}
@@ -22,22 +22,87 @@ pub mod server;
 mod wasm_ffi;
 
 /// String manipulation functions
-#[cfg(any(target_feature = "avx512f", target_feature = "avx2"))]
+#[cfg(any(
+    target_feature = "avx512f",
+    target_feature = "avx2",
+    all(target_arch = "x86_64", feature = "multiversion")
+))]
 mod strings;
 
 /// SHA-256 primitives
 mod sha256;
 
+/// Unrolled round loops of the 80 round SHA-1 and SHA-512 cores
+#[cfg(all(
+    feature = "alloc",
+    target_arch = "x86_64",
+    any(doc, target_feature = "avx512f")
+))]
+#[macro_use]
+mod loop_macros;
+
+/// SHA-1 primitives
+#[cfg(feature = "alloc")]
+mod sha1;
+
+/// Decimal counter search shared by the leading zero schemes
+#[cfg(feature = "alloc")]
+mod decimal_counter;
+
+/// BLAKE2b primitives
+#[cfg(feature = "adapter")]
+mod blake2b;
+
+#[cfg(feature = "adapter")]
+/// Argon2id primitives and PoW solving
+pub mod argon2;
+
 /// Message builders
 pub mod message;
 
 /// Solvers
 pub mod solver;
 
+#[cfg(feature = "alloc")]
+/// Generic description of decimal nonce PoW schemes
+pub mod scheme;
+
+#[cfg(feature = "alloc")]
+/// Hashcash stamp minting
+pub mod hashcash;
+
+#[cfg(feature = "alloc")]
+/// SHA-256d (double SHA-256) PoW solving
+pub mod sha256d;
+
+#[cfg(feature = "alloc")]
+/// SHA-512 primitives and PoW solving
+pub mod sha512;
+
 #[cfg(feature = "adapter")]
 /// Adapters for end-to-end PoW solving
 pub mod adapter;
 
+#[cfg(all(feature = "adapter", feature = "std"))]
+/// Synthetic challenge generators for benchmarking and fuzzing
+pub mod generator;
+
+#[cfg(feature = "distributed")]
+/// Distributed solving of a single challenge over TCP
+pub mod distributed;
+
+#[cfg(feature = "std")]
+/// CPU topology detection for the default number of solver threads
+pub mod topology;
+
+#[cfg(feature = "std")]
+/// Hash rate capping for emulating slower client hardware
+pub mod throttle;
+
+#[cfg(feature = "std")]
+/// Detection of proofs produced by this crate
+pub mod fingerprint;
+
 #[cfg(all(
     not(doc),
     not(any(target_arch = "x86_64", target_arch = "x86")),
@@ -169,6 +234,15 @@ cfg_if::cfg_if! {
     }
 }
 
+/// Hybrid AVX-512 + SHA-NI go-away solver
+#[cfg(all(
+    target_arch = "x86_64",
+    target_feature = "avx512f",
+    target_feature = "sha",
+    feature = "hybrid-sha-ni"
+))]
+pub type HybridGoAwaySolver = crate::solver::hybrid::GoAwaySolver;
+
 #[cfg(not(any(target_arch = "x86_64", target_arch = "x86")))]
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "wasm32")] {
@@ -10,12 +10,19 @@ use std::{
 use clap::{Parser, Subcommand};
 
 use pow_buster::{
-    DecimalSolver, DoubleBlockSolver, GoAwaySolver, SingleBlockSolver, compute_target_anubis,
-    compute_target_mcaptcha,
+    DecimalSolver, DoubleBlockSolver, GoAwaySolver, SingleBlockSolver,
+    adapter::McaptchaChallengeDescriptor,
+    compute_target_anubis, compute_target_mcaptcha,
     message::{DecimalMessage, GoAwayMessage},
     solver::Solver,
+    throttle::HashrateCap,
 };
 
+mod thermal;
+
+#[cfg(feature = "tui")]
+mod tui;
+
 #[derive(Parser)]
 struct Cli {
     #[clap(subcommand)]
@@ -43,8 +50,725 @@ impl std::str::FromStr for ApiType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    #[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+    Avx512,
+    #[cfg(all(
+        any(target_arch = "x86_64", target_arch = "x86"),
+        target_feature = "sha"
+    ))]
+    ShaNi,
+    Safe,
+    /// Argon2id in place of SHA-256 at the same expected workload, for comparing memory hard
+    /// defenses in the bench, its solutions are not valid mCaptcha proofs
+    Argon2(pow_buster::argon2::Params),
+}
+
+impl Backend {
+    const AVAILABLE: &'static [Backend] = &[
+        #[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+        Backend::Avx512,
+        #[cfg(all(
+            any(target_arch = "x86_64", target_arch = "x86"),
+            target_feature = "sha"
+        ))]
+        Backend::ShaNi,
+        Backend::Safe,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            #[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+            Backend::Avx512 => "avx512",
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                target_feature = "sha"
+            ))]
+            Backend::ShaNi => "sha-ni",
+            Backend::Safe => "safe",
+            Backend::Argon2(_) => "argon2",
+        }
+    }
+
+    /// the available backend the autotuner measures fastest on this CPU, the winner is cached across runs
+    fn autotuned() -> Backend {
+        let winner = pow_buster::solver::autotune::autotune();
+        Backend::AVAILABLE
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == winner.name())
+            .unwrap_or(Backend::AVAILABLE[0])
+    }
+
+    /// solve an mCaptcha challenge on this backend, returns the solution and the attempted nonces
+    fn solve_mcaptcha(
+        self,
+        descriptor: &McaptchaChallengeDescriptor,
+    ) -> (Option<(u64, [u32; 8])>, u64) {
+        macro_rules! solve_with {
+            ($solver:ty) => {{
+                let target = compute_target_mcaptcha(descriptor.difficulty_factor as u64);
+                let prefix = descriptor.prefix();
+                let mut result = None;
+                let mut attempted_nonces = 0;
+                for search_bank in 0.. {
+                    let Some(message) = DecimalMessage::new(&prefix, search_bank) else {
+                        break;
+                    };
+                    let mut solver = <$solver>::from(message);
+                    result = solver.solve::<{ pow_buster::solver::SOLVE_TYPE_GT }>(target, !0);
+                    attempted_nonces += solver.get_attempted_nonces();
+                    if result.is_some() {
+                        break;
+                    }
+                }
+                (result, attempted_nonces)
+            }};
+        }
+
+        match self {
+            #[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+            Backend::Avx512 => solve_with!(pow_buster::solver::avx512::DecimalSolver),
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                target_feature = "sha"
+            ))]
+            Backend::ShaNi => solve_with!(pow_buster::solver::sha_ni::DecimalSolver),
+            Backend::Safe => solve_with!(pow_buster::solver::safe::DecimalSolver),
+            Backend::Argon2(params) => {
+                let challenge = pow_buster::argon2::Argon2Challenge {
+                    params,
+                    salt: descriptor.salt.as_bytes().to_vec(),
+                    prefix: descriptor.string.as_bytes().to_vec(),
+                    tag_length: 32,
+                    solve_type: pow_buster::solver::SOLVE_TYPE_GT,
+                    target: compute_target_mcaptcha(descriptor.difficulty_factor as u64),
+                };
+                let (nonce, attempted_nonces) = challenge.solve();
+                // the tag is not a SHA-256 result, report no hash
+                (nonce.map(|nonce| (nonce, [0; 8])), attempted_nonces)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(Backend::autotuned());
+        }
+        Backend::AVAILABLE
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "invalid or unavailable backend: {} (available: auto, {})",
+                    s,
+                    Backend::AVAILABLE
+                        .iter()
+                        .map(|backend| backend.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// (label, salt length, phrase length) of the standardized bench prefixes
+const BENCH_PREFIX_SHAPES: &[(&str, usize, usize)] = &[
+    // 40 bytes, solved in the final block
+    ("single-block", 16, 16),
+    // 49 bytes (mCaptcha's default 33-byte salt), requires the double block solver
+    ("double-block", 33, 8),
+];
+
+struct BenchResult {
+    backend: Backend,
+    shape: &'static str,
+    difficulty: u32,
+    latencies: Vec<Duration>,
+    attempted_nonces: u64,
+    thermal: Option<thermal::Report>,
+}
+
+/// `samples` synthetic challenges of a standardized bench prefix shape
+fn synthetic_challenges(
+    shape: &str,
+    difficulty: u32,
+    samples: u32,
+) -> Vec<McaptchaChallengeDescriptor> {
+    let &(_, salt_len, phrase_len) = BENCH_PREFIX_SHAPES
+        .iter()
+        .find(|(label, _, _)| *label == shape)
+        .expect("unknown prefix shape");
+    (0..samples)
+        .map(|i| McaptchaChallengeDescriptor {
+            salt: "x".repeat(salt_len),
+            string: format!("{:0>width$}", i, width = phrase_len),
+            difficulty_factor: difficulty,
+        })
+        .collect()
+}
+
+/// reads a JSON lines challenge corpus, as written by `record` or `generate`, grouped by difficulty
+fn read_corpus(path: &str) -> Vec<(u32, Vec<McaptchaChallengeDescriptor>)> {
+    use std::io::BufRead;
+
+    let file = std::fs::File::open(path).expect("failed to open corpus");
+    let mut groups = std::collections::BTreeMap::<u32, Vec<_>>::new();
+    for (i, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line.expect("failed to read corpus");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let challenge: McaptchaChallengeDescriptor = serde_json::from_str(&line)
+            .unwrap_or_else(|e| panic!("invalid challenge on line {}: {}", i + 1, e));
+        assert!(
+            challenge.difficulty_factor > 0,
+            "difficulty must be positive (line {})",
+            i + 1
+        );
+        groups
+            .entry(challenge.difficulty_factor)
+            .or_default()
+            .push(challenge);
+    }
+    assert!(!groups.is_empty(), "corpus is empty");
+    groups.into_iter().collect()
+}
+
+impl BenchResult {
+    fn run(backend: Backend, shape: &'static str, difficulty: u32, samples: u32) -> Self {
+        let challenges = synthetic_challenges(shape, difficulty, samples);
+        Self::run_with(backend, shape, difficulty, &challenges, None, |_, _| {})
+    }
+
+    /// solves every challenge in order, calling `on_sample` with the latency and attempted nonces of every sample
+    ///
+    /// With a hash rate cap, every solve is followed by the idle time a client at that hash rate would need.
+    fn run_with(
+        backend: Backend,
+        shape: &'static str,
+        difficulty: u32,
+        challenges: &[McaptchaChallengeDescriptor],
+        hashrate_cap: Option<HashrateCap>,
+        mut on_sample: impl FnMut(Duration, u64),
+    ) -> Self {
+        let mut latencies = Vec::with_capacity(challenges.len());
+        let mut attempted_nonces = 0;
+        let sampler = thermal::Sampler::start();
+        for descriptor in challenges {
+            let start = Instant::now();
+            let (result, attempted) = match hashrate_cap {
+                Some(cap) => cap.run(|| backend.solve_mcaptcha(descriptor)),
+                None => backend.solve_mcaptcha(descriptor),
+            };
+            let latency = start.elapsed();
+            latencies.push(latency);
+            core::hint::black_box(result.expect("solver failed"));
+            attempted_nonces += attempted;
+            on_sample(latency, attempted);
+        }
+        Self {
+            backend,
+            shape,
+            difficulty,
+            latencies,
+            attempted_nonces,
+            thermal: sampler.finish(),
+        }
+    }
+
+    fn throttled(&self) -> bool {
+        self.thermal.is_some_and(|thermal| thermal.throttled)
+    }
+
+    fn total(&self) -> Duration {
+        self.latencies.iter().sum()
+    }
+
+    fn mean(&self) -> Duration {
+        self.total() / self.latencies.len().max(1) as u32
+    }
+
+    fn max(&self) -> Duration {
+        self.latencies.iter().max().copied().unwrap_or_default()
+    }
+
+    fn hashrate(&self) -> f64 {
+        self.attempted_nonces as f64 / self.total().as_secs_f64()
+    }
+
+    fn id(&self) -> String {
+        format!("{}/{}/{}", self.backend.name(), self.shape, self.difficulty)
+    }
+
+    fn sorted_ns(&self) -> Vec<f64> {
+        let mut ns: Vec<f64> = self.latencies.iter().map(|l| l.as_nanos() as f64).collect();
+        ns.sort_by(f64::total_cmp);
+        ns
+    }
+
+    /// a `benchmark-complete` message in cargo-criterion's `--message-format=json` format
+    fn criterion_message(&self) -> serde_json::Value {
+        let ns = self.sorted_ns();
+        let (min, max) = (ns[0], ns[ns.len() - 1]);
+        let mean = ns.iter().sum::<f64>() / ns.len() as f64;
+        let median = ns[ns.len() / 2];
+        let estimate = |estimate: f64| {
+            serde_json::json!({
+                "estimate": estimate,
+                "lower_bound": min,
+                "upper_bound": max,
+                "unit": "ns",
+            })
+        };
+        serde_json::json!({
+            "reason": "benchmark-complete",
+            "id": self.id(),
+            "report_directory": null,
+            "iteration_count": vec![1; ns.len()],
+            "measured_values": ns,
+            "unit": "ns",
+            "throughput": [{
+                "per_iteration": self.attempted_nonces / self.latencies.len() as u64,
+                "unit": "elements",
+            }],
+            "typical": estimate(mean),
+            "mean": estimate(mean),
+            "median": estimate(median),
+            "median_abs_dev": null,
+            "slope": null,
+            "change": null,
+        })
+    }
+
+    /// a libtest `--bench` output line, as parsed by bencher-style dashboards
+    fn bencher_line(&self) -> String {
+        let ns = self.sorted_ns();
+        let mean = (ns.iter().sum::<f64>() / ns.len() as f64) as u64;
+        let range = (ns[ns.len() - 1] - ns[0]) as u64;
+        format!(
+            "test {} ... bench: {:>15} ns/iter (+/- {})",
+            self.id(),
+            thousands(mean),
+            thousands(range)
+        )
+    }
+}
+
+/// output format of the bench subcommand
+#[derive(Clone, Copy)]
+enum BenchFormat {
+    Table,
+    Criterion,
+    Bencher,
+}
+
+impl std::str::FromStr for BenchFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(BenchFormat::Table),
+            "criterion" => Ok(BenchFormat::Criterion),
+            "bencher" => Ok(BenchFormat::Bencher),
+            _ => Err(format!("invalid bench format: {}", s)),
+        }
+    }
+}
+
+/// file format of the load subcommand report
+#[cfg(feature = "client")]
+#[derive(Clone, Copy)]
+enum ReportFormat {
+    Json,
+    Csv,
+}
+
+#[cfg(feature = "client")]
+impl std::str::FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            _ => Err(format!("invalid report format: {}", s)),
+        }
+    }
+}
+
+/// formats a unix timestamp as a `YYMMDD` UTC date
+fn yymmdd(unix_secs: u64) -> String {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (unix_secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:02}{:02}{:02}", year % 100, month, day)
+}
+
+/// builds an HTTP client, optionally routed through a proxy
+#[cfg(feature = "client")]
+fn proxied(builder: reqwest::ClientBuilder, proxy: Option<&str>) -> reqwest::Client {
+    let builder = match proxy {
+        Some(proxy) => pow_buster::client::with_proxy(builder, proxy).expect("invalid proxy URL"),
+        None => builder,
+    };
+    builder.build().unwrap()
+}
+
+/// applies `--header "Name: value"` and `--user-agent` options to a client builder
+#[cfg(feature = "client")]
+fn with_cli_headers(
+    builder: reqwest::ClientBuilder,
+    header: &[String],
+    user_agent: Option<&str>,
+) -> reqwest::ClientBuilder {
+    let headers: Vec<_> = header
+        .iter()
+        .map(|header| {
+            let (name, value) = header
+                .split_once(':')
+                .unwrap_or_else(|| panic!("invalid header, expected \"Name: value\": {}", header));
+            (name.trim().to_string(), value.trim().to_string())
+        })
+        .collect();
+    let builder = pow_buster::client::with_headers(builder, &headers).unwrap();
+    match user_agent {
+        Some(user_agent) => builder.user_agent(user_agent),
+        None => builder,
+    }
+}
+
+/// applies `--client-cert`, `--client-key` and `--ca-cert` options to a client builder
+#[cfg(feature = "client")]
+#[cfg_attr(
+    not(any(feature = "rustls-tls", feature = "native-tls")),
+    allow(unused_mut)
+)]
+fn with_cli_tls(
+    mut builder: reqwest::ClientBuilder,
+    client_cert: Option<&std::path::Path>,
+    client_key: Option<&std::path::Path>,
+    ca_cert: Option<&std::path::Path>,
+) -> reqwest::ClientBuilder {
+    #[cfg(not(any(feature = "rustls-tls", feature = "native-tls")))]
+    assert!(
+        client_cert.is_none() && client_key.is_none() && ca_cert.is_none(),
+        "built without TLS support"
+    );
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    {
+        if let (Some(cert), Some(key)) = (client_cert, client_key) {
+            builder = pow_buster::client::with_client_certificate(
+                builder,
+                &std::fs::read(cert).expect("failed to read client certificate"),
+                &std::fs::read(key).expect("failed to read client key"),
+            )
+            .expect("invalid client certificate");
+        }
+        if let Some(ca_cert) = ca_cert {
+            builder = pow_buster::client::with_root_certificate(
+                builder,
+                &std::fs::read(ca_cert).expect("failed to read CA certificate"),
+            )
+            .expect("invalid CA certificate");
+        }
+    }
+    builder
+}
+
+/// formats an integer with comma thousands separators like libtest
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// difficulty used to measure hashrate in `calibrate`
+const CALIBRATION_DIFFICULTY: u32 = 1_000_000;
+
+/// approximate number of nonces in one search bank of a decimal message
+const SEARCH_BANK_KEYSPACE: f64 = 80e7;
+
+/// a challenge line read in `solve --stdio` mode
+#[derive(serde::Deserialize)]
+struct StdioRequest {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    challenge: McaptchaChallengeDescriptor,
+}
+
+/// a proof line written in `solve --stdio` mode, in the same order as the requests
+#[derive(serde::Serialize)]
+struct StdioResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    outcome: StdioOutcome,
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum StdioOutcome {
+    Solved { nonce: u64, result: String },
+    Error { error: String },
+}
+
 #[derive(Subcommand)]
 enum SubCommand {
+    Bench {
+        #[clap(short, long, num_args = 1.., default_values_t = [100_000u32, 1_000_000, 5_000_000])]
+        difficulty: Vec<u32>,
+
+        #[clap(short, long, default_value = "10")]
+        samples: u32,
+
+        #[clap(
+            long,
+            help = "backends to run (default: all available), auto runs the one the autotuner measures fastest, argon2 compares against Argon2id at the same workload"
+        )]
+        backend: Vec<String>,
+
+        #[clap(
+            long,
+            default_value = "1",
+            help = "Argon2id passes of the argon2 backend"
+        )]
+        argon2_time: u32,
+
+        #[clap(
+            long,
+            default_value = "4096",
+            help = "Argon2id memory in KiB of the argon2 backend"
+        )]
+        argon2_kb: u32,
+
+        #[clap(
+            long,
+            default_value = "1",
+            help = "Argon2id lanes of the argon2 backend, each filled on its own thread"
+        )]
+        argon2_lanes: u32,
+
+        #[clap(
+            long,
+            default_value = "table",
+            help = "output format (table, criterion or bencher)"
+        )]
+        format: String,
+
+        #[clap(
+            long,
+            conflicts_with_all = ["difficulty", "samples"],
+            help = "replay the challenges of a JSON lines corpus instead of synthetic ones"
+        )]
+        corpus: Option<String>,
+
+        #[clap(
+            long,
+            help = "cap the hash rate to emulate slower clients, in hashes per second with an optional k, M or G suffix (e.g. 2M)"
+        )]
+        hashrate_cap: Option<String>,
+
+        #[cfg(feature = "tui")]
+        #[clap(long, help = "show a live dashboard while the benchmark runs")]
+        tui: bool,
+    },
+    Generate {
+        #[clap(short, long, default_value = "100")]
+        count: usize,
+
+        #[clap(long, default_value = "0")]
+        seed: u64,
+
+        #[clap(
+            long,
+            default_value = "random",
+            help = "challenge distribution (random or mcaptcha)"
+        )]
+        preset: String,
+
+        #[clap(short, long, help = "use this difficulty for every challenge")]
+        difficulty: Option<u32>,
+    },
+    Calibrate {
+        #[clap(short, long, num_args = 1.., default_values_t = [
+            50_000u32, 100_000, 500_000, 1_000_000, 5_000_000, 10_000_000, 50_000_000, 100_000_000,
+        ])]
+        difficulty: Vec<u32>,
+
+        #[clap(long, default_value = "auto")]
+        backend: String,
+
+        #[clap(
+            long,
+            default_value = "single-block",
+            help = "prefix shape to measure (single-block or double-block)"
+        )]
+        shape: String,
+
+        #[clap(short, long, default_value = "20")]
+        samples: u32,
+
+        #[clap(
+            short,
+            long,
+            default_value = "1",
+            help = "scale the measured hashrate to this many cores"
+        )]
+        threads: u32,
+    },
+    Solve {
+        #[clap(long, required_unless_present = "stdio")]
+        salt: Option<String>,
+
+        #[clap(long, required_unless_present = "stdio")]
+        phrase: Option<String>,
+
+        #[clap(short, long, required_unless_present = "stdio")]
+        difficulty: Option<u32>,
+
+        #[clap(long, default_value = "auto")]
+        backend: String,
+
+        #[clap(long, help = "print the proof as JSON")]
+        json: bool,
+
+        #[clap(
+            long,
+            conflicts_with_all = ["salt", "phrase", "difficulty"],
+            help = "read JSON challenges line by line from stdin and write JSON proofs line by line to stdout"
+        )]
+        stdio: bool,
+    },
+    #[cfg(feature = "distributed")]
+    #[clap(about = "shard one challenge across TCP workers and print the first proof")]
+    ClusterCoordinator {
+        #[clap(long, default_value = "0.0.0.0:9090")]
+        listen: String,
+
+        #[clap(short, long, help = "number of workers to wait for before dispatching")]
+        workers: usize,
+
+        #[clap(long)]
+        salt: String,
+
+        #[clap(long)]
+        phrase: String,
+
+        #[clap(short, long)]
+        difficulty: u32,
+    },
+    #[cfg(feature = "distributed")]
+    #[clap(about = "solve a slice of a challenge for a cluster coordinator")]
+    ClusterWorker {
+        #[clap(long)]
+        connect: String,
+
+        #[clap(
+            short,
+            long,
+            help = "number of solver threads (default: one per logical CPU, or per physical core for AVX-512)"
+        )]
+        threads: Option<u32>,
+    },
+    #[clap(about = "mint a hashcash stamp and print it as an X-Hashcash header")]
+    Hashcash {
+        #[clap(
+            long,
+            help = "the resource to mint the stamp for, usually an email address"
+        )]
+        resource: String,
+
+        #[clap(short, long, default_value = "20")]
+        bits: u32,
+
+        #[clap(long, help = "stamp date as YYMMDD (default: today in UTC)")]
+        date: Option<String>,
+
+        #[clap(long, default_value = "")]
+        ext: String,
+
+        #[clap(
+            long,
+            help = "random string of the stamp (default: derived from the clock)"
+        )]
+        rand: Option<String>,
+    },
+    #[clap(about = "solve a Friendly Captcha puzzle and print the solution the widget submits")]
+    FriendlyCaptcha {
+        #[clap(
+            long,
+            help = "the puzzle as returned by the puzzle endpoint (signature.puzzle)"
+        )]
+        puzzle: String,
+    },
+    #[clap(about = "detect the scheme of a challenge, solve it and print the proof")]
+    Auto {
+        #[clap(
+            long,
+            help = "the challenge payload (JSON, hashcash stamp or Friendly Captcha puzzle), - to read stdin"
+        )]
+        challenge: String,
+
+        #[clap(long, help = "give up after this many attempted nonces")]
+        limit: Option<u64>,
+    },
+    #[clap(about = "solve a haproxy-protection bot check and print the PoW cookie")]
+    HaproxyProtection {
+        #[clap(long, help = "the data-pow attribute (userkey#challenge#signature)")]
+        pow: String,
+
+        #[clap(long, help = "the data-diff attribute")]
+        diff: String,
+
+        #[clap(
+            long,
+            default_value = "sha256",
+            help = "the data-mode attribute (sha256 or argon2)"
+        )]
+        mode: String,
+
+        #[clap(long, help = "the data-time attribute, argon2 mode only")]
+        time: Option<String>,
+
+        #[clap(long, help = "the data-kb attribute, argon2 mode only")]
+        kb: Option<String>,
+    },
+    Verify {
+        #[clap(long)]
+        salt: String,
+
+        #[clap(long)]
+        phrase: String,
+
+        #[clap(long)]
+        nonce: u64,
+
+        #[clap(short, long)]
+        difficulty: u32,
+
+        #[clap(long, help = "also check the result reported alongside the nonce")]
+        result: Option<u128>,
+    },
     #[cfg(feature = "live-throughput-test")]
     Live {
         #[clap(long, default_value = "mcaptcha")]
@@ -61,6 +785,109 @@ enum SubCommand {
 
         #[clap(short, long)]
         n_threads: Option<u32>,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+
+        #[cfg(feature = "tui")]
+        #[clap(long, help = "show a live dashboard instead of periodic log lines")]
+        tui: bool,
+    },
+    #[cfg(feature = "live-throughput-test")]
+    #[clap(about = "open-loop mCaptcha load test at a fixed arrival rate")]
+    Load {
+        #[clap(
+            long,
+            default_value = "http://localhost:7000",
+            help = "mCaptcha instance, may be repeated to rotate cycles across instances"
+        )]
+        host: Vec<String>,
+
+        #[clap(
+            long,
+            default_value = "x",
+            help = "site key, may be repeated to rotate cycles across every host and site key pair"
+        )]
+        site_key: Vec<String>,
+
+        #[clap(
+            long,
+            required_unless_present = "profile",
+            help = "config/solve/verify cycles started per second"
+        )]
+        rps: Option<f64>,
+
+        #[clap(long, default_value = "60", help = "test duration in seconds")]
+        duration: u64,
+
+        #[clap(
+            long,
+            conflicts_with_all = ["rps", "duration"],
+            help = "comma separated stages of [warmup:]<seconds>s@<rate>[..<rate>], e.g. warmup:10s@5,120s@5..200"
+        )]
+        profile: Option<String>,
+
+        #[clap(
+            long = "assert",
+            help = "threshold that must hold or the command exits 1, e.g. 'p99(verify) < 200ms' or 'error_rate < 0.1%' (repeatable)"
+        )]
+        assertions: Vec<String>,
+
+        #[clap(
+            long,
+            default_value = "4096",
+            help = "cycles allowed in flight, later arrivals are dropped"
+        )]
+        max_in_flight: usize,
+
+        #[clap(short, long)]
+        n_threads: Option<u32>,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+
+        #[clap(
+            long,
+            help = "local source address to send from, may be repeated to rotate cycles across addresses"
+        )]
+        local_address: Vec<std::net::IpAddr>,
+
+        #[clap(
+            long,
+            help = "write every received difficulty with the offered rate and solve time to this CSV file"
+        )]
+        timeline: Option<std::path::PathBuf>,
+
+        #[clap(
+            long,
+            help = "write the final results with a stable schema to this file, for comparing runs"
+        )]
+        report: Option<std::path::PathBuf>,
+
+        #[clap(
+            long,
+            default_value = "json",
+            requires = "report",
+            help = "format of the report file (json, or csv with one metric per row)"
+        )]
+        report_format: String,
+
+        #[clap(
+            long,
+            requires = "client_key",
+            help = "PEM client certificate chain for mTLS gateways"
+        )]
+        client_cert: Option<std::path::PathBuf>,
+
+        #[clap(
+            long,
+            requires = "client_cert",
+            help = "PEM private key of --client-cert"
+        )]
+        client_key: Option<std::path::PathBuf>,
+
+        #[clap(long, help = "additional PEM root certificate to trust")]
+        ca_cert: Option<std::path::PathBuf>,
     },
     #[cfg(feature = "client")]
     CapJs {
@@ -72,27 +899,158 @@ enum SubCommand {
 
         #[clap(long)]
         num_threads: Option<u32>,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+    },
+    #[cfg(feature = "client")]
+    #[clap(about = "solve the mCaptcha widget on a page and print the verification token")]
+    SolveUrl {
+        #[clap(long)]
+        url: String,
+
+        #[clap(long)]
+        num_threads: Option<u32>,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+
+        #[clap(
+            long,
+            requires = "client_key",
+            help = "PEM client certificate chain for mTLS gateways"
+        )]
+        client_cert: Option<std::path::PathBuf>,
+
+        #[clap(
+            long,
+            requires = "client_cert",
+            help = "PEM private key of --client-cert"
+        )]
+        client_key: Option<std::path::PathBuf>,
+
+        #[clap(long, help = "additional PEM root certificate to trust")]
+        ca_cert: Option<std::path::PathBuf>,
+
+        #[clap(
+            long,
+            help = "extra request header as \"Name: value\", may be repeated"
+        )]
+        header: Vec<String>,
+
+        #[clap(long, help = "User-Agent to send instead of a mobile Firefox one")]
+        user_agent: Option<String>,
+    },
+    #[cfg(feature = "client")]
+    #[clap(
+        about = "check the health of an mCaptcha instance and poll the difficulty of a site key"
+    )]
+    Status {
+        #[clap(long, default_value = "http://localhost:7000")]
+        host: String,
+
+        #[clap(
+            long,
+            help = "also poll the difficulty of this site key, each poll counts as a visit"
+        )]
+        site_key: Option<String>,
+
+        #[clap(long, help = "repeat every this many seconds")]
+        watch: Option<u64>,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+    },
+    #[cfg(feature = "client")]
+    #[clap(about = "record mCaptcha challenges from a server as JSON lines for `bench --corpus`")]
+    Record {
+        #[clap(long, default_value = "http://localhost:7000")]
+        host: String,
+
+        #[clap(long, default_value = "x")]
+        site_key: String,
+
+        #[clap(short, long, default_value = "100")]
+        count: usize,
+
+        #[clap(long, help = "send at most this many config requests per second")]
+        max_rps: Option<f64>,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+
+        #[clap(
+            long,
+            requires = "client_key",
+            help = "PEM client certificate chain for mTLS gateways"
+        )]
+        client_cert: Option<std::path::PathBuf>,
+
+        #[clap(
+            long,
+            requires = "client_cert",
+            help = "PEM private key of --client-cert"
+        )]
+        client_key: Option<std::path::PathBuf>,
+
+        #[clap(long, help = "additional PEM root certificate to trust")]
+        ca_cert: Option<std::path::PathBuf>,
     },
     #[cfg(feature = "client")]
     Anubis {
         #[clap(long, default_value = "http://localhost:8923/")]
         url: String,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+
+        #[clap(
+            long,
+            help = "extra request header as \"Name: value\", may be repeated"
+        )]
+        header: Vec<String>,
+
+        #[clap(long, help = "User-Agent to send instead of a mobile Firefox one")]
+        user_agent: Option<String>,
     },
     #[cfg(feature = "client")]
     GoAway {
         #[clap(long, default_value = "http://localhost:8080/")]
         url: String,
+
+        #[clap(long, help = "HTTP or SOCKS5 proxy URL, e.g. socks5h://127.0.0.1:1080")]
+        proxy: Option<String>,
+
+        #[clap(
+            long,
+            help = "extra request header as \"Name: value\", may be repeated"
+        )]
+        header: Vec<String>,
+
+        #[clap(long, help = "User-Agent to send instead of a mobile Firefox one")]
+        user_agent: Option<String>,
     },
     #[cfg(feature = "server")]
+    #[clap(visible_alias = "serve")]
     Server {
-        #[clap(long, default_value = "127.0.0.1:8080")]
+        #[clap(long, visible_alias = "listen", default_value = "127.0.0.1:8080")]
         addr: String,
 
         #[clap(short, long, default_value = "200000000")]
         limit: u64,
 
-        #[clap(short, long, default_value = "2")]
-        n_workers: usize,
+        #[clap(
+            short,
+            long,
+            help = "number of solver threads (default: one per logical CPU, or per physical core for AVX-512)"
+        )]
+        n_workers: Option<usize>,
+
+        #[clap(
+            long,
+            help = "maximum number of jobs waiting for a worker (default: 16 per worker)"
+        )]
+        queue_capacity: Option<usize>,
 
         #[clap(
             short,
@@ -102,38 +1060,686 @@ enum SubCommand {
         )]
         timeout: u64,
 
-        #[clap(long)]
-        check_origin: Option<String>,
-    },
-    Profile {
-        #[clap(short, long, default_value = "10000000")]
-        difficulty: u64,
+        #[clap(long)]
+        check_origin: Option<String>,
+
+        #[clap(
+            long,
+            help = "require this API key on solve endpoints, may be repeated"
+        )]
+        api_key: Vec<String>,
+
+        #[clap(long, help = "read API keys from a file, one per line")]
+        api_key_file: Option<std::path::PathBuf>,
+
+        #[cfg(feature = "tls")]
+        #[clap(
+            long,
+            requires = "tls_key",
+            help = "PEM certificate chain to serve TLS with"
+        )]
+        tls_cert: Option<std::path::PathBuf>,
+
+        #[cfg(feature = "tls")]
+        #[clap(
+            long,
+            requires = "tls_cert",
+            help = "PEM private key to serve TLS with"
+        )]
+        tls_key: Option<std::path::PathBuf>,
+
+        #[cfg(feature = "cluster")]
+        #[clap(
+            long,
+            conflicts_with = "join",
+            help = "act as a cluster coordinator forwarding mCaptcha jobs to registered workers"
+        )]
+        coordinator: bool,
+
+        #[cfg(feature = "cluster")]
+        #[clap(
+            long,
+            default_value = "50000000",
+            help = "split challenges of at least this difficulty across all workers"
+        )]
+        shard_threshold: u64,
+
+        #[cfg(feature = "cluster")]
+        #[clap(
+            long,
+            requires = "advertise",
+            help = "register as a worker with the coordinator at this URL"
+        )]
+        join: Option<String>,
+
+        #[cfg(feature = "cluster")]
+        #[clap(long, help = "base URL the coordinator reaches this worker on")]
+        advertise: Option<String>,
+
+        #[cfg(feature = "cluster")]
+        #[clap(long, help = "API key presented to other cluster members")]
+        cluster_key: Option<String>,
+
+        #[clap(
+            long,
+            help = "journal mCaptcha jobs carrying an X-Job-Id header in this directory"
+        )]
+        journal_dir: Option<std::path::PathBuf>,
+
+        #[cfg(feature = "grpc")]
+        #[clap(long, help = "also serve the gRPC solver service on this address")]
+        grpc_addr: Option<String>,
+
+        #[cfg(unix)]
+        #[clap(
+            long,
+            help = "also serve the binary solver protocol on this Unix socket"
+        )]
+        uds_path: Option<std::path::PathBuf>,
+    },
+    Profile {
+        #[clap(short, long, default_value = "10000000")]
+        difficulty: u64,
+
+        #[clap(short, long, default_value = "64")]
+        prefix_length: usize,
+    },
+    ProfileMt {
+        #[clap(short, long, default_value = "10000000")]
+        difficulty: u64,
+
+        #[clap(long)]
+        speed: bool,
+
+        #[clap(short, long, conflicts_with = "smt")]
+        n_threads: Option<u32>,
+
+        #[clap(
+            long,
+            help = "run one thread per logical CPU or per physical core (default: preferred by the backend)"
+        )]
+        smt: Option<String>,
+
+        #[clap(short, long, default_value = "64")]
+        prefix_length: usize,
+    },
+    Time {
+        #[clap(short, long, default_value = "10000000")]
+        difficulty: u64,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    match cli.subcommand {
+        SubCommand::Bench {
+            difficulty,
+            samples,
+            backend,
+            argon2_time,
+            argon2_kb,
+            argon2_lanes,
+            format,
+            corpus,
+            hashrate_cap,
+            #[cfg(feature = "tui")]
+            tui,
+        } => {
+            let backends: Vec<Backend> = if backend.is_empty() {
+                Backend::AVAILABLE.to_vec()
+            } else {
+                backend
+                    .iter()
+                    .map(|b| match b.as_str() {
+                        "argon2" => Backend::Argon2(pow_buster::argon2::Params {
+                            time: argon2_time,
+                            memory_kib: argon2_kb,
+                            parallelism: argon2_lanes,
+                        }),
+                        _ => b.parse().unwrap(),
+                    })
+                    .collect()
+            };
+            assert!(samples > 0, "samples must be positive");
+            assert!(
+                difficulty.iter().all(|d| *d > 0),
+                "difficulty must be positive"
+            );
+
+            let format: BenchFormat = format.parse().unwrap();
+            let hashrate_cap: Option<HashrateCap> = hashrate_cap.map(|cap| cap.parse().unwrap());
+
+            let print_result = |result: &BenchResult| {
+                if result.throttled() && !matches!(format, BenchFormat::Table) {
+                    eprintln!(
+                        "warning: {} ran while the CPU was throttling, its hashrate is understated",
+                        result.id()
+                    );
+                }
+                let thermal = result.thermal.as_ref();
+                let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+                match format {
+                    BenchFormat::Table => println!(
+                        "{:<8} {:<14} {:>12} {:>8} {:>12.3} {:>12.3} {:>10.2} {:>9} {:>9} {:>7}{}",
+                        result.backend.name(),
+                        result.shape,
+                        result.difficulty,
+                        result.latencies.len(),
+                        result.mean().as_secs_f64() * 1000.0,
+                        result.max().as_secs_f64() * 1000.0,
+                        result.hashrate() / 1024.0 / 1024.0,
+                        or_dash(thermal.and_then(|t| t.mean_mhz).map(|f| f.to_string())),
+                        or_dash(thermal.and_then(|t| t.min_mhz).map(|f| f.to_string())),
+                        or_dash(
+                            thermal
+                                .and_then(|t| t.peak_celsius)
+                                .map(|c| format!("{:.0}", c))
+                        ),
+                        if result.throttled() { " THROTTLED" } else { "" },
+                    ),
+                    BenchFormat::Criterion => println!("{}", result.criterion_message()),
+                    BenchFormat::Bencher => println!("{}", result.bencher_line()),
+                }
+            };
+
+            let challenge_sets: Vec<(&'static str, u32, Arc<[McaptchaChallengeDescriptor]>)> =
+                match &corpus {
+                    Some(path) => read_corpus(path)
+                        .into_iter()
+                        .map(|(difficulty, challenges)| {
+                            ("corpus", difficulty, Arc::from(challenges))
+                        })
+                        .collect(),
+                    None => BENCH_PREFIX_SHAPES
+                        .iter()
+                        .flat_map(|&(shape, _, _)| {
+                            difficulty.iter().map(move |&difficulty| {
+                                let challenges = synthetic_challenges(shape, difficulty, samples);
+                                (shape, difficulty, Arc::from(challenges))
+                            })
+                        })
+                        .collect(),
+                };
+            let mut jobs = Vec::new();
+            for &backend in &backends {
+                for (shape, difficulty, challenges) in &challenge_sets {
+                    jobs.push((backend, *shape, *difficulty, challenges.clone()));
+                }
+            }
+
+            if let BenchFormat::Table = format {
+                println!(
+                    "{:<8} {:<14} {:>12} {:>8} {:>12} {:>12} {:>10} {:>9} {:>9} {:>7}",
+                    "backend",
+                    "prefix",
+                    "difficulty",
+                    "samples",
+                    "mean (ms)",
+                    "max (ms)",
+                    "MH/s",
+                    "mean MHz",
+                    "min MHz",
+                    "max °C"
+                );
+            }
+
+            #[cfg(feature = "tui")]
+            if tui {
+                let rows: Vec<_> = jobs
+                    .iter()
+                    .map(|(backend, shape, difficulty, _)| {
+                        tui::Stats::new(format!("{}/{}/{}", backend.name(), shape, difficulty))
+                    })
+                    .collect();
+                let runner = {
+                    let rows = rows.clone();
+                    std::thread::spawn(move || {
+                        jobs.into_iter()
+                            .zip(rows)
+                            .map(|((backend, shape, difficulty, challenges), row)| {
+                                row.lock().unwrap().start();
+                                let result = BenchResult::run_with(
+                                    backend,
+                                    shape,
+                                    difficulty,
+                                    &challenges,
+                                    hashrate_cap,
+                                    |latency, attempted| {
+                                        row.lock().unwrap().record_solve(latency, attempted)
+                                    },
+                                );
+                                row.lock().unwrap().finish();
+                                result
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                };
+                if !tui::run("pow-buster bench", &rows, || runner.is_finished()).unwrap() {
+                    std::process::exit(130);
+                }
+                runner.join().unwrap().iter().for_each(print_result);
+                return;
+            }
+
+            for (backend, shape, difficulty, challenges) in jobs {
+                print_result(&BenchResult::run_with(
+                    backend,
+                    shape,
+                    difficulty,
+                    &challenges,
+                    hashrate_cap,
+                    |_, _| {},
+                ));
+            }
+        }
+        SubCommand::Generate {
+            count,
+            seed,
+            preset,
+            difficulty,
+        } => {
+            use pow_buster::generator::{
+                ChallengeGenerator, DifficultyDistribution, GeneratorConfig,
+            };
+            use std::io::Write;
+
+            let mut config = match preset.as_str() {
+                "random" => GeneratorConfig::default(),
+                "mcaptcha" => GeneratorConfig::mcaptcha_default(),
+                _ => panic!("unknown preset: {}", preset),
+            };
+            if let Some(difficulty) = difficulty {
+                config.difficulty = DifficultyDistribution::Fixed(difficulty);
+            }
+
+            let mut stdout = std::io::stdout().lock();
+            for challenge in ChallengeGenerator::new(config, seed).take(count) {
+                serde_json::to_writer(&mut stdout, &challenge).unwrap();
+                writeln!(stdout).unwrap();
+            }
+        }
+        SubCommand::Calibrate {
+            difficulty,
+            backend,
+            shape,
+            samples,
+            threads,
+        } => {
+            let backend: Backend = backend.parse().unwrap();
+            let shape = BENCH_PREFIX_SHAPES
+                .iter()
+                .map(|(label, _, _)| *label)
+                .find(|label| *label == shape)
+                .expect("unknown prefix shape");
+            assert!(samples > 0, "samples must be positive");
+            assert!(threads > 0, "threads must be positive");
+
+            let measured = BenchResult::run(backend, shape, CALIBRATION_DIFFICULTY, samples);
+            if measured.throttled() {
+                eprintln!(
+                    "warning: the CPU was throttling during calibration, estimates are pessimistic"
+                );
+            }
+            let hashrate = measured.hashrate() * threads as f64;
+            println!(
+                "[{}] {} prefix: {:.2} MH/s per core, {:.2} MH/s on {} cores (assuming linear scaling)",
+                backend.name(),
+                shape,
+                measured.hashrate() / 1024.0 / 1024.0,
+                hashrate / 1024.0 / 1024.0,
+                threads,
+            );
+            println!();
+            println!(
+                "{:>12} {:>12} {:>12} {:>12} {:>16}",
+                "difficulty", "mean (ms)", "p90 (ms)", "p99 (ms)", "P(bank fails)"
+            );
+            for &difficulty in &difficulty {
+                assert!(difficulty > 0, "difficulty must be positive");
+                let difficulty = difficulty as f64;
+                // attempts until success are geometric with p = 1/difficulty
+                let quantile_ms = |q: f64| -(1.0 - q).ln() * difficulty / hashrate * 1000.0;
+                let bank_failure = ((-1.0 / difficulty).ln_1p() * SEARCH_BANK_KEYSPACE).exp();
+                println!(
+                    "{:>12} {:>12.3} {:>12.3} {:>12.3} {:>16.3e}",
+                    difficulty,
+                    difficulty / hashrate * 1000.0,
+                    quantile_ms(0.9),
+                    quantile_ms(0.99),
+                    bank_failure,
+                );
+            }
+        }
+        SubCommand::Solve {
+            salt,
+            phrase,
+            difficulty,
+            backend,
+            json,
+            stdio,
+        } => {
+            let backend: Backend = backend.parse().unwrap();
+
+            if stdio {
+                use std::io::{BufRead, Write};
+
+                let mut stdout = std::io::stdout().lock();
+                for line in std::io::stdin().lock().lines() {
+                    let line = line.expect("failed to read stdin");
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let response = match serde_json::from_str::<StdioRequest>(&line) {
+                        Ok(request) if request.challenge.difficulty_factor == 0 => StdioResponse {
+                            id: request.id,
+                            outcome: StdioOutcome::Error {
+                                error: "difficulty must be positive".to_string(),
+                            },
+                        },
+                        Ok(request) => StdioResponse {
+                            outcome: match backend.solve_mcaptcha(&request.challenge).0 {
+                                Some((nonce, result)) => StdioOutcome::Solved {
+                                    nonce,
+                                    result: pow_buster::extract128_be(result).to_string(),
+                                },
+                                None => StdioOutcome::Error {
+                                    error: "solver failed".to_string(),
+                                },
+                            },
+                            id: request.id,
+                        },
+                        Err(e) => StdioResponse {
+                            id: None,
+                            outcome: StdioOutcome::Error {
+                                error: e.to_string(),
+                            },
+                        },
+                    };
+                    serde_json::to_writer(&mut stdout, &response).unwrap();
+                    stdout.write_all(b"\n").unwrap();
+                    stdout.flush().unwrap();
+                }
+                return;
+            }
+
+            let difficulty = difficulty.unwrap();
+            assert!(difficulty > 0, "difficulty must be positive");
+            let descriptor = McaptchaChallengeDescriptor {
+                salt: salt.unwrap(),
+                string: phrase.unwrap(),
+                difficulty_factor: difficulty,
+            };
 
-        #[clap(short, long, default_value = "64")]
-        prefix_length: usize,
-    },
-    ProfileMt {
-        #[clap(short, long, default_value = "10000000")]
-        difficulty: u64,
+            let start = Instant::now();
+            let (result, attempted_nonces) = backend.solve_mcaptcha(&descriptor);
+            let elapsed = start.elapsed();
+            let (nonce, result) = result.expect("solver failed");
+            let result = pow_buster::extract128_be(result).to_string();
 
-        #[clap(long)]
-        speed: bool,
+            eprintln!(
+                "[{}]: solved in {:.3} seconds ({} attempted nonces, {:.2} MH/s)",
+                backend.name(),
+                elapsed.as_secs_f32(),
+                attempted_nonces,
+                attempted_nonces as f32 / elapsed.as_secs_f32() / 1024.0 / 1024.0
+            );
 
-        #[clap(short, long, default_value = "1")]
-        n_threads: Option<u32>,
+            if json {
+                serde_json::to_writer_pretty(
+                    std::io::stdout(),
+                    &serde_json::json!({
+                        "string": descriptor.string,
+                        "nonce": nonce,
+                        "result": result,
+                        "_meta": {
+                            "backend": backend.name(),
+                            "elapsed_us": elapsed.as_micros() as u64,
+                            "attempted_nonces": attempted_nonces,
+                        },
+                    }),
+                )
+                .unwrap();
+                println!();
+            } else {
+                println!("nonce: {}", nonce);
+                println!("result: {}", result);
+            }
+        }
+        #[cfg(feature = "distributed")]
+        SubCommand::ClusterCoordinator {
+            listen,
+            workers,
+            salt,
+            phrase,
+            difficulty,
+        } => {
+            assert!(workers > 0, "workers must be positive");
+            assert!(difficulty > 0, "difficulty must be positive");
+            let descriptor = McaptchaChallengeDescriptor {
+                salt,
+                string: phrase,
+                difficulty_factor: difficulty,
+            };
+            let listener = std::net::TcpListener::bind(&listen).unwrap();
+            eprintln!("waiting for {} workers on {}", workers, listen);
 
-        #[clap(short, long, default_value = "64")]
-        prefix_length: usize,
-    },
-    Time {
-        #[clap(short, long, default_value = "10000000")]
-        difficulty: u64,
-    },
-}
+            let start = Instant::now();
+            let mut last_report = Instant::now();
+            let proof =
+                pow_buster::distributed::coordinate(&listener, workers, &descriptor, |attempted| {
+                    if last_report.elapsed() >= Duration::from_secs(5) {
+                        last_report = Instant::now();
+                        eprintln!(
+                            "{} attempted nonces, {:.2} MH/s",
+                            attempted,
+                            attempted as f32 / start.elapsed().as_secs_f32() / 1024.0 / 1024.0
+                        );
+                    }
+                })
+                .unwrap();
 
-fn main() {
-    let cli = Cli::parse();
-    match cli.subcommand {
+            let Some(proof) = proof else {
+                eprintln!("all workers exhausted their keyspace without a proof");
+                std::process::exit(1);
+            };
+            eprintln!(
+                "solved by {} in {:.3} seconds ({} attempted nonces, {:.2} MH/s)",
+                proof.worker,
+                proof.elapsed.as_secs_f32(),
+                proof.attempted_nonces,
+                proof.attempted_nonces as f32 / proof.elapsed.as_secs_f32() / 1024.0 / 1024.0
+            );
+            println!("nonce: {}", proof.nonce);
+            println!("result: {}", proof.result);
+        }
+        #[cfg(feature = "distributed")]
+        SubCommand::ClusterWorker { connect, threads } => {
+            let threads = threads.unwrap_or_else(|| pow_buster::topology::default_threads() as u32);
+            pow_buster::distributed::work(connect, threads).unwrap();
+        }
+        SubCommand::Verify {
+            salt,
+            phrase,
+            nonce,
+            difficulty,
+            result,
+        } => {
+            let descriptor = pow_buster::adapter::McaptchaChallengeDescriptor {
+                salt,
+                string: phrase,
+                difficulty_factor: difficulty,
+            };
+            let verification = descriptor.verify(nonce);
+
+            println!("result: {}", verification.result);
+            println!("target: {}", verification.target);
+            let mut valid = difficulty > 0 && verification.is_sufficient();
+            if difficulty == 0 {
+                println!("difficulty: invalid, must be positive");
+            } else if verification.is_sufficient() {
+                println!("difficulty: ok");
+            } else {
+                println!("difficulty: insufficient");
+            }
+            if let Some(expected) = result {
+                if expected == verification.result {
+                    println!("reported result: ok");
+                } else {
+                    println!("reported result: mismatch, expected {}", expected);
+                    valid = false;
+                }
+            }
+
+            if !valid {
+                std::process::exit(1);
+            }
+        }
+        SubCommand::FriendlyCaptcha { puzzle } => {
+            let puzzle =
+                pow_buster::adapter::FriendlyCaptchaPuzzle::parse(&puzzle).expect("invalid puzzle");
+            eprintln!(
+                "{} puzzles at difficulty {} (estimated workload: {})",
+                puzzle.number_of_solutions(),
+                puzzle.difficulty(),
+                puzzle.estimated_workload()
+            );
+            let start = Instant::now();
+            let (solution, attempted_nonces) = puzzle.solve();
+            let elapsed = start.elapsed();
+            eprintln!(
+                "attempted {} nonces in {:.3}s ({:.2} MH/s)",
+                attempted_nonces,
+                elapsed.as_secs_f64(),
+                attempted_nonces as f64 / elapsed.as_secs_f64() / 1024.0 / 1024.0
+            );
+            match solution {
+                Some(solution) => println!("{}", solution),
+                None => {
+                    eprintln!("solver failed");
+                    std::process::exit(1);
+                }
+            }
+        }
+        SubCommand::Auto { challenge, limit } => {
+            let challenge = if challenge == "-" {
+                std::io::read_to_string(std::io::stdin()).expect("failed to read stdin")
+            } else {
+                challenge
+            };
+            let Some(challenge) = pow_buster::adapter::DetectedChallenge::detect(&challenge) else {
+                eprintln!("unrecognized challenge");
+                std::process::exit(1);
+            };
+            if !challenge.supported() {
+                eprintln!("{} challenge is not supported", challenge.scheme());
+                std::process::exit(1);
+            }
+            eprintln!(
+                "detected {} challenge (estimated workload: {})",
+                challenge.scheme(),
+                challenge.estimated_workload()
+            );
+            let start = Instant::now();
+            let (proof, attempted_nonces) = challenge.solve_with_limit(limit.unwrap_or(u64::MAX));
+            let elapsed = start.elapsed();
+            eprintln!(
+                "attempted {} nonces in {:.3}s ({:.2} MH/s)",
+                attempted_nonces,
+                elapsed.as_secs_f64(),
+                attempted_nonces as f64 / elapsed.as_secs_f64() / 1024.0 / 1024.0
+            );
+            match proof {
+                Some(proof) => println!("{}", proof),
+                None => {
+                    eprintln!("solver failed");
+                    std::process::exit(1);
+                }
+            }
+        }
+        SubCommand::HaproxyProtection {
+            pow,
+            diff,
+            mode,
+            time,
+            kb,
+        } => {
+            let challenge = pow_buster::adapter::HaproxyProtectionChallenge::from_data_attributes(
+                &pow,
+                &diff,
+                &mode,
+                time.as_deref(),
+                kb.as_deref(),
+            )
+            .expect("invalid challenge");
+            if !challenge.supported() {
+                eprintln!("difficulty {} is not supported", challenge.difficulty);
+                std::process::exit(1);
+            }
+            eprintln!(
+                "{:?} at difficulty {} (estimated workload: {})",
+                challenge.mode,
+                challenge.difficulty,
+                challenge.estimated_workload()
+            );
+            let start = Instant::now();
+            let (nonce, attempted_nonces) = challenge.solve();
+            let elapsed = start.elapsed();
+            eprintln!(
+                "attempted {} nonces in {:.3}s ({:.2} MH/s)",
+                attempted_nonces,
+                elapsed.as_secs_f64(),
+                attempted_nonces as f64 / elapsed.as_secs_f64() / 1024.0 / 1024.0
+            );
+            match nonce {
+                Some(nonce) => {
+                    eprintln!("pow_response: {}", challenge.response(nonce));
+                    println!("{}", challenge.cookie(nonce));
+                }
+                None => {
+                    eprintln!("solver failed");
+                    std::process::exit(1);
+                }
+            }
+        }
+        SubCommand::Hashcash {
+            resource,
+            bits,
+            date,
+            ext,
+            rand,
+        } => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap();
+            let stamp = pow_buster::hashcash::HashcashStamp {
+                bits,
+                date: date.unwrap_or_else(|| yymmdd(now.as_secs())),
+                resource,
+                ext,
+                rand: rand.unwrap_or_else(|| {
+                    format!(
+                        "{:x}",
+                        now.as_nanos() as u64 ^ (std::process::id() as u64) << 32
+                    )
+                }),
+            };
+            let start = Instant::now();
+            let (minted, attempted_nonces) = stamp.mint();
+            let elapsed = start.elapsed();
+            eprintln!(
+                "attempted {} nonces in {:.3}s ({:.2} MH/s)",
+                attempted_nonces,
+                elapsed.as_secs_f64(),
+                attempted_nonces as f64 / elapsed.as_secs_f64() / 1024.0 / 1024.0
+            );
+            match minted {
+                Some(minted) => println!("X-Hashcash: {}", minted),
+                None => {
+                    eprintln!("failed to mint a stamp, try fewer bits or another rand");
+                    std::process::exit(1);
+                }
+            }
+        }
         SubCommand::Profile {
             difficulty,
             prefix_length,
@@ -161,12 +1767,18 @@ fn main() {
             difficulty,
             speed,
             n_threads,
+            smt,
             prefix_length,
         } => {
-            let n_threads = n_threads.unwrap_or_else(|| num_cpus::get() as u32);
+            let smt = smt.map_or_else(pow_buster::topology::SmtPolicy::current, |smt| {
+                smt.parse().unwrap()
+            });
+            let n_threads = n_threads.unwrap_or_else(|| smt.threads() as u32);
             println!(
-                "entering busy loop, attach profiler to this process now (difficulty: {}, n_threads: {})",
-                difficulty, n_threads
+                "entering busy loop, attach profiler to this process now (difficulty: {}, n_threads: {}, smt: {})",
+                difficulty,
+                n_threads,
+                smt.name()
             );
             let counter = Arc::new(AtomicU64::new(0));
 
@@ -221,21 +1833,228 @@ fn main() {
                 }
             }
         }
+        #[cfg(feature = "live-throughput-test")]
+        SubCommand::Load {
+            host,
+            site_key,
+            rps,
+            duration,
+            profile,
+            assertions,
+            max_in_flight,
+            n_threads,
+            proxy,
+            client_cert,
+            client_key,
+            ca_cert,
+            local_address,
+            timeline,
+            report: report_path,
+            report_format,
+        } => {
+            use pow_buster::client::{
+                McaptchaWidget,
+                load::{LoadConfig, LoadProfile, Metric, Phase, Threshold, run_open_loop},
+            };
+
+            let report_format: ReportFormat = report_format.parse().unwrap();
+            let assertions: Vec<Threshold> =
+                assertions.iter().map(|a| a.parse().unwrap()).collect();
+
+            let profile = match (profile, rps) {
+                (Some(profile), _) => profile.parse().unwrap(),
+                (None, Some(rps)) => {
+                    assert!(rps > 0.0, "rps must be positive");
+                    LoadProfile::constant(rps, Duration::from_secs(duration))
+                }
+                (None, None) => unreachable!(),
+            };
+            let pb = rayon::ThreadPoolBuilder::new().num_threads(
+                n_threads.map_or_else(pow_buster::topology::default_threads, |n| n as usize),
+            );
+            let pool = Arc::new(pb.build().unwrap());
+
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            eprintln!(
+                "You are hitting host {} for {} seconds",
+                host.join(", "),
+                profile.duration().as_secs_f64()
+            );
+            let targets = host
+                .iter()
+                .flat_map(|host| {
+                    site_key.iter().map(|site_key| McaptchaWidget {
+                        base_url: host.trim_end_matches('/').to_string(),
+                        site_key: site_key.clone(),
+                    })
+                })
+                .collect();
+            let config = LoadConfig {
+                targets,
+                profile,
+                max_in_flight,
+            };
+
+            let report = runtime.block_on(async {
+                let build = |address: Option<std::net::IpAddr>| {
+                    let mut builder = reqwest::ClientBuilder::new();
+                    if let Some(address) = address {
+                        builder = pow_buster::client::with_local_address(builder, address);
+                    }
+                    proxied(
+                        with_cli_tls(
+                            builder,
+                            client_cert.as_deref(),
+                            client_key.as_deref(),
+                            ca_cert.as_deref(),
+                        ),
+                        proxy.as_deref(),
+                    )
+                };
+                let clients: Vec<_> = match local_address.is_empty() {
+                    true => vec![build(None)],
+                    false => local_address.iter().map(|a| build(Some(*a))).collect(),
+                };
+                run_open_loop(&pool, &clients, &config, |report| {
+                    eprintln!(
+                        "[{:>4}s] target {:.1}/s, issued {}, ok {}, failed {}, dropped {}, in flight {}, difficulty {}",
+                        report.elapsed.as_secs(),
+                        report.target_rate,
+                        report.issued,
+                        report.succeeded,
+                        report.failed,
+                        report.dropped,
+                        report.in_flight,
+                        report
+                            .difficulty
+                            .map_or_else(|| "-".to_string(), |d| d.to_string()),
+                    );
+                })
+                .await
+            });
+
+            let ms = |phase: Phase, q: f64| {
+                report
+                    .percentile(phase, q)
+                    .map(|d| format!("{:.1}", d.as_secs_f64() * 1000.0))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            println!(
+                "issued {}, ok {}, failed {}, dropped {} ({:.2}% errors) in {:.1} seconds, {:.2} ok/s",
+                report.issued,
+                report.succeeded,
+                report.failed,
+                report.dropped,
+                report.error_rate() * 100.0,
+                report.elapsed.as_secs_f64(),
+                report.throughput(),
+            );
+            println!();
+            println!(
+                "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                "phase", "p50 (ms)", "p90 (ms)", "p99 (ms)", "p999 (ms)", "max (ms)"
+            );
+            for phase in Phase::ALL {
+                println!(
+                    "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                    phase.name(),
+                    ms(phase, 0.5),
+                    ms(phase, 0.9),
+                    ms(phase, 0.99),
+                    ms(phase, 0.999),
+                    ms(phase, 1.0),
+                );
+            }
+
+            if config.targets.len() > 1 {
+                println!();
+                println!("{:<48} {:>10} {:>10}", "target", "ok", "failed");
+                for (target, stats) in config.targets.iter().zip(&report.per_target) {
+                    println!(
+                        "{:<48} {:>10} {:>10}",
+                        format!("{} {}", target.base_url, target.site_key),
+                        stats.succeeded,
+                        stats.failed,
+                    );
+                }
+            }
+
+            if let Some(timeline) = timeline {
+                let file =
+                    std::fs::File::create(&timeline).expect("failed to create timeline file");
+                report
+                    .write_timeline_csv(std::io::BufWriter::new(file))
+                    .expect("failed to write timeline");
+                eprintln!(
+                    "wrote {} difficulty samples to {}",
+                    report.timeline.len(),
+                    timeline.display()
+                );
+            }
+
+            if let Some(report_path) = report_path {
+                use std::io::Write;
+
+                let file =
+                    std::fs::File::create(&report_path).expect("failed to create report file");
+                let mut writer = std::io::BufWriter::new(file);
+                match report_format {
+                    ReportFormat::Json => {
+                        serde_json::to_writer_pretty(&mut writer, &report.to_json(&config))
+                            .expect("failed to write report");
+                        writeln!(writer).expect("failed to write report");
+                    }
+                    ReportFormat::Csv => report
+                        .write_summary_csv(&mut writer)
+                        .expect("failed to write report"),
+                }
+                writer.flush().expect("failed to write report");
+                eprintln!("wrote report to {}", report_path.display());
+            }
+
+            if !assertions.is_empty() {
+                println!();
+                let mut violated = false;
+                for assertion in &assertions {
+                    let (observed, holds) = assertion.check(&report);
+                    violated |= !holds;
+                    let observed = match (observed, assertion.metric) {
+                        (None, _) => "no data".to_string(),
+                        (Some(ms), Metric::Latency { .. }) => format!("{:.1}ms", ms),
+                        (Some(rate), Metric::ErrorRate) => format!("{:.3}%", rate * 100.0),
+                        (Some(rate), Metric::Throughput) => format!("{:.2}/s", rate),
+                    };
+                    println!(
+                        "{} {} (observed {})",
+                        if holds { "PASS" } else { "FAIL" },
+                        assertion,
+                        observed
+                    );
+                }
+                if violated {
+                    std::process::exit(1);
+                }
+            }
+        }
         #[cfg(feature = "client")]
         SubCommand::CapJs {
             url,
             site_key,
             num_threads,
+            proxy,
         } => {
             let runtime = tokio::runtime::Builder::new_current_thread()
                 .enable_all()
                 .build()
                 .unwrap();
 
-            let mut pb = rayon::ThreadPoolBuilder::new();
-            if let Some(num_threads) = num_threads {
-                pb = pb.num_threads(num_threads as usize);
-            }
+            let pb = rayon::ThreadPoolBuilder::new().num_threads(
+                num_threads.map_or_else(pow_buster::topology::default_threads, |n| n as usize),
+            );
 
             let pool = pb.build().unwrap();
             let pool = Arc::new(pool);
@@ -243,10 +2062,10 @@ fn main() {
             runtime.block_on(async move {
                 use pow_buster::adapter::{CapJsResponse, SolveCapJsResponseMeta};
 
-                let client = reqwest::ClientBuilder::new()
-                    .redirect(reqwest::redirect::Policy::none())
-                    .build()
-                    .unwrap();
+                let client = proxied(
+                    reqwest::ClientBuilder::new().redirect(reqwest::redirect::Policy::none()),
+                    proxy.as_deref(),
+                );
                 let (response, meta) =
                     pow_buster::client::solve_capjs(&pool, &client, &url, &site_key)
                         .await
@@ -351,18 +2170,174 @@ fn main() {
             );
         }
         #[cfg(feature = "client")]
-        SubCommand::Anubis { url } => {
-            let runtime = tokio::runtime::Builder::new_multi_thread()
+        SubCommand::SolveUrl {
+            url,
+            num_threads,
+            proxy,
+            header,
+            user_agent,
+            client_cert,
+            client_key,
+            ca_cert,
+        } => {
+            let runtime = tokio::runtime::Builder::new_current_thread()
                 .enable_all()
                 .build()
                 .unwrap();
 
+            let pb = rayon::ThreadPoolBuilder::new().num_threads(
+                num_threads.map_or_else(pow_buster::topology::default_threads, |n| n as usize),
+            );
+
+            let pool = pb.build().unwrap();
+
             runtime.block_on(async move {
-                let client = reqwest::ClientBuilder::new()
-                    .gzip(true)
-                    .redirect(reqwest::redirect::Policy::none())
-                    .build()
+                let client = proxied(
+                    with_cli_headers(
+                        with_cli_tls(
+                            pow_buster::client::browser_client_builder(),
+                            client_cert.as_deref(),
+                            client_key.as_deref(),
+                            ca_cert.as_deref(),
+                        ),
+                        &header,
+                        user_agent.as_deref(),
+                    ),
+                    proxy.as_deref(),
+                );
+                let begin = Instant::now();
+                let (widget, token) = pow_buster::client::solve_mcaptcha_url(&pool, &client, &url)
+                    .await
                     .unwrap();
+                eprintln!(
+                    "instance: {}, sitekey: {}, solved in {:.3} seconds",
+                    widget.base_url,
+                    widget.site_key,
+                    begin.elapsed().as_secs_f32()
+                );
+                println!("{}", token);
+            });
+        }
+        #[cfg(feature = "client")]
+        SubCommand::Status {
+            host,
+            site_key,
+            watch,
+            proxy,
+        } => {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            let host = host.trim_end_matches('/');
+
+            runtime.block_on(async move {
+                let client = proxied(reqwest::ClientBuilder::new(), proxy.as_deref());
+                let healthy = loop {
+                    let (healthy, mut line) =
+                        match pow_buster::client::mcaptcha_health(&client, host).await {
+                            Ok(health) => (
+                                health.is_healthy(),
+                                format!(
+                                    "{}: db {}, redis {}",
+                                    if health.is_healthy() {
+                                        "healthy"
+                                    } else {
+                                        "unhealthy"
+                                    },
+                                    health.db,
+                                    health.redis.map_or("-".to_string(), |r| r.to_string()),
+                                ),
+                            ),
+                            Err(e) => (false, format!("unreachable: {}", e)),
+                        };
+                    if let Some(site_key) = &site_key {
+                        match pow_buster::client::poll_mcaptcha_difficulty(&client, host, site_key)
+                            .await
+                        {
+                            Ok(difficulty) => {
+                                line.push_str(&format!(", difficulty {}", difficulty))
+                            }
+                            Err(e) => line.push_str(&format!(", difficulty unavailable: {}", e)),
+                        }
+                    }
+                    println!("{}", line);
+                    match watch {
+                        Some(interval) => tokio::time::sleep(Duration::from_secs(interval)).await,
+                        None => break healthy,
+                    }
+                };
+                if !healthy {
+                    std::process::exit(1);
+                }
+            });
+        }
+        #[cfg(feature = "client")]
+        SubCommand::Record {
+            host,
+            site_key,
+            count,
+            max_rps,
+            proxy,
+            client_cert,
+            client_key,
+            ca_cert,
+        } => {
+            use std::io::Write;
+
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            runtime.block_on(async move {
+                let client = proxied(
+                    with_cli_tls(
+                        reqwest::ClientBuilder::new(),
+                        client_cert.as_deref(),
+                        client_key.as_deref(),
+                        ca_cert.as_deref(),
+                    ),
+                    proxy.as_deref(),
+                );
+                let pacer = max_rps.map(|rate| pow_buster::client::pacing::Pacer::new(rate, 1));
+                let mut stdout = std::io::stdout().lock();
+                for _ in 0..count {
+                    if let Some(pacer) = &pacer {
+                        pacer.acquire(1).await;
+                    }
+                    let challenge =
+                        pow_buster::client::fetch_mcaptcha_challenge(&client, &host, &site_key)
+                            .await
+                            .unwrap();
+                    serde_json::to_writer(&mut stdout, &challenge).unwrap();
+                    writeln!(stdout).unwrap();
+                }
+            });
+        }
+        #[cfg(feature = "client")]
+        SubCommand::Anubis {
+            url,
+            proxy,
+            header,
+            user_agent,
+        } => {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            runtime.block_on(async move {
+                let client = proxied(
+                    with_cli_headers(
+                        pow_buster::client::browser_client_builder()
+                            .gzip(true)
+                            .redirect(reqwest::redirect::Policy::none()),
+                        &header,
+                        user_agent.as_deref(),
+                    ),
+                    proxy.as_deref(),
+                );
                 let response = pow_buster::client::solve_anubis(&client, &url)
                     .await
                     .unwrap();
@@ -370,17 +2345,27 @@ fn main() {
             });
         }
         #[cfg(feature = "client")]
-        SubCommand::GoAway { url } => {
+        SubCommand::GoAway {
+            url,
+            proxy,
+            header,
+            user_agent,
+        } => {
             let runtime = tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
                 .build()
                 .unwrap();
 
             runtime.block_on(async move {
-                let client = reqwest::ClientBuilder::new()
-                    .redirect(reqwest::redirect::Policy::none())
-                    .build()
-                    .unwrap();
+                let client = proxied(
+                    with_cli_headers(
+                        pow_buster::client::browser_client_builder()
+                            .redirect(reqwest::redirect::Policy::none()),
+                        &header,
+                        user_agent.as_deref(),
+                    ),
+                    proxy.as_deref(),
+                );
                 let response = pow_buster::client::solve_goaway_js_pow_sha256(&client, &url)
                     .await
                     .unwrap();
@@ -394,15 +2379,23 @@ fn main() {
             site_key,
             n_workers,
             n_threads,
+            proxy,
+            #[cfg(feature = "tui")]
+            tui,
         } => {
             let api_type: ApiType = api_type.parse().unwrap();
+            #[cfg(feature = "tui")]
+            let row = tui.then(|| tui::Stats::new(format!("{:?} {}", api_type, host)));
+            #[cfg(feature = "tui")]
+            let quiet = tui;
+            #[cfg(not(feature = "tui"))]
+            let quiet = false;
             let n_workers = n_workers.unwrap_or_else(|| num_cpus::get() as u32);
             eprintln!("You are hitting host {}, n_workers: {}", host, n_workers);
 
-            let mut pb = rayon::ThreadPoolBuilder::new();
-            if let Some(n_threads) = n_threads {
-                pb = pb.num_threads(n_threads as usize);
-            }
+            let pb = rayon::ThreadPoolBuilder::new().num_threads(
+                n_threads.map_or_else(pow_buster::topology::default_threads, |n| n as usize),
+            );
 
             let pool = pb.build().unwrap();
             let semaphore = Arc::new(tokio::sync::Semaphore::new(pool.current_num_threads()));
@@ -430,19 +2423,23 @@ fn main() {
                     let pool = pool.clone();
 
                     let api_type = api_type.clone();
+                    let proxy = proxy.clone();
                     let semaphore = semaphore.clone();
+                    #[cfg(feature = "tui")]
+                    let row = row.clone();
                     tokio::spawn(async move {
-                        let client = reqwest::ClientBuilder::new()
-                            .gzip(api_type == ApiType::Anubis) // for some reason anubis requires gzip
-                            .redirect(reqwest::redirect::Policy::none())
-                            .build()
-                            .unwrap();
+                        let client = proxied(
+                            pow_buster::client::browser_client_builder()
+                                .gzip(api_type == ApiType::Anubis) // for some reason anubis requires gzip
+                                .redirect(reqwest::redirect::Policy::none()),
+                            proxy.as_deref(),
+                        );
 
-                        match api_type {
-                            ApiType::Mcaptcha => loop {
-                                let mut iotime = 0;
-                                let start = Instant::now();
-                                match pow_buster::client::solve_mcaptcha_ex(
+                        loop {
+                            let mut iotime = 0;
+                            let start = Instant::now();
+                            let outcome = match api_type {
+                                ApiType::Mcaptcha => pow_buster::client::solve_mcaptcha_ex(
                                     &pool,
                                     &client,
                                     &host_clone,
@@ -451,62 +2448,58 @@ fn main() {
                                     &mut iotime,
                                 )
                                 .await
-                                {
-                                    Ok(_) => {
-                                        succeeded_clone.fetch_add(1, Ordering::Relaxed)
-                                    }
-                                    Err(_) => failed_clone.fetch_add(1, Ordering::Relaxed),
-                                };
-                                let mut packed_time = start.elapsed().as_micros() as u64 / 16;
-                                packed_time <<=32;
-                                packed_time += iotime as u64 / 16;
-                                packed_time_clone.fetch_add(packed_time, Ordering::Relaxed);
-                            },
-                            ApiType::Anubis => loop {
-                                let mut iotime = 0;
-                                let start = Instant::now();
-                                match pow_buster::client::solve_anubis_ex(
+                                .map(drop),
+                                ApiType::Anubis => {
+                                    pow_buster::client::solve_anubis_ex(&client, &host_clone, &mut iotime)
+                                        .await
+                                        .map(drop)
+                                }
+                                ApiType::CapJs => pow_buster::client::solve_capjs_worker(
+                                    &pool,
                                     &client,
                                     &host_clone,
+                                    &site_key_clone,
                                     &mut iotime,
+                                    &semaphore,
                                 )
                                 .await
-                                {
-                                    Ok(_) => {
-                                        succeeded_clone.fetch_add(1, Ordering::Relaxed);
+                                .map(drop),
+                            };
+                            match &outcome {
+                                Ok(()) => succeeded_clone.fetch_add(1, Ordering::Relaxed),
+                                Err(e) => {
+                                    if !quiet && api_type != ApiType::Mcaptcha {
+                                        eprintln!("{:?} error: {:?}", api_type, e);
                                     }
-                                    Err(e) => {
-                                        eprintln!("anubis error: {:?}", e);
-                                        failed_clone.fetch_add(1, Ordering::Relaxed);
-                                    }
-                                };
-                                let mut packed_time = start.elapsed().as_micros() as u64 / 16;
-                                packed_time <<= 32;
-                                packed_time += iotime as u64 / 16;
-                                packed_time_clone.fetch_add(packed_time, Ordering::Relaxed);
-                            },
-                            ApiType::CapJs => loop {
-                                let mut iotime = 0;
-                                let start = Instant::now();
-                                match pow_buster::client::solve_capjs_worker(&pool, &client, &host_clone, &site_key_clone, &mut iotime, &semaphore)
-                                    .await {
-                                        Ok(_) => {
-                                            succeeded_clone.fetch_add(1, Ordering::Relaxed);
-                                        }
-                                        Err(e) => {
-                                            eprintln!("capjs error: {:?}", e);
-                                            failed_clone.fetch_add(1, Ordering::Relaxed);
-                                        }
-                                    };
-                                let mut packed_time = start.elapsed().as_micros() as u64 / 16;
-                                packed_time <<= 32;
-                                packed_time += iotime as u64 / 16;
-                                packed_time_clone.fetch_add(packed_time, Ordering::Relaxed);
-                            },
+                                    failed_clone.fetch_add(1, Ordering::Relaxed)
+                                }
+                            };
+                            #[cfg(feature = "tui")]
+                            if let Some(row) = &row {
+                                row.lock().unwrap().record_request(
+                                    outcome.is_ok(),
+                                    start.elapsed(),
+                                    Duration::from_micros(iotime as u64),
+                                );
+                            }
+                            let mut packed_time = start.elapsed().as_micros() as u64 / 16;
+                            packed_time <<= 32;
+                            packed_time += iotime as u64 / 16;
+                            packed_time_clone.fetch_add(packed_time, Ordering::Relaxed);
                         }
                     });
                 }
 
+                #[cfg(feature = "tui")]
+                if let Some(row) = row {
+                    let title = format!("pow-buster live ({} workers)", n_workers);
+                    tokio::task::spawn_blocking(move || tui::run(&title, &[row], || false))
+                        .await
+                        .unwrap()
+                        .unwrap();
+                    std::process::exit(0);
+                }
+
                 let begin = Instant::now();
                 let mut ticker = tokio::time::interval(Duration::from_secs(5));
                 loop {
@@ -542,8 +2535,30 @@ fn main() {
             addr,
             mut limit,
             n_workers,
+            queue_capacity,
             check_origin,
             timeout,
+            mut api_key,
+            api_key_file,
+            journal_dir,
+            #[cfg(feature = "tls")]
+            tls_cert,
+            #[cfg(feature = "tls")]
+            tls_key,
+            #[cfg(feature = "cluster")]
+            coordinator,
+            #[cfg(feature = "cluster")]
+            shard_threshold,
+            #[cfg(feature = "cluster")]
+            join,
+            #[cfg(feature = "cluster")]
+            advertise,
+            #[cfg(feature = "cluster")]
+            cluster_key,
+            #[cfg(feature = "grpc")]
+            grpc_addr,
+            #[cfg(unix)]
+            uds_path,
         } => {
             use tracing::level_filters::LevelFilter;
             use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -561,13 +2576,44 @@ fn main() {
                 limit = u64::MAX;
             }
 
+            // before sizing the worker pool, which depends on the selected backend
+            let backend = pow_buster::solver::autotune::autotune();
+            tracing::info!("selected the {} solver backend", backend.name());
+
+            let n_workers = n_workers.unwrap_or_else(pow_buster::topology::default_threads);
+
+            let mut state = pow_buster::server::AppState::new(n_workers, limit);
+            if let Some(capacity) = queue_capacity {
+                state = state.with_queue_capacity(capacity);
+            }
+            if let Some(path) = api_key_file {
+                let keys = std::fs::read_to_string(path).expect("failed to read API key file");
+                api_key.extend(keys.lines().map(str::to_string));
+            }
+            let api_keys = pow_buster::server::auth::ApiKeys::new(api_key);
+            if api_keys.is_empty() {
+                tracing::warn!("no API key configured, solve endpoints are open to anyone");
+            }
+            state = state.with_api_keys(api_keys);
+            if let Some(dir) = journal_dir {
+                let journal = pow_buster::server::journal::Journal::open(dir)
+                    .expect("failed to open job journal");
+                state = state.with_journal(journal);
+            }
+            #[cfg(feature = "cluster")]
+            if coordinator {
+                state = state.with_coordinator(pow_buster::server::cluster::Coordinator::new(
+                    cluster_key.clone(),
+                    shard_threshold,
+                ));
+            }
+
             let mut app = match check_origin {
                 Some(check_origin) => {
                     let expected_origin = url::Url::parse(&check_origin).unwrap();
-                    pow_buster::server::AppState::new(n_workers, limit)
-                        .router_with_origin_check(expected_origin)
+                    state.router_with_origin_check(expected_origin)
                 }
-                None => pow_buster::server::AppState::new(n_workers, limit).router(),
+                None => state.router(),
             };
 
             if timeout > 0 {
@@ -582,6 +2628,65 @@ fn main() {
                 .unwrap();
 
             runtime.block_on(async move {
+                let resumed = state.recover_journal().expect("failed to read job journal");
+                if resumed > 0 {
+                    tracing::info!("resumed {} journaled jobs", resumed);
+                }
+
+                #[cfg(feature = "grpc")]
+                if let Some(grpc_addr) = grpc_addr {
+                    let grpc_addr = grpc_addr.parse().expect("invalid gRPC address");
+                    let service = state.grpc_service();
+                    #[cfg_attr(not(feature = "tls"), allow(unused_mut))]
+                    let mut builder = tonic::transport::Server::builder();
+                    #[cfg(feature = "tls")]
+                    if let (Some(cert), Some(key)) = (&tls_cert, &tls_key) {
+                        let identity = tonic::transport::Identity::from_pem(
+                            std::fs::read(cert).expect("failed to read TLS certificate"),
+                            std::fs::read(key).expect("failed to read TLS key"),
+                        );
+                        builder = builder
+                            .tls_config(tonic::transport::ServerTlsConfig::new().identity(identity))
+                            .expect("invalid gRPC TLS configuration");
+                    }
+                    tokio::spawn(async move {
+                        builder.add_service(service).serve(grpc_addr).await.unwrap();
+                    });
+                }
+
+                #[cfg(feature = "cluster")]
+                if let (Some(join), Some(advertise)) = (join, advertise) {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        state.join_cluster(join, advertise, cluster_key).await;
+                    });
+                }
+
+                #[cfg(unix)]
+                if let Some(uds_path) = uds_path {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        state.serve_uds(uds_path).await.unwrap();
+                    });
+                }
+
+                #[cfg(feature = "tls")]
+                if let (Some(cert), Some(key)) = (tls_cert, tls_key) {
+                    let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key)
+                        .await
+                        .expect("failed to load TLS certificate");
+                    let addr = tokio::net::lookup_host(&addr)
+                        .await
+                        .unwrap()
+                        .next()
+                        .expect("address did not resolve");
+                    axum_server::bind_rustls(addr, config)
+                        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+                        .await
+                        .unwrap();
+                    return;
+                }
+
                 let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
                 axum::serve(
                     listener,
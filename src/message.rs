@@ -65,22 +65,11 @@ impl SingleBlockMessage {
         let mut complete_blocks_before = 0;
         let mut approx_working_set_count = 1;
 
-        // first consume all full blocks, this is shared so use scalar reference implementation
-        while prefix.len() >= 64 {
-            sha256::digest_block(
-                &mut prefix_state,
-                &core::array::from_fn(|i| {
-                    u32::from_be_bytes([
-                        prefix[i * 4],
-                        prefix[i * 4 + 1],
-                        prefix[i * 4 + 2],
-                        prefix[i * 4 + 3],
-                    ])
-                }),
-            );
-            prefix = &prefix[64..];
-            complete_blocks_before += 1;
-        }
+        // first consume all full blocks, this is shared so compress them in a single stream
+        let full_blocks = prefix.len() / 64;
+        sha256::digest_blocks(&mut prefix_state, &prefix[..full_blocks * 64]);
+        prefix = &prefix[full_blocks * 64..];
+        complete_blocks_before += full_blocks;
 
         let mut is_fitst_digit = true;
         let mut pop_padding_digit = || {
@@ -229,22 +218,11 @@ impl SingleBlockMessage {
         let mut complete_blocks_before = 0;
         let mut approx_working_set_count = 1;
 
-        // first consume all full blocks, this is shared so use scalar reference implementation
-        while prefix.len() >= 64 {
-            sha256::digest_block(
-                &mut prefix_state,
-                &core::array::from_fn(|i| {
-                    u32::from_be_bytes([
-                        prefix[i * 4],
-                        prefix[i * 4 + 1],
-                        prefix[i * 4 + 2],
-                        prefix[i * 4 + 3],
-                    ])
-                }),
-            );
-            prefix = &prefix[64..];
-            complete_blocks_before += 1;
-        }
+        // first consume all full blocks, this is shared so compress them in a single stream
+        let full_blocks = prefix.len() / 64;
+        sha256::digest_blocks(&mut prefix_state, &prefix[..full_blocks * 64]);
+        prefix = &prefix[full_blocks * 64..];
+        complete_blocks_before += full_blocks;
 
         let mut message: [u8; 64] = [0; 64]; // the final message
         let mut ptr = 0;
@@ -487,22 +465,11 @@ impl DoubleBlockMessage {
             }
         };
 
-        // first consume all full blocks, this is shared so use scalar reference implementation
-        while prefix.len() >= 64 {
-            sha256::digest_block(
-                &mut prefix_state,
-                &core::array::from_fn(|i| {
-                    u32::from_be_bytes([
-                        prefix[i * 4],
-                        prefix[i * 4 + 1],
-                        prefix[i * 4 + 2],
-                        prefix[i * 4 + 3],
-                    ])
-                }),
-            );
-            prefix = &prefix[64..];
-            complete_blocks_before += 1;
-        }
+        // first consume all full blocks, this is shared so compress them in a single stream
+        let full_blocks = prefix.len() / 64;
+        sha256::digest_blocks(&mut prefix_state, &prefix[..full_blocks * 64]);
+        prefix = &prefix[full_blocks * 64..];
+        complete_blocks_before += full_blocks as u64;
 
         let mut message: [u8; 64] = [0; 64];
         let mut ptr = 0;
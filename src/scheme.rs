@@ -0,0 +1,279 @@
+//! Description of SHA-256 proof-of-work schemes searched by the decimal nonce solvers.
+//!
+//! A scheme decides what is hashed, what counts as a solution and how a solution is encoded,
+//! the search itself is shared by every scheme.
+use core::ops::ControlFlow;
+
+use alloc::{string::ToString, vec::Vec};
+use sha2::Digest;
+
+use crate::{
+    message::DecimalMessage,
+    solver::{SOLVE_TYPE_GT, SOLVE_TYPE_LT, SolverDyn, autotune::AnySolver},
+};
+
+/// A SHA-256 PoW whose proof is a decimal nonce appended to a fixed prefix.
+///
+/// Construct: Proof := encode(nonce, SHA-256(prefix || ASCII_DECIMAL(nonce)))
+/// where the top 64 bits of the hash satisfy [`PowScheme::target`].
+pub trait PowScheme {
+    /// The proof submitted to the verifier.
+    type Proof;
+
+    /// How the top 64 bits of the hash are compared against the target, one of
+    /// [`SOLVE_TYPE_LT`], [`SOLVE_TYPE_GT`] or [`crate::solver::SOLVE_TYPE_MASK`].
+    const SOLVE_TYPE: u8;
+
+    /// Write the prefix the nonce digits are appended to.
+    fn build_prefix(&self, out: &mut Vec<u8>);
+
+    /// The target and mask of the top 64 bits of the hash, or None if no nonce can satisfy the challenge.
+    ///
+    /// The mask is only used by [`crate::solver::SOLVE_TYPE_MASK`] schemes.
+    fn target(&self) -> Option<(u64, u64)>;
+
+    /// Encode a nonce and its hash as a proof.
+    fn encode_proof(&self, nonce: u64, hash: [u32; 8]) -> Self::Proof;
+
+    /// Check a nonce with the scalar reference implementation.
+    ///
+    /// Schemes with a finer target than 64 bits should override this with the verifier's rules.
+    fn is_valid(&self, nonce: u64) -> bool {
+        let Some((target, mask)) = self.target() else {
+            return false;
+        };
+        let mut prefix = Vec::new();
+        self.build_prefix(&mut prefix);
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(&prefix);
+        hasher.update(nonce.to_string().as_bytes());
+        let hash = hasher.finalize();
+        let value = u64::from_be_bytes(hash[..8].try_into().unwrap());
+        match Self::SOLVE_TYPE {
+            SOLVE_TYPE_LT => value < target,
+            SOLVE_TYPE_GT => value > target,
+            _ => value & mask == target,
+        }
+    }
+}
+
+/// Solve a PoW with a limit.
+pub fn solve_with_limit<S: PowScheme>(scheme: &S, limit: u64) -> (Option<S::Proof>, u64) {
+    let (result, attempted_nonces) =
+        solve_banks_with_limit(scheme, 0, 1, limit, |_, _| ControlFlow::Continue(()));
+    (
+        result.map(|(nonce, hash)| scheme.encode_proof(nonce, hash)),
+        attempted_nonces,
+    )
+}
+
+/// Solve a PoW with a limit, visiting every `stride`-th search bank starting at `first_bank`.
+///
+/// `checkpoint` is called with the next search bank and the nonces attempted so far
+/// each time a search bank is exhausted, so a long solve can be resumed later.
+/// Returning [`ControlFlow::Break`] stops the search.
+pub fn solve_banks_with_limit<S: PowScheme>(
+    scheme: &S,
+    first_bank: u32,
+    stride: u32,
+    limit: u64,
+    mut checkpoint: impl FnMut(u32, u64) -> ControlFlow<()>,
+) -> (Option<(u64, [u32; 8])>, u64) {
+    let Some((target, mask)) = scheme.target() else {
+        return (None, 0);
+    };
+    if stride == 0 {
+        return (None, 0);
+    }
+    let mut prefix = Vec::new();
+    scheme.build_prefix(&mut prefix);
+
+    let mut result = None;
+    let mut attempted_nonces = 0;
+    let mut remaining_limit = limit;
+    for search_bank in (first_bank..).step_by(stride as usize) {
+        let Some(message) = DecimalMessage::new(&prefix, search_bank) else {
+            break;
+        };
+        let mut solver = AnySolver::for_target(message, S::SOLVE_TYPE, target, mask);
+        solver.set_limit(remaining_limit);
+        result = solver.solve_dyn(target, S::SOLVE_TYPE, mask);
+        attempted_nonces += solver.get_attempted_nonces();
+        remaining_limit = remaining_limit.saturating_sub(solver.get_attempted_nonces());
+        if result.is_some() || remaining_limit == 0 {
+            break;
+        }
+        if checkpoint(search_bank.saturating_add(stride), attempted_nonces).is_break() {
+            break;
+        }
+    }
+
+    (result, attempted_nonces)
+}
+
+/// Solve a batch of PoWs with a limit each.
+///
+/// On the AVX-512 backend up to 16 challenges share a pass, one per lane, which is much faster for
+/// low difficulty challenges than filling the lanes with nonces of a single challenge.
+/// Challenges that cannot be packed are solved one by one.
+pub fn solve_batch_with_limit<S: PowScheme>(
+    schemes: &[S],
+    limit: u64,
+) -> Vec<(Option<S::Proof>, u64)> {
+    // only filled in ahead of time by the packed search
+    #[allow(unused_mut)]
+    let mut results: Vec<_> = schemes.iter().map(|_| None).collect();
+    // the nonces of search bank 0 spent by lanes that ran out of their limit in the packed search
+    #[allow(unused_mut)]
+    let mut packed_attempts = alloc::vec![0; schemes.len()];
+
+    #[cfg(all(
+        target_arch = "x86_64",
+        any(target_feature = "avx512f", feature = "multiversion")
+    ))]
+    if crate::solver::autotune::selected() == crate::solver::autotune::Backend::Avx512 {
+        use crate::{message::SingleBlockMessage, solver::avx512::MultiChallengeSolver};
+
+        let mut solver = MultiChallengeSolver::new();
+        let mut lanes = [0; 16];
+        let mut pending = schemes.iter().enumerate();
+        loop {
+            while !solver.is_full() {
+                let Some((index, scheme)) = pending.next() else {
+                    break;
+                };
+                let Some((target, mask)) = scheme.target() else {
+                    results[index] = Some((None, 0));
+                    continue;
+                };
+                let mut prefix = Vec::new();
+                scheme.build_prefix(&mut prefix);
+                let Some(message) = SingleBlockMessage::new(&prefix, 0) else {
+                    continue;
+                };
+                if let Some(lane) = solver.push(message, target, mask, limit) {
+                    lanes[lane] = index;
+                }
+            }
+
+            let outcome = match S::SOLVE_TYPE {
+                SOLVE_TYPE_LT => solver.solve_next::<SOLVE_TYPE_LT>(),
+                SOLVE_TYPE_GT => solver.solve_next::<SOLVE_TYPE_GT>(),
+                _ => solver.solve_next::<{ crate::solver::SOLVE_TYPE_MASK }>(),
+            };
+            let Some(outcome) = outcome else {
+                break;
+            };
+            // an exhausted lane is left to the one by one search, which resumes at search bank 1
+            let index = lanes[outcome.lane];
+            match outcome.solution {
+                Some((nonce, hash)) => {
+                    results[index] = Some((
+                        Some(schemes[index].encode_proof(nonce, hash)),
+                        outcome.attempted_nonces,
+                    ));
+                }
+                None => packed_attempts[index] = outcome.attempted_nonces,
+            }
+        }
+    }
+
+    results
+        .into_iter()
+        .zip(schemes)
+        .zip(packed_attempts)
+        .map(|((result, scheme), packed_attempts)| {
+            result.unwrap_or_else(|| {
+                if packed_attempts == 0 {
+                    return solve_with_limit(scheme, limit);
+                }
+                if packed_attempts >= limit {
+                    return (None, packed_attempts);
+                }
+                let (result, attempted_nonces) =
+                    solve_banks_with_limit(scheme, 1, 1, limit - packed_attempts, |_, _| {
+                        ControlFlow::Continue(())
+                    });
+                (
+                    result.map(|(nonce, hash)| scheme.encode_proof(nonce, hash)),
+                    packed_attempts + attempted_nonces,
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// hex digest must start with `zeroes` zero nibbles
+    struct LeadingZeroes<'a> {
+        prefix: &'a str,
+        zeroes: u32,
+    }
+
+    impl PowScheme for LeadingZeroes<'_> {
+        type Proof = u64;
+        const SOLVE_TYPE: u8 = SOLVE_TYPE_LT;
+
+        fn build_prefix(&self, out: &mut Vec<u8>) {
+            out.extend_from_slice(self.prefix.as_bytes());
+        }
+
+        fn target(&self) -> Option<(u64, u64)> {
+            Some((1 << (64 - self.zeroes * 4), !0))
+        }
+
+        fn encode_proof(&self, nonce: u64, _hash: [u32; 8]) -> u64 {
+            nonce
+        }
+    }
+
+    #[test]
+    fn test_solve_batch() {
+        let prefixes: Vec<String> = (0..40).map(|i| "x".repeat(i * 3)).collect();
+        let schemes: Vec<_> = prefixes
+            .iter()
+            .map(|prefix| LeadingZeroes { prefix, zeroes: 3 })
+            .collect();
+        let results = solve_batch_with_limit(&schemes, u64::MAX);
+        for (scheme, (nonce, attempted)) in schemes.iter().zip(results) {
+            let nonce = nonce.expect("solver failed");
+            assert!(attempted > 0);
+            assert!(scheme.is_valid(nonce));
+        }
+    }
+
+    #[test]
+    fn test_solve_batch_exhausted() {
+        const LIMIT: u64 = 4096;
+        let prefixes: Vec<String> = (0..20).map(|i| "y".repeat(i * 2)).collect();
+        let schemes: Vec<_> = prefixes
+            .iter()
+            .map(|prefix| LeadingZeroes { prefix, zeroes: 12 })
+            .collect();
+        let results = solve_batch_with_limit(&schemes, LIMIT);
+        for (nonce, attempted) in results {
+            assert_eq!(nonce, None);
+            // the limit is shared by the packed search and the one by one search
+            assert!((LIMIT..2 * LIMIT).contains(&attempted), "{attempted}");
+        }
+    }
+
+    #[test]
+    fn test_custom_scheme() {
+        let long_prefix = "x".repeat(100);
+        for prefix in ["", "hello", &long_prefix] {
+            let scheme = LeadingZeroes { prefix, zeroes: 3 };
+            let (Some(nonce), attempted) = solve_with_limit(&scheme, u64::MAX) else {
+                panic!("solver failed for prefix of length {}", prefix.len());
+            };
+            assert!(attempted > 0);
+            assert!(scheme.is_valid(nonce));
+            let hash = sha2::Sha256::digest(format!("{}{}", prefix, nonce));
+            assert_eq!(hash[0], 0);
+            assert_eq!(hash[1] >> 4, 0);
+        }
+    }
+}
@@ -11,18 +11,44 @@ use axum::{
     routing::{get, post},
 };
 use axum_extra::response::JavaScript;
-use tokio::sync::Semaphore;
 
 use crate::{
     Align16, DecimalSolver,
     adapter::{
-        AnubisChallengeDescriptor, CapJsChallengeDescriptor, GoAwayConfig, SolveCapJsResponse,
+        AnubisChallengeDescriptor, CapJsChallengeDescriptor, DetectedChallenge, GoAwayConfig,
+        McaptchaChallengeDescriptor, SolveCapJsResponse,
     },
     compute_target_anubis,
     message::DecimalMessage,
+    server::{
+        auth::ApiKeys,
+        journal::JobId,
+        queue::{JobOptions, JobQueue, QueueError},
+    },
     solver::{SOLVE_TYPE_LT, Solver},
 };
 
+#[cfg(feature = "grpc")]
+/// gRPC transport for the solver
+pub mod grpc;
+
+/// Priority job queue for the solver pool
+pub mod queue;
+
+/// API-key authentication
+pub mod auth;
+
+#[cfg(feature = "cluster")]
+/// Coordinator/worker horizontal scaling
+pub mod cluster;
+
+/// Crash-safe job journal
+pub mod journal;
+
+#[cfg(unix)]
+/// Unix domain socket transport for the solver
+pub mod uds;
+
 #[cfg(feature = "server-wasm")]
 mod assets {
     use axum::response::{IntoResponse, Response};
@@ -63,7 +89,11 @@ async fn serve_worker() -> JavaScript<&'static str> {
 /// Server app state
 pub struct AppState {
     pool: Arc<rayon::ThreadPool>,
-    semaphore: Arc<Semaphore>,
+    queue: Arc<JobQueue>,
+    api_keys: Option<Arc<ApiKeys>>,
+    #[cfg(feature = "cluster")]
+    coordinator: Option<Arc<cluster::Coordinator>>,
+    journal: Option<Arc<journal::Journal>>,
     limit: u64,
 }
 
@@ -101,11 +131,27 @@ impl AppState {
                     .build()
                     .unwrap(),
             ),
-            semaphore: Arc::new(Semaphore::new(n_threads)),
+            queue: Arc::new(JobQueue::new(n_threads, n_threads * 16)),
+            api_keys: None,
+            #[cfg(feature = "cluster")]
+            coordinator: None,
+            journal: None,
             limit,
         }
     }
 
+    /// sets the maximum number of jobs waiting for a solver thread
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue = Arc::new(JobQueue::new(self.queue.slots(), capacity));
+        self
+    }
+
+    /// requires one of the given API keys on every solve endpoint, an empty set disables authentication
+    pub fn with_api_keys(mut self, keys: ApiKeys) -> Self {
+        self.api_keys = (!keys.is_empty()).then(|| Arc::new(keys));
+        self
+    }
+
     /// returns the effective limit clamped to supported range
     pub const fn effective_limit(&self) -> u64 {
         let cap = match cfg!(feature = "compare-64bit") {
@@ -117,12 +163,26 @@ impl AppState {
 
     /// returns the router
     pub fn router(&self) -> Router {
+        let mut api = Router::new()
+            .route("/solve", post(solve_generic))
+            .route("/api/anubis_offload", post(anubis_offload_api))
+            .route("/api/mcaptcha", post(mcaptcha_api));
+        #[cfg(feature = "cluster")]
+        {
+            api = api.route("/api/cluster/register", post(cluster::register_api));
+        }
+        if let Some(keys) = &self.api_keys {
+            api = api.route_layer(axum::middleware::from_fn_with_state(
+                keys.clone(),
+                auth::require_api_key,
+            ));
+        }
+
         Router::new()
             .route("/", get(index))
             .route("/worker.js", get(serve_worker))
-            .route("/solve", post(solve_generic))
             .route("/pkg/{*file}", get(serve_wasm))
-            .route("/api/anubis_offload", post(anubis_offload_api))
+            .merge(api)
             .layer(tower_http::limit::RequestBodyLimitLayer::new(128 << 10))
             .layer(
                 tower_http::trace::TraceLayer::new_for_http()
@@ -177,6 +237,16 @@ enum SolveError {
 
     #[error("unexpected challenge format")]
     UnexpectedChallengeFormat,
+
+    #[error(transparent)]
+    Queue(#[from] QueueError),
+
+    #[error("missing or invalid API key")]
+    Unauthorized,
+
+    #[cfg(feature = "cluster")]
+    #[error("no cluster worker could solve the challenge")]
+    ClusterUnavailable,
 }
 
 impl IntoResponse for SolveError {
@@ -218,6 +288,27 @@ impl IntoResponse for SolveError {
                 "unexpected challenge format".to_string(),
                 "unexpected_challenge_format",
             ),
+            #[cfg(feature = "cluster")]
+            SolveError::ClusterUnavailable => (
+                axum::http::StatusCode::BAD_GATEWAY,
+                "no cluster worker could solve the challenge".to_string(),
+                "cluster_unavailable",
+            ),
+            SolveError::Unauthorized => (
+                axum::http::StatusCode::UNAUTHORIZED,
+                "missing or invalid API key".to_string(),
+                "unauthorized",
+            ),
+            SolveError::Queue(QueueError::Full) => (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                "job queue is full".to_string(),
+                "queue_full",
+            ),
+            SolveError::Queue(QueueError::DeadlineExceeded) => (
+                axum::http::StatusCode::GATEWAY_TIMEOUT,
+                "job deadline exceeded while queued".to_string(),
+                "deadline_exceeded",
+            ),
             SolveError::EstimatedWorkloadGreaterThanLimit { limit, estimated } => (
                 axum::http::StatusCode::BAD_REQUEST,
                 format!(
@@ -243,37 +334,79 @@ async fn solve_generic(
     remote_addr: axum::extract::ConnectInfo<std::net::SocketAddr>,
     x_forwarded_for: axum_extra::TypedHeader<XForwardedFor>,
     state: State<AppState>,
+    job: JobOptions,
+    job_id: JobId,
     form: Form<SolveForm>,
 ) -> Result<Response, SolveError> {
     let form = form.0;
 
-    let left_strip = form.challenge.find('{').unwrap_or(0);
-    let right_strip = form
-        .challenge
-        .rfind('}')
-        .map(|x| x + 1)
-        .unwrap_or(form.challenge.len());
-    let challenge = &form.challenge[left_strip..right_strip];
+    let challenge =
+        DetectedChallenge::detect(&form.challenge).ok_or(SolveError::InvalidChallenge)?;
 
-    if let Ok(config) = serde_json::from_str(challenge) {
-        return solve_goaway(remote_addr, x_forwarded_for, state, config)
+    match challenge {
+        DetectedChallenge::GoAway(config) => {
+            solve_goaway(remote_addr, x_forwarded_for, state, job, config)
+                .await
+                .map(IntoResponse::into_response)
+        }
+        DetectedChallenge::Anubis(descriptor) => {
+            solve_anubis(remote_addr, x_forwarded_for, state, job, descriptor)
+                .await
+                .map(IntoResponse::into_response)
+        }
+        DetectedChallenge::CapJs(config) => {
+            solve_capjs(remote_addr, x_forwarded_for, state, job, config)
+                .await
+                .map(IntoResponse::into_response)
+        }
+        DetectedChallenge::Mcaptcha(descriptor) => {
+            solve_mcaptcha(state, job, job_id, descriptor, (0, 1))
+                .await
+                .map(IntoResponse::into_response)
+        }
+        challenge => solve_detected(state, job, challenge)
             .await
-            .map(IntoResponse::into_response);
+            .map(IntoResponse::into_response),
     }
+}
 
-    if let Ok(config) = serde_json::from_str(challenge) {
-        return solve_anubis(remote_addr, x_forwarded_for, state, config)
-            .await
-            .map(IntoResponse::into_response);
+/// Solve a detected challenge without a dedicated handler, responding with the proof as text.
+#[tracing::instrument(skip(state, challenge), name = "solve_detected")]
+async fn solve_detected(
+    State(state): State<AppState>,
+    job: JobOptions,
+    challenge: DetectedChallenge,
+) -> Result<String, SolveError> {
+    tracing::info!("solving {} challenge", challenge.scheme());
+
+    if !challenge.supported() {
+        return Err(SolveError::UnexpectedChallengeFormat);
     }
 
-    if let Ok(config) = serde_json::from_str(challenge) {
-        return solve_capjs(remote_addr, x_forwarded_for, state, config)
-            .await
-            .map(IntoResponse::into_response);
+    let estimated_workload = challenge.estimated_workload();
+    if estimated_workload > state.effective_limit() {
+        return Err(SolveError::EstimatedWorkloadGreaterThanLimit {
+            limit: state.effective_limit(),
+            estimated: estimated_workload,
+        });
     }
 
-    Err(SolveError::InvalidChallenge)
+    let (result, attempted_nonces) = {
+        let _permit = state.queue.acquire(job).await?;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        state.pool.spawn(move || {
+            let result = challenge.solve_with_limit(state.limit);
+            tx.send(result).ok();
+        });
+
+        rx.await.map_err(|_| SolveError::SolverFatal)?
+    };
+
+    result.ok_or(SolveError::SolverFailed {
+        limit: state.limit,
+        attempted: attempted_nonces,
+    })
 }
 
 #[tracing::instrument(skip(state, config), name = "solve_capjs")]
@@ -281,6 +414,7 @@ async fn solve_capjs(
     remote_addr: axum::extract::ConnectInfo<std::net::SocketAddr>,
     x_forwarded_for: axum_extra::TypedHeader<XForwardedFor>,
     State(state): State<AppState>,
+    job: JobOptions,
     config: CapJsChallengeDescriptor,
 ) -> Result<Json<SolveCapJsResponse>, SolveError> {
     tracing::info!("solving capjs challenge {:?}", config);
@@ -294,7 +428,7 @@ async fn solve_capjs(
     }
 
     let (result, attempted_nonces) = {
-        let _permit = state.semaphore.acquire().await.unwrap();
+        let _permit = state.queue.acquire(job).await?;
 
         let (tx, rx) = tokio::sync::oneshot::channel();
         state.pool.spawn(move || {
@@ -313,12 +447,144 @@ async fn solve_capjs(
     Ok(Json(response))
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
+struct McaptchaForm {
+    #[serde(flatten)]
+    challenge: McaptchaChallengeDescriptor,
+    #[cfg(feature = "cluster")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shard: Option<cluster::Shard>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct McaptchaResponse {
+    string: String,
+    nonce: u64,
+    result: String,
+    #[serde(rename = "_meta")]
+    meta: OffloadResponseMeta,
+}
+
+async fn mcaptcha_api(
+    state: State<AppState>,
+    job: JobOptions,
+    job_id: JobId,
+    form: Json<McaptchaForm>,
+) -> Result<Json<McaptchaResponse>, SolveError> {
+    let form = form.0;
+    #[cfg(feature = "cluster")]
+    if let Some(shard) = form.shard {
+        if shard.index >= shard.count {
+            return Err(SolveError::InvalidChallenge);
+        }
+        return solve_mcaptcha(
+            state,
+            job,
+            job_id,
+            form.challenge,
+            (shard.index, shard.count),
+        )
+        .await;
+    }
+    solve_mcaptcha(state, job, job_id, form.challenge, (0, 1)).await
+}
+
+#[tracing::instrument(skip(state, descriptor), name = "solve_mcaptcha")]
+async fn solve_mcaptcha(
+    State(state): State<AppState>,
+    job: JobOptions,
+    job_id: JobId,
+    descriptor: McaptchaChallengeDescriptor,
+    shard: (u32, u32),
+) -> Result<Json<McaptchaResponse>, SolveError> {
+    tracing::info!("solving mcaptcha challenge {:?}", descriptor);
+
+    if descriptor.difficulty_factor == 0 {
+        return Err(SolveError::InvalidChallenge);
+    }
+
+    let estimated_workload = descriptor.estimated_workload();
+    if estimated_workload > state.effective_limit() {
+        return Err(SolveError::EstimatedWorkloadGreaterThanLimit {
+            limit: state.effective_limit(),
+            estimated: estimated_workload,
+        });
+    }
+
+    let job_id = job_id.0.filter(|_| state.journal.is_some());
+    if let Some(response) = job_id
+        .as_deref()
+        .and_then(|id| state.journaled_result(id))
+        .filter(|response| response.string == descriptor.string)
+    {
+        return Ok(Json(response));
+    }
+
+    #[cfg(feature = "cluster")]
+    if shard == (0, 1)
+        && let Some(coordinator) = &state.coordinator
+        && let Some(response) = coordinator.dispatch(descriptor.clone()).await
+    {
+        return response.map(Json);
+    }
+
+    if let Some(id) = job_id {
+        let pending = journal::PendingJob {
+            challenge: descriptor,
+            next_bank: shard.0,
+            stride: shard.1,
+            attempted_nonces: 0,
+        };
+        return state.run_journaled(id, pending, job).await.map(Json);
+    }
+
+    let string = descriptor.string.clone();
+
+    let ((result, attempted_nonces), elapsed) = {
+        let _permit = state.queue.acquire(job).await?;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        state.pool.spawn(move || {
+            let start = std::time::Instant::now();
+            let result = descriptor.solve_shard_with_limit(shard.0, shard.1, state.limit);
+            let elapsed = start.elapsed();
+            tx.send((result, elapsed)).ok();
+        });
+
+        rx.await.map_err(|_| SolveError::SolverFatal)?
+    };
+
+    let (nonce, result) = result.ok_or(SolveError::SolverFailed {
+        limit: state.limit,
+        attempted: attempted_nonces,
+    })?;
+
+    tracing::info!(
+        "solver completed in {}ms; nonce: {}; hash rate: {:.2} MH/s; limit used: {:.2}%",
+        elapsed.as_millis(),
+        nonce,
+        attempted_nonces as f32 / elapsed.as_secs_f32() / 1024.0 / 1024.0,
+        attempted_nonces as f32 / state.limit as f32 * 100.0,
+    );
+
+    Ok(Json(McaptchaResponse {
+        string,
+        nonce,
+        result: crate::extract128_be(result).to_string(),
+        meta: OffloadResponseMeta {
+            elapsed: elapsed.as_millis() as u64,
+            attempted_nonces,
+        },
+    }))
+}
+
 #[tracing::instrument(skip(state, config), name = "solve_goaway")]
 
 async fn solve_goaway(
     remote_addr: axum::extract::ConnectInfo<std::net::SocketAddr>,
     x_forwarded_for: axum_extra::TypedHeader<XForwardedFor>,
     State(state): State<AppState>,
+    job: JobOptions,
     config: GoAwayConfig,
 ) -> Result<String, SolveError> {
     tracing::info!("solving goaway challenge {:?}", config);
@@ -339,7 +605,7 @@ async fn solve_goaway(
     }
 
     let ((result, attempted_nonces), elapsed) = {
-        let _permit = state.semaphore.acquire().await.unwrap();
+        let _permit = state.queue.acquire(job).await?;
 
         let (tx, rx) = tokio::sync::oneshot::channel();
         state.pool.spawn(move || {
@@ -433,7 +699,7 @@ async fn solve_goaway(
     Ok(String::from_utf8(output).unwrap())
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 struct OffloadResponseMeta {
     elapsed: u64,
     attempted_nonces: u64,
@@ -458,6 +724,7 @@ struct OffloadForm {
 #[tracing::instrument(skip(state, form), name = "offload_api")]
 async fn anubis_offload_api(
     State(state): State<AppState>,
+    job: JobOptions,
     form: Json<OffloadForm>,
 ) -> Result<Json<OffloadResponse>, SolveError> {
     let form = form.0;
@@ -486,7 +753,7 @@ async fn anubis_offload_api(
         let elapsed = start.elapsed();
         ((result, solver.get_attempted_nonces()), elapsed)
     } else {
-        let _permit = state.semaphore.acquire().await.unwrap();
+        let _permit = state.queue.acquire(job).await?;
 
         let data_clone = form.data.clone();
 
@@ -574,6 +841,7 @@ async fn solve_anubis(
     remote_addr: axum::extract::ConnectInfo<std::net::SocketAddr>,
     x_forwarded_for: axum_extra::TypedHeader<XForwardedFor>,
     State(state): State<AppState>,
+    job: JobOptions,
     descriptor: AnubisChallengeDescriptor,
 ) -> Result<String, SolveError> {
     let rules = descriptor.rules();
@@ -602,7 +870,7 @@ async fn solve_anubis(
         let elapsed = start.elapsed();
         (result, elapsed)
     } else {
-        let _permit = state.semaphore.acquire().await.unwrap();
+        let _permit = state.queue.acquire(job).await?;
 
         let (tx, rx) = tokio::sync::oneshot::channel();
         state.pool.spawn(move || {
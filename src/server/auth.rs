@@ -0,0 +1,90 @@
+//! API-key authentication for the solver daemon.
+//!
+//! Keys are presented either as `Authorization: Bearer <key>` or as `X-Api-Key: <key>`,
+//! the same header names are accepted as gRPC metadata.
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::SolveError;
+
+/// Header carrying a bare API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Debug, Clone, Default)]
+/// A set of accepted API keys
+pub struct ApiKeys {
+    keys: Vec<Box<[u8]>>,
+}
+
+impl ApiKeys {
+    /// creates a new key set, empty keys are ignored
+    pub fn new<I, S>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            keys: keys
+                .into_iter()
+                .filter(|k| !k.as_ref().trim().is_empty())
+                .map(|k| k.as_ref().trim().as_bytes().into())
+                .collect(),
+        }
+    }
+
+    /// returns true if no keys are configured
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// checks a presented key against every configured key without short-circuiting
+    pub fn verify(&self, presented: &[u8]) -> bool {
+        self.keys
+            .iter()
+            .fold(false, |acc, key| acc | constant_time_eq(key, presented))
+    }
+
+    /// extracts the presented key from either of the accepted headers
+    pub(super) fn presented<'a>(
+        authorization: Option<&'a [u8]>,
+        api_key: Option<&'a [u8]>,
+    ) -> Option<&'a [u8]> {
+        authorization
+            .and_then(|v| v.strip_prefix(b"Bearer "))
+            .or(api_key)
+            .map(<[u8]>::trim_ascii)
+    }
+
+    fn verify_headers(&self, headers: &HeaderMap) -> bool {
+        let authorization = headers
+            .get(axum::http::header::AUTHORIZATION)
+            .map(|v| v.as_bytes());
+        let api_key = headers.get(API_KEY_HEADER).map(|v| v.as_bytes());
+        Self::presented(authorization, api_key).is_some_and(|key| self.verify(key))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub(super) async fn require_api_key(
+    State(keys): State<Arc<ApiKeys>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if !keys.verify_headers(req.headers()) {
+        return SolveError::Unauthorized.into_response();
+    }
+    next.run(req).await
+}
@@ -0,0 +1,233 @@
+//! Coordinator/worker horizontal scaling.
+//!
+//! Workers periodically register their base URL with a coordinator (`POST /api/cluster/register`).
+//! The coordinator forwards mCaptcha jobs received on `/api/mcaptcha` to live workers:
+//! challenges below the shard threshold go to one worker in round-robin order,
+//! harder ones are split by search bank across every live worker and the first proof wins.
+//! Other challenge types are still solved locally by the coordinator.
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{Json, extract::State, http::StatusCode};
+
+use super::{AppState, McaptchaForm, McaptchaResponse, SolveError, auth::API_KEY_HEADER};
+use crate::adapter::McaptchaChallengeDescriptor;
+
+/// How often a worker re-registers with its coordinator
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a worker stays eligible for jobs after its last heartbeat
+pub const WORKER_TTL: Duration = Duration::from_secs(30);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+/// Worker registration sent to the coordinator
+pub struct Registration {
+    /// The base URL the coordinator reaches the worker on
+    pub url: String,
+    /// The number of solver threads on the worker
+    pub slots: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+/// A disjoint slice of the nonce space of a single challenge
+pub struct Shard {
+    /// The index of this shard
+    pub index: u32,
+    /// The total number of shards
+    pub count: u32,
+}
+
+struct WorkerEntry {
+    slots: usize,
+    last_seen: Instant,
+}
+
+/// Coordinator side of the cluster
+pub struct Coordinator {
+    client: reqwest::Client,
+    api_key: Option<String>,
+    shard_threshold: u64,
+    next: AtomicUsize,
+    workers: Mutex<HashMap<String, WorkerEntry>>,
+}
+
+impl Coordinator {
+    /// creates a new coordinator
+    ///
+    /// `api_key` is sent to workers, challenges with an estimated workload of at least
+    /// `shard_threshold` are split across all live workers.
+    pub fn new(api_key: Option<String>, shard_threshold: u64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+            shard_threshold,
+            next: AtomicUsize::new(0),
+            workers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// records a worker heartbeat
+    pub fn register(&self, registration: Registration) {
+        let mut workers = self.workers.lock().unwrap();
+        workers.insert(
+            registration.url.trim_end_matches('/').to_string(),
+            WorkerEntry {
+                slots: registration.slots,
+                last_seen: Instant::now(),
+            },
+        );
+    }
+
+    /// returns the URLs of workers seen within [`WORKER_TTL`], evicting the rest
+    pub fn live_workers(&self) -> Vec<String> {
+        let mut workers = self.workers.lock().unwrap();
+        workers.retain(|_, entry| entry.last_seen.elapsed() < WORKER_TTL && entry.slots > 0);
+        let mut urls: Vec<_> = workers.keys().cloned().collect();
+        urls.sort_unstable();
+        urls
+    }
+
+    async fn forward(
+        &self,
+        worker: &str,
+        descriptor: &McaptchaChallengeDescriptor,
+        shard: Option<Shard>,
+    ) -> Result<McaptchaResponse, reqwest::Error> {
+        let mut request =
+            self.client
+                .post(format!("{}/api/mcaptcha", worker))
+                .json(&McaptchaForm {
+                    challenge: descriptor.clone(),
+                    shard,
+                });
+        if let Some(api_key) = &self.api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        request.send().await?.error_for_status()?.json().await
+    }
+
+    /// solves a challenge on the cluster, returns `None` if no worker is registered
+    pub(super) async fn dispatch(
+        self: &Arc<Self>,
+        descriptor: McaptchaChallengeDescriptor,
+    ) -> Option<Result<McaptchaResponse, SolveError>> {
+        let workers = self.live_workers();
+        if workers.is_empty() {
+            return None;
+        }
+
+        if descriptor.estimated_workload() < self.shard_threshold || workers.len() == 1 {
+            let worker = &workers[self.next.fetch_add(1, Ordering::Relaxed) % workers.len()];
+            return Some(match self.forward(worker, &descriptor, None).await {
+                Ok(response) => checked_response(&descriptor, response).ok_or_else(|| {
+                    tracing::warn!("worker {} returned an insufficient proof", worker);
+                    SolveError::ClusterUnavailable
+                }),
+                Err(e) => {
+                    tracing::warn!("worker {} failed: {}", worker, e);
+                    Err(SolveError::ClusterUnavailable)
+                }
+            });
+        }
+
+        let count = workers.len() as u32;
+        let mut set = tokio::task::JoinSet::new();
+        for (index, worker) in workers.into_iter().enumerate() {
+            let this = self.clone();
+            let descriptor = descriptor.clone();
+            let shard = Shard {
+                index: index as u32,
+                count,
+            };
+            set.spawn(async move {
+                let result = this.forward(&worker, &descriptor, Some(shard)).await;
+                (worker, result)
+            });
+        }
+
+        while let Some(joined) = set.join_next().await {
+            match joined {
+                Ok((worker, Ok(response))) => match checked_response(&descriptor, response) {
+                    Some(response) => {
+                        set.abort_all();
+                        return Some(Ok(response));
+                    }
+                    None => tracing::warn!("worker {} returned an insufficient proof", worker),
+                },
+                Ok((worker, Err(e))) => tracing::warn!("worker {} failed: {}", worker, e),
+                Err(e) => tracing::warn!("shard task failed: {}", e),
+            }
+        }
+        Some(Err(SolveError::ClusterUnavailable))
+    }
+}
+
+/// verifies a worker's proof, with the result and challenge string taken from the verification
+/// instead of trusting the worker
+fn checked_response(
+    descriptor: &McaptchaChallengeDescriptor,
+    mut response: McaptchaResponse,
+) -> Option<McaptchaResponse> {
+    let verification = descriptor.verify(response.nonce);
+    if !verification.is_sufficient() {
+        return None;
+    }
+    response.string = descriptor.string.clone();
+    response.result = verification.result.to_string();
+    Some(response)
+}
+
+pub(super) async fn register_api(
+    State(state): State<AppState>,
+    Json(registration): Json<Registration>,
+) -> StatusCode {
+    let Some(coordinator) = &state.coordinator else {
+        return StatusCode::NOT_FOUND;
+    };
+    tracing::debug!("worker heartbeat from {}", registration.url);
+    coordinator.register(registration);
+    StatusCode::NO_CONTENT
+}
+
+impl AppState {
+    /// turns this daemon into a coordinator that forwards mCaptcha jobs to registered workers
+    pub fn with_coordinator(mut self, coordinator: Coordinator) -> Self {
+        self.coordinator = Some(Arc::new(coordinator));
+        self
+    }
+
+    /// registers this daemon as a worker of `coordinator` every [`HEARTBEAT_INTERVAL`]
+    ///
+    /// `advertise` is the base URL the coordinator reaches this daemon on.
+    pub async fn join_cluster(
+        &self,
+        coordinator: String,
+        advertise: String,
+        api_key: Option<String>,
+    ) {
+        let client = reqwest::Client::new();
+        let url = format!("{}/api/cluster/register", coordinator.trim_end_matches('/'));
+        let registration = Registration {
+            url: advertise,
+            slots: self.queue.slots(),
+        };
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut request = client.post(&url).json(&registration);
+            if let Some(api_key) = &api_key {
+                request = request.header(API_KEY_HEADER, api_key);
+            }
+            match request.send().await.and_then(|r| r.error_for_status()) {
+                Ok(_) => tracing::debug!("registered with coordinator {}", coordinator),
+                Err(e) => tracing::warn!("failed to register with coordinator: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::Instant;
+
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use super::{
+    AppState, SolveError,
+    auth::{API_KEY_HEADER, ApiKeys},
+    queue::{JobOptions, QueueError},
+};
+use crate::adapter::McaptchaChallengeDescriptor;
+
+/// Generated protobuf types and service stubs
+#[allow(missing_docs, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("pow_buster.v1");
+}
+
+use proto::{
+    SolveProgress, SolveRequest, SolveResponse, solve_progress,
+    solver_server::{Solver, SolverServer},
+};
+
+impl From<SolveError> for Status {
+    fn from(e: SolveError) -> Self {
+        match e {
+            SolveError::Json(_) | SolveError::InvalidChallenge => {
+                Status::invalid_argument(e.to_string())
+            }
+            SolveError::EstimatedWorkloadGreaterThanLimit { .. } => {
+                Status::failed_precondition(e.to_string())
+            }
+            SolveError::SolverFailed { .. } => Status::resource_exhausted(e.to_string()),
+            SolveError::SolverFatal => Status::internal(e.to_string()),
+            SolveError::UnexpectedOrigin => Status::permission_denied(e.to_string()),
+            SolveError::Unauthorized => Status::unauthenticated(e.to_string()),
+            #[cfg(feature = "cluster")]
+            SolveError::ClusterUnavailable => Status::unavailable(e.to_string()),
+            SolveError::UnexpectedChallengeFormat => Status::unimplemented(e.to_string()),
+            SolveError::Queue(QueueError::Full) => Status::unavailable(e.to_string()),
+            SolveError::Queue(QueueError::DeadlineExceeded) => {
+                Status::deadline_exceeded(e.to_string())
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+/// gRPC solver service
+pub struct SolverService {
+    state: AppState,
+}
+
+impl AppState {
+    /// returns the gRPC solver service
+    pub fn grpc_service(&self) -> SolverServer<SolverService> {
+        SolverServer::new(SolverService {
+            state: self.clone(),
+        })
+    }
+}
+
+impl SolverService {
+    fn descriptor(request: SolveRequest) -> Result<McaptchaChallengeDescriptor, SolveError> {
+        let challenge = request.mcaptcha.ok_or(SolveError::InvalidChallenge)?;
+        if challenge.difficulty_factor == 0 {
+            return Err(SolveError::InvalidChallenge);
+        }
+        Ok(McaptchaChallengeDescriptor {
+            salt: challenge.salt,
+            string: challenge.string,
+            difficulty_factor: challenge.difficulty_factor,
+        })
+    }
+
+    fn check_api_key<T>(&self, request: &Request<T>) -> Result<(), SolveError> {
+        let Some(keys) = &self.state.api_keys else {
+            return Ok(());
+        };
+        let metadata = request.metadata();
+        let authorization = metadata.get("authorization").map(|v| v.as_bytes());
+        let api_key = metadata.get(API_KEY_HEADER).map(|v| v.as_bytes());
+        match ApiKeys::presented(authorization, api_key) {
+            Some(key) if keys.verify(key) => Ok(()),
+            _ => Err(SolveError::Unauthorized),
+        }
+    }
+
+    fn check_limit(&self, descriptor: &McaptchaChallengeDescriptor) -> Result<(), SolveError> {
+        let estimated_workload = descriptor.estimated_workload();
+        if estimated_workload > self.state.effective_limit() {
+            return Err(SolveError::EstimatedWorkloadGreaterThanLimit {
+                limit: self.state.effective_limit(),
+                estimated: estimated_workload,
+            });
+        }
+        Ok(())
+    }
+
+    /// solve on the pool, the caller is expected to hold a queue permit
+    async fn spawn_solve(
+        state: &AppState,
+        descriptor: McaptchaChallengeDescriptor,
+    ) -> Result<SolveResponse, SolveError> {
+        let limit = state.limit;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        state.pool.spawn(move || {
+            let start = Instant::now();
+            let result = descriptor.solve_with_limit(limit);
+            tx.send((result, start.elapsed())).ok();
+        });
+
+        let ((result, attempted_nonces), elapsed) =
+            rx.await.map_err(|_| SolveError::SolverFatal)?;
+        let (nonce, result) = result.ok_or(SolveError::SolverFailed {
+            limit,
+            attempted: attempted_nonces,
+        })?;
+
+        Ok(SolveResponse {
+            nonce,
+            result: crate::extract128_be(result).to_string(),
+            attempted_nonces,
+            elapsed_us: elapsed.as_micros() as u64,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Solver for SolverService {
+    #[tracing::instrument(skip(self, request), name = "grpc_solve")]
+    async fn solve(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<SolveResponse>, Status> {
+        self.check_api_key(&request)?;
+        let descriptor = Self::descriptor(request.into_inner())?;
+        tracing::info!("solving mcaptcha challenge {:?}", descriptor);
+        self.check_limit(&descriptor)?;
+
+        let _permit = self
+            .state
+            .queue
+            .acquire(JobOptions::default())
+            .await
+            .map_err(SolveError::from)?;
+        Ok(Response::new(
+            Self::spawn_solve(&self.state, descriptor).await?,
+        ))
+    }
+
+    type SolveStreamStream = ReceiverStream<Result<SolveProgress, Status>>;
+
+    #[tracing::instrument(skip(self, request), name = "grpc_solve_stream")]
+    async fn solve_stream(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<Self::SolveStreamStream>, Status> {
+        self.check_api_key(&request)?;
+        let descriptor = Self::descriptor(request.into_inner())?;
+        tracing::info!("solving mcaptcha challenge {:?}", descriptor);
+        self.check_limit(&descriptor)?;
+
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            let stage = |stage: solve_progress::Stage| SolveProgress {
+                event: Some(solve_progress::Event::Stage(stage as i32)),
+            };
+
+            if tx
+                .send(Ok(stage(solve_progress::Stage::Queued)))
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let _permit = match state.queue.acquire(JobOptions::default()).await {
+                Ok(permit) => permit,
+                Err(e) => {
+                    tx.send(Err(SolveError::from(e).into())).await.ok();
+                    return;
+                }
+            };
+            // the caller went away while queued, don't waste a solver thread on it
+            if tx
+                .send(Ok(stage(solve_progress::Stage::Solving)))
+                .await
+                .is_err()
+            {
+                return;
+            }
+
+            let event = Self::spawn_solve(&state, descriptor)
+                .await
+                .map(|solved| SolveProgress {
+                    event: Some(solve_progress::Event::Solved(solved)),
+                })
+                .map_err(Status::from);
+            tx.send(event).await.ok();
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
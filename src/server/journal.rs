@@ -0,0 +1,423 @@
+//! Crash-safe journal of accepted mCaptcha jobs.
+//!
+//! Jobs submitted with an `X-Job-Id` header are written to `<dir>/<id>.pending.json` before
+//! they are queued and checkpointed every time a search bank is exhausted. Once solved the
+//! entry is replaced by `<id>.done.json`, so a client that retries with the same id after a
+//! daemon restart receives the proof without redoing the work. Pending entries found on
+//! startup are resumed from their last checkpoint at bulk priority.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use axum::http::request::Parts;
+
+use super::{
+    AppState, McaptchaResponse, OffloadResponseMeta,
+    queue::{JobOptions, Priority},
+};
+use crate::adapter::McaptchaChallengeDescriptor;
+
+/// How long completed results are kept on disk
+pub const RESULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Default)]
+/// Client supplied job id, taken from the `X-Job-Id` request header
+///
+/// Ids are 1 to 64 ASCII alphanumerics, `-` or `_`, anything else is ignored.
+pub struct JobId(pub Option<String>);
+
+impl JobId {
+    fn parse(value: &str) -> Option<String> {
+        let valid = !value.is_empty()
+            && value.len() <= 64
+            && value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        valid.then(|| value.to_string())
+    }
+}
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for JobId {
+    type Rejection = core::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(JobId(
+            parts
+                .headers
+                .get("X-Job-Id")
+                .and_then(|value| value.to_str().ok())
+                .and_then(JobId::parse),
+        ))
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+/// A journaled job that has not completed yet
+pub struct PendingJob {
+    /// The challenge
+    pub challenge: McaptchaChallengeDescriptor,
+    /// The next search bank to visit
+    pub next_bank: u32,
+    /// The distance between visited search banks
+    pub stride: u32,
+    /// The nonces attempted before the checkpoint
+    pub attempted_nonces: u64,
+}
+
+/// On-disk job journal
+pub struct Journal {
+    dir: PathBuf,
+}
+
+impl Journal {
+    /// opens a journal directory, creating it if needed and pruning expired results
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let expired = entry
+                .metadata()?
+                .modified()?
+                .elapsed()
+                .is_ok_and(|age| age > RESULT_TTL);
+            if name.ends_with(".tmp") || (name.ends_with(".done.json") && expired) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(Self { dir })
+    }
+
+    fn path(&self, id: &str, kind: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}.json", id, kind))
+    }
+
+    /// writes a file so that it is either fully present or absent after a crash
+    fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+        use std::io::Write;
+
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    /// records or checkpoints a pending job
+    pub fn record(&self, id: &str, job: &PendingJob) -> io::Result<()> {
+        Self::write_atomic(&self.path(id, "pending"), &serde_json::to_vec(job)?)
+    }
+
+    /// returns every pending job left over from a previous run
+    pub fn pending(&self) -> io::Result<Vec<(String, PendingJob)>> {
+        let mut jobs = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".pending.json"))
+            else {
+                continue;
+            };
+            match serde_json::from_slice(&fs::read(&path)?) {
+                Ok(job) => jobs.push((id.to_string(), job)),
+                Err(e) => tracing::warn!("discarding corrupt journal entry {}: {}", id, e),
+            }
+        }
+        Ok(jobs)
+    }
+
+    fn complete(&self, id: &str, response: &McaptchaResponse) -> io::Result<()> {
+        Self::write_atomic(&self.path(id, "done"), &serde_json::to_vec(response)?)?;
+        self.discard(id)
+    }
+
+    /// removes a pending job
+    pub fn discard(&self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id, "pending")) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn result(&self, id: &str) -> Option<McaptchaResponse> {
+        let path = self.path(id, "done");
+        let data = fs::read(&path).ok()?;
+        let fresh = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|mtime| {
+                SystemTime::now()
+                    .duration_since(mtime)
+                    .is_ok_and(|age| age <= RESULT_TTL)
+            });
+        if !fresh {
+            return None;
+        }
+        serde_json::from_slice(&data).ok()
+    }
+}
+
+impl AppState {
+    /// journals mCaptcha jobs that carry an `X-Job-Id` header
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(Arc::new(journal));
+        self
+    }
+
+    /// resumes jobs left pending by a previous run, returns the number of resumed jobs
+    pub fn recover_journal(&self) -> io::Result<usize> {
+        let Some(journal) = &self.journal else {
+            return Ok(0);
+        };
+        let pending = journal.pending()?;
+        let count = pending.len();
+        for (id, job) in pending {
+            tracing::info!(
+                "resuming journaled job {} at search bank {}",
+                id,
+                job.next_bank
+            );
+            let state = self.clone();
+            tokio::spawn(async move {
+                let options = JobOptions {
+                    priority: Priority::Bulk,
+                    deadline: None,
+                };
+                if let Err(e) = state.run_journaled(id.clone(), job, options).await {
+                    tracing::warn!("journaled job {} failed: {}", id, e);
+                }
+            });
+        }
+        Ok(count)
+    }
+
+    /// returns the stored result of a completed job
+    pub(super) fn journaled_result(&self, id: &str) -> Option<McaptchaResponse> {
+        self.journal.as_ref()?.result(id)
+    }
+
+    /// solves a journaled job on the pool, checkpointing after each search bank
+    pub(super) async fn run_journaled(
+        &self,
+        id: String,
+        job: PendingJob,
+        options: JobOptions,
+    ) -> Result<McaptchaResponse, super::SolveError> {
+        let journal = self.journal.clone().ok_or(super::SolveError::SolverFatal)?;
+        journal.record(&id, &job).map_err(|e| {
+            tracing::error!("failed to journal job {}: {}", id, e);
+            super::SolveError::SolverFatal
+        })?;
+
+        let _permit = self.queue.acquire(options).await?;
+
+        let limit = self.limit.saturating_sub(job.attempted_nonces);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        {
+            let journal = journal.clone();
+            let id = id.clone();
+            let job = job.clone();
+            self.pool.spawn(move || {
+                let start = std::time::Instant::now();
+                let (result, attempted) = job.challenge.solve_banks_with_limit(
+                    job.next_bank,
+                    job.stride,
+                    limit,
+                    |next_bank, attempted| {
+                        let checkpoint = PendingJob {
+                            next_bank,
+                            attempted_nonces: job.attempted_nonces + attempted,
+                            ..job.clone()
+                        };
+                        if let Err(e) = journal.record(&id, &checkpoint) {
+                            tracing::warn!("failed to checkpoint job {}: {}", id, e);
+                        }
+                        core::ops::ControlFlow::Continue(())
+                    },
+                );
+                tx.send((result, job.attempted_nonces + attempted, start.elapsed()))
+                    .ok();
+            });
+        }
+        let (result, attempted_nonces, elapsed) =
+            rx.await.map_err(|_| super::SolveError::SolverFatal)?;
+
+        let Some((nonce, result)) = result else {
+            journal.discard(&id).ok();
+            return Err(super::SolveError::SolverFailed {
+                limit: self.limit,
+                attempted: attempted_nonces,
+            });
+        };
+
+        let response = McaptchaResponse {
+            string: job.challenge.string,
+            nonce,
+            result: crate::extract128_be(result).to_string(),
+            meta: OffloadResponseMeta {
+                elapsed: elapsed.as_millis() as u64,
+                attempted_nonces,
+            },
+        };
+        if let Err(e) = journal.complete(&id, &response) {
+            tracing::warn!("failed to store result of job {}: {}", id, e);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// an empty journal directory unique to a test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pow-buster-journal-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn challenge() -> McaptchaChallengeDescriptor {
+        McaptchaChallengeDescriptor {
+            salt: "x".repeat(33),
+            string: "journal".to_string(),
+            difficulty_factor: 1000,
+        }
+    }
+
+    fn response(nonce: u64) -> McaptchaResponse {
+        McaptchaResponse {
+            string: "journal".to_string(),
+            nonce,
+            result: "42".to_string(),
+            meta: OffloadResponseMeta {
+                elapsed: 1,
+                attempted_nonces: 2,
+            },
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = temp_dir("round-trip");
+        let journal = Journal::open(&dir).unwrap();
+        let job = PendingJob {
+            challenge: challenge(),
+            next_bank: 0,
+            stride: 2,
+            attempted_nonces: 0,
+        };
+        journal.record("job-1", &job).unwrap();
+        journal
+            .record(
+                "job-1",
+                &PendingJob {
+                    next_bank: 4,
+                    attempted_nonces: 1234,
+                    ..job
+                },
+            )
+            .unwrap();
+
+        // a restarted daemon sees the last checkpoint only
+        let pending = Journal::open(&dir).unwrap().pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        let (id, job) = &pending[0];
+        assert_eq!(id, "job-1");
+        assert_eq!((job.next_bank, job.stride), (4, 2));
+        assert_eq!(job.attempted_nonces, 1234);
+        assert_eq!(job.challenge.string, "journal");
+
+        let (result, attempted) =
+            job.challenge
+                .solve_banks_with_limit(job.next_bank, job.stride, u64::MAX, |_, _| {
+                    core::ops::ControlFlow::Continue(())
+                });
+        let (nonce, _) = result.expect("solver failed");
+        assert!(attempted > 0);
+        assert!(job.challenge.verify(nonce).is_sufficient());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_complete_replaces_pending() {
+        let dir = temp_dir("complete");
+        let journal = Journal::open(&dir).unwrap();
+        let job = PendingJob {
+            challenge: challenge(),
+            next_bank: 0,
+            stride: 1,
+            attempted_nonces: 0,
+        };
+        journal.record("job-2", &job).unwrap();
+        assert!(journal.result("job-2").is_none());
+
+        journal.complete("job-2", &response(7)).unwrap();
+        assert!(!dir.join("job-2.pending.json").exists());
+        assert!(dir.join("job-2.done.json").exists());
+        assert!(journal.pending().unwrap().is_empty());
+        let stored = journal.result("job-2").expect("result is stored");
+        assert_eq!(stored.nonce, 7);
+        assert_eq!(stored.result, "42");
+        assert_eq!(stored.meta.attempted_nonces, 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_removes_stale_tmp() {
+        let dir = temp_dir("tmp");
+        let journal = Journal::open(&dir).unwrap();
+        let job = PendingJob {
+            challenge: challenge(),
+            next_bank: 0,
+            stride: 1,
+            attempted_nonces: 0,
+        };
+        journal.record("job-3", &job).unwrap();
+        // a write interrupted by a crash, before the rename
+        fs::write(dir.join("job-4.pending.tmp"), b"{\"chal").unwrap();
+
+        let journal = Journal::open(&dir).unwrap();
+        assert!(!dir.join("job-4.pending.tmp").exists());
+        let pending = journal.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].0, "job-3");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_result_expires() {
+        let dir = temp_dir("ttl");
+        let journal = Journal::open(&dir).unwrap();
+        journal.complete("job-5", &response(9)).unwrap();
+        assert!(journal.result("job-5").is_some());
+
+        let path = dir.join("job-5.done.json");
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - RESULT_TTL - Duration::from_secs(60))
+            .unwrap();
+        assert!(journal.result("job-5").is_none());
+
+        // and is pruned on the next start
+        Journal::open(&dir).unwrap();
+        assert!(!path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
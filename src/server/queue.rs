@@ -0,0 +1,226 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::http::request::Parts;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+/// Job priority, higher priorities are dispatched first
+pub enum Priority {
+    /// Bulk jobs such as load tests
+    Bulk,
+    /// Interactive jobs where a user is waiting
+    #[default]
+    Interactive,
+}
+
+impl core::str::FromStr for Priority {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bulk" => Ok(Priority::Bulk),
+            "interactive" => Ok(Priority::Interactive),
+            _ => Err(format!("invalid priority: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Scheduling options of a job
+///
+/// Over HTTP these are taken from the `X-Job-Priority` (`interactive` or `bulk`)
+/// and `X-Job-Deadline-Ms` (milliseconds from now) request headers.
+pub struct JobOptions {
+    /// The priority
+    pub priority: Priority,
+    /// The instant after which the job is dropped if it is still queued
+    pub deadline: Option<Instant>,
+}
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for JobOptions {
+    type Rejection = core::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        Ok(JobOptions {
+            priority: header("X-Job-Priority")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_default(),
+            deadline: header("X-Job-Deadline-Ms")
+                .and_then(|value| value.parse().ok())
+                .map(|ms| Instant::now() + Duration::from_millis(ms)),
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Job queue error
+pub enum QueueError {
+    #[error("job queue is full")]
+    /// The queue is at capacity
+    Full,
+    #[error("job deadline exceeded while queued")]
+    /// The job deadline passed before a solver thread was free
+    DeadlineExceeded,
+}
+
+struct Waiter {
+    priority: Priority,
+    seq: u64,
+    deadline: Option<Instant>,
+    tx: oneshot::Sender<Permit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    // max-heap: higher priority first, then first come first served
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct Inner {
+    available: usize,
+    seq: u64,
+    waiting: BinaryHeap<Waiter>,
+}
+
+/// A bounded priority queue handing out solver slots
+pub struct JobQueue {
+    slots: usize,
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+/// A solver slot, returned to the queue on drop
+pub struct Permit {
+    queue: Option<Arc<JobQueue>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            queue.release();
+        }
+    }
+}
+
+impl JobQueue {
+    /// creates a new queue with `slots` concurrent jobs and at most `capacity` waiting jobs
+    pub fn new(slots: usize, capacity: usize) -> Self {
+        Self {
+            slots,
+            capacity,
+            inner: Mutex::new(Inner {
+                available: slots,
+                seq: 0,
+                waiting: BinaryHeap::new(),
+            }),
+        }
+    }
+
+    /// returns the number of concurrent jobs
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    /// returns the maximum number of waiting jobs
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// returns the number of waiting jobs
+    pub fn waiting(&self) -> usize {
+        self.inner.lock().unwrap().waiting.len()
+    }
+
+    /// waits for a solver slot
+    ///
+    /// Dropping the returned future gives up the place in the queue.
+    pub async fn acquire(self: &Arc<Self>, options: JobOptions) -> Result<Permit, QueueError> {
+        if options
+            .deadline
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            return Err(QueueError::DeadlineExceeded);
+        }
+
+        let rx = {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.available > 0 && inner.waiting.is_empty() {
+                inner.available -= 1;
+                return Ok(Permit {
+                    queue: Some(self.clone()),
+                });
+            }
+            if inner.waiting.len() >= self.capacity {
+                inner.waiting.retain(|waiter| !waiter.tx.is_closed());
+                if inner.waiting.len() >= self.capacity {
+                    return Err(QueueError::Full);
+                }
+            }
+            let (tx, rx) = oneshot::channel();
+            inner.seq += 1;
+            let seq = inner.seq;
+            inner.waiting.push(Waiter {
+                priority: options.priority,
+                seq,
+                deadline: options.deadline,
+                tx,
+            });
+            rx
+        };
+
+        // the sender is only dropped when the job expired in the queue
+        match options.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), rx)
+                .await
+                .map_err(|_| QueueError::DeadlineExceeded)?,
+            None => rx.await,
+        }
+        .map_err(|_| QueueError::DeadlineExceeded)
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        while let Some(waiter) = inner.waiting.pop() {
+            if waiter.deadline.is_some_and(|deadline| deadline <= now) {
+                continue;
+            }
+            // if the waiter went away after this the permit is dropped on its side and released again
+            match waiter.tx.send(Permit {
+                queue: Some(self.clone()),
+            }) {
+                Ok(()) => return,
+                // the waiter is gone, don't let the permit release itself while we hold the lock
+                Err(mut permit) => permit.queue = None,
+            }
+        }
+        inner.available += 1;
+    }
+}
@@ -0,0 +1,189 @@
+//! Unix domain socket transport with a compact length-prefixed binary protocol.
+//!
+//! All integers are little-endian. Every frame is prefixed by a `u32` payload length,
+//! requests on a connection are answered in order.
+//!
+//! Request payload:
+//!
+//! | size        | field                      |
+//! | ----------- | -------------------------- |
+//! | 1           | version (`1`)              |
+//! | 4           | mCaptcha difficulty factor |
+//! | 2           | salt length `n`            |
+//! | n           | salt                       |
+//! | (remaining) | phrase                     |
+//!
+//! Response payload:
+//!
+//! | size | field                                     |
+//! | ---- | ----------------------------------------- |
+//! | 1    | status (`0` on success, see [`Status`])   |
+//! | 8    | nonce                                     |
+//! | 16   | top 128 bits of the hash, big-endian      |
+//! | 8    | attempted nonces                          |
+//!
+//! On failure the status is followed by a UTF-8 error message instead.
+
+use std::{os::unix::fs::FileTypeExt, path::Path};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+};
+
+use super::{AppState, SolveError, queue::JobOptions};
+use crate::adapter::McaptchaChallengeDescriptor;
+
+/// Protocol version
+pub const VERSION: u8 = 1;
+
+/// Maximum accepted request payload length
+pub const MAX_FRAME_LEN: u32 = 128 << 10;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Response status
+pub enum Status {
+    /// The challenge was solved
+    Ok = 0,
+    /// The request is malformed or the challenge is invalid
+    InvalidChallenge = 1,
+    /// The estimated workload is greater than the server limit
+    EstimatedWorkloadGreaterThanLimit = 2,
+    /// The solver failed or the server limit was reached
+    SolverFailed = 3,
+    /// The solver crashed
+    SolverFatal = 4,
+    /// The job queue is full or the job expired in the queue
+    Busy = 5,
+}
+
+impl From<&SolveError> for Status {
+    fn from(e: &SolveError) -> Self {
+        match e {
+            SolveError::EstimatedWorkloadGreaterThanLimit { .. } => {
+                Status::EstimatedWorkloadGreaterThanLimit
+            }
+            SolveError::SolverFailed { .. } => Status::SolverFailed,
+            SolveError::SolverFatal => Status::SolverFatal,
+            SolveError::Queue(_) => Status::Busy,
+            _ => Status::InvalidChallenge,
+        }
+    }
+}
+
+fn decode_request(payload: &[u8]) -> Result<McaptchaChallengeDescriptor, SolveError> {
+    let (&version, payload) = payload.split_first().ok_or(SolveError::InvalidChallenge)?;
+    if version != VERSION || payload.len() < 6 {
+        return Err(SolveError::InvalidChallenge);
+    }
+    let difficulty_factor = u32::from_le_bytes(payload[..4].try_into().unwrap());
+    let salt_len = u16::from_le_bytes(payload[4..6].try_into().unwrap()) as usize;
+    let payload = &payload[6..];
+    if payload.len() < salt_len || difficulty_factor == 0 {
+        return Err(SolveError::InvalidChallenge);
+    }
+    let (salt, string) = payload.split_at(salt_len);
+    Ok(McaptchaChallengeDescriptor {
+        salt: String::from_utf8(salt.to_vec()).map_err(|_| SolveError::InvalidChallenge)?,
+        string: String::from_utf8(string.to_vec()).map_err(|_| SolveError::InvalidChallenge)?,
+        difficulty_factor,
+    })
+}
+
+async fn solve(state: &AppState, payload: &[u8]) -> Result<(u64, [u32; 8], u64), SolveError> {
+    let descriptor = decode_request(payload)?;
+
+    let estimated_workload = descriptor.estimated_workload();
+    if estimated_workload > state.effective_limit() {
+        return Err(SolveError::EstimatedWorkloadGreaterThanLimit {
+            limit: state.effective_limit(),
+            estimated: estimated_workload,
+        });
+    }
+
+    let _permit = state.queue.acquire(JobOptions::default()).await?;
+
+    let limit = state.limit;
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    state.pool.spawn(move || {
+        tx.send(descriptor.solve_with_limit(limit)).ok();
+    });
+
+    let (result, attempted_nonces) = rx.await.map_err(|_| SolveError::SolverFatal)?;
+    let (nonce, result) = result.ok_or(SolveError::SolverFailed {
+        limit,
+        attempted: attempted_nonces,
+    })?;
+    Ok((nonce, result, attempted_nonces))
+}
+
+async fn handle_connection(state: AppState, mut stream: UnixStream) -> std::io::Result<()> {
+    let mut payload = Vec::new();
+    let mut response = Vec::with_capacity(4 + 1 + 8 + 16 + 8);
+    loop {
+        let len = match stream.read_u32_le().await {
+            Ok(len) => len,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if len > MAX_FRAME_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "frame too large",
+            ));
+        }
+        payload.resize(len as usize, 0);
+        stream.read_exact(&mut payload).await?;
+
+        response.clear();
+        response.extend_from_slice(&[0; 4]);
+        match solve(&state, &payload).await {
+            Ok((nonce, result, attempted_nonces)) => {
+                response.push(Status::Ok as u8);
+                response.extend_from_slice(&nonce.to_le_bytes());
+                response.extend_from_slice(&crate::extract128_be(result).to_be_bytes());
+                response.extend_from_slice(&attempted_nonces.to_le_bytes());
+            }
+            Err(e) => {
+                tracing::warn!("uds request failed: {}", e);
+                response.push(Status::from(&e) as u8);
+                response.extend_from_slice(e.to_string().as_bytes());
+            }
+        }
+        let response_len = (response.len() - 4) as u32;
+        response[..4].copy_from_slice(&response_len.to_le_bytes());
+        stream.write_all(&response).await?;
+    }
+}
+
+impl AppState {
+    /// serves the binary solver protocol on a Unix domain socket
+    ///
+    /// A stale socket file at `path` is removed before binding, any other file there is left alone
+    /// and fails with [`std::io::ErrorKind::AddrInUse`].
+    pub async fn serve_uds(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = UnixListener::bind(path)?;
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let state = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(state, stream).await {
+                    tracing::warn!("uds connection closed: {}", e);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(all(target_arch = "x86_64", any(doc, target_feature = "avx512f")))]
+pub mod avx512;
+
+use crate::decimal_counter::be_words;
+
+// Initial hash values for SHA-1
+pub(crate) const IV: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+// Round constants, one per 20 rounds
+const K: [u32; 4] = [0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xca62c1d6];
+
+/// A reference software implementation of SHA-1 compression function
+#[inline(always)]
+pub(crate) const fn digest_block(state: &mut [u32; 5], block: &[u32; 16]) {
+    let mut w = *block;
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+
+    let mut i = 0;
+    while i < 80 {
+        if i >= 16 {
+            w[i % 16] =
+                (w[(i - 3) % 16] ^ w[(i - 8) % 16] ^ w[(i - 14) % 16] ^ w[i % 16]).rotate_left(1);
+        }
+        let f = match i / 20 {
+            0 => (b & c) | (!b & d),
+            2 => (b & c) | (b & d) | (c & d),
+            _ => b ^ c ^ d,
+        };
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(K[i / 20])
+            .wrapping_add(w[i % 16]);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+        i += 1;
+    }
+
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+    state[4] = state[4].wrapping_add(e);
+}
+
+/// hash a whole message with the reference implementation
+pub(crate) fn digest(message: &[u8]) -> [u32; 5] {
+    let mut state = IV;
+    let mut chunks = message.chunks_exact(64);
+    for chunk in &mut chunks {
+        digest_block(&mut state, &be_words(chunk));
+    }
+
+    let remainder = chunks.remainder();
+    let mut tail = [0u8; 128];
+    tail[..remainder.len()].copy_from_slice(remainder);
+    tail[remainder.len()] = 0x80;
+    let tail_len = if remainder.len() + 9 > 64 { 128 } else { 64 };
+    tail[tail_len - 8..tail_len].copy_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+    for chunk in tail[..tail_len].chunks_exact(64) {
+        digest_block(&mut state, &be_words(chunk));
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha1_digest() {
+        // Test vectors from NIST FIPS 180-1
+        assert_eq!(
+            digest(b"abc"),
+            [0xa9993e36, 0x4706816a, 0xba3e2571, 0x7850c26c, 0x9cd0d89d]
+        );
+        assert_eq!(
+            digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            [0x84983e44, 0x1c3bd26e, 0xbaae4aa1, 0xf95129e5, 0xe54670f1]
+        );
+        assert_eq!(
+            digest(b""),
+            [0xda39a3ee, 0x5e6b4b0d, 0x3255bfef, 0x95601890, 0xafd80709]
+        );
+    }
+}
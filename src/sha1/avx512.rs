@@ -0,0 +1,92 @@
+//! Multi-way SHA-1 implementation for AVX-512.
+use core::arch::x86_64::*;
+
+use super::*;
+
+// disable inline because without hardware AVX-512 this will explode in complexity and cause comptime to skyrocket
+#[cfg_attr(
+    all(not(debug_assertions), not(test), target_feature = "avx512f"),
+    inline(always)
+)]
+/// Do a 16-way SHA-1 compression function without adding back the saved state
+///
+/// Ch, Parity and Maj are each a single ternary logic instruction.
+pub(crate) fn multiway_arx(state: &mut [__m512i; 5], block: &mut [__m512i; 16]) {
+    unsafe {
+        let [a, b, c, d, e] = &mut *state;
+
+        repeat80!(i, {
+            let w = if i < 16 {
+                block[i]
+            } else {
+                let x = _mm512_ternarylogic_epi32(
+                    block[(i - 3) % 16],
+                    block[(i - 8) % 16],
+                    block[(i - 14) % 16],
+                    0x96,
+                );
+                block[i % 16] = _mm512_rol_epi32(_mm512_xor_si512(x, block[i % 16]), 1);
+                block[i % 16]
+            };
+
+            let f = match i / 20 {
+                0 => _mm512_ternarylogic_epi32(*b, *c, *d, 0xca),
+                2 => _mm512_ternarylogic_epi32(*b, *c, *d, 0xe8),
+                _ => _mm512_ternarylogic_epi32(*b, *c, *d, 0x96),
+            };
+            let mut t = _mm512_rol_epi32(*a, 5);
+            t = _mm512_add_epi32(t, f);
+            t = _mm512_add_epi32(t, *e);
+            t = _mm512_add_epi32(t, _mm512_set1_epi32(K[i / 20] as _));
+            t = _mm512_add_epi32(t, w);
+
+            *e = *d;
+            *d = *c;
+            *c = _mm512_rol_epi32(*b, 30);
+            *b = *a;
+            *a = t;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_digest_block_equivalence() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let mut states: [[u32; 5]; 16] =
+            core::array::from_fn(|_| core::array::from_fn(|_| rng.random()));
+        let blocks: [[u32; 16]; 16] =
+            core::array::from_fn(|_| core::array::from_fn(|_| rng.random()));
+        let lanes = |words: &dyn Fn(usize) -> u32| unsafe {
+            let words: [u32; 16] = core::array::from_fn(words);
+            _mm512_loadu_si512(words.as_ptr() as *const _)
+        };
+        let mut state_avx512: [__m512i; 5] =
+            core::array::from_fn(|i| lanes(&|lane| states[lane][i]));
+        let state_avx512_save = state_avx512;
+        let mut block_avx512: [__m512i; 16] =
+            core::array::from_fn(|i| lanes(&|lane| blocks[lane][i]));
+
+        for i in 0..16 {
+            digest_block(&mut states[i], &blocks[i]);
+        }
+        multiway_arx(&mut state_avx512, &mut block_avx512);
+
+        let mut output_state_simd_soa = [[0u32; 16]; 5];
+        for i in 0..5 {
+            unsafe {
+                let state = _mm512_add_epi32(state_avx512[i], state_avx512_save[i]);
+                _mm512_storeu_si512(output_state_simd_soa[i].as_mut_ptr() as *mut _, state);
+            }
+        }
+        let output_state_simd: [[u32; 5]; 16] =
+            core::array::from_fn(|i| core::array::from_fn(|j| output_state_simd_soa[j][i]));
+
+        assert_eq!(states, output_state_simd);
+    }
+}
@@ -1,9 +1,12 @@
-#[cfg(all(target_arch = "x86_64", any(doc, target_feature = "avx512f")))]
+#[cfg(all(
+    target_arch = "x86_64",
+    any(doc, target_feature = "avx512f", feature = "multiversion")
+))]
 pub mod avx512;
 
 #[cfg(all(
     any(target_arch = "x86_64", target_arch = "x86"),
-    any(doc, target_feature = "sha")
+    any(doc, target_feature = "sha", feature = "multiversion")
 ))]
 pub mod sha_ni;
 
@@ -66,6 +69,33 @@ pub(crate) fn digest_block(state: &mut [u32; 8], block: &[u32; 16]) {
     sha2::compress256(state, &[tmp]);
 }
 
+/// Compress whole 64-byte blocks into the state
+///
+/// Used to absorb long prefixes, single stream SHA-NI is used when available,
+/// otherwise the bytes are handed to the reference implementation without a round trip through words.
+///
+/// Panics if the input length is not a multiple of 64.
+#[inline]
+pub(crate) fn digest_blocks(state: &mut [u32; 8], blocks: &[u8]) {
+    assert!(
+        blocks.len().is_multiple_of(64),
+        "input must be whole blocks"
+    );
+
+    cfg_if::cfg_if! {
+        if #[cfg(all(any(target_arch = "x86_64", target_arch = "x86"), target_feature = "sha"))] {
+            sha_ni::digest_blocks(state, blocks);
+        } else {
+            for block in blocks.chunks_exact(64) {
+                sha2::compress256(
+                    state,
+                    core::slice::from_ref(sha2::digest::generic_array::GenericArray::from_slice(block)),
+                );
+            }
+        }
+    }
+}
+
 /// ingest a message prefix into the state
 #[inline(always)]
 pub(crate) fn ingest_message_prefix<const LEN: usize>(state: &mut [u32; 8], w: [u32; LEN]) {
@@ -1,4 +1,7 @@
 //! Multi-way sha256 implementation extracted from `sha2` crate for AVX-512.
+
+// with multiversion only the runtime dispatched solvers use this
+#![cfg_attr(not(target_feature = "avx512f"), allow(dead_code))]
 use core::arch::x86_64::*;
 
 use super::*;
@@ -7,10 +10,104 @@ use super::*;
 #[path = "loop_macros.rs"]
 mod loop_macros;
 
+// the boolean functions are written as single `vpternlogd` so the fusion does not depend on the optimizer,
+// the `no-ternary-logic` feature swaps in the textbook and/xor formulation for comparison
+
+/// `Ch(e, f, g)`
+#[inline(always)]
+fn ch(e: __m512i, f: __m512i, g: __m512i) -> __m512i {
+    unsafe {
+        if cfg!(feature = "no-ternary-logic") {
+            _mm512_xor_si512(_mm512_and_si512(e, f), _mm512_andnot_si512(e, g))
+        } else {
+            _mm512_ternarylogic_epi32(e, f, g, 0xca)
+        }
+    }
+}
+
+/// `Maj(a, b, c)`
+#[inline(always)]
+fn maj(a: __m512i, b: __m512i, c: __m512i) -> __m512i {
+    unsafe {
+        if cfg!(feature = "no-ternary-logic") {
+            _mm512_xor_si512(
+                _mm512_xor_si512(_mm512_and_si512(a, b), _mm512_and_si512(a, c)),
+                _mm512_and_si512(b, c),
+            )
+        } else {
+            _mm512_ternarylogic_epi32(a, b, c, 0xe8)
+        }
+    }
+}
+
+/// `x ^ y ^ z`
+#[inline(always)]
+fn xor3(x: __m512i, y: __m512i, z: __m512i) -> __m512i {
+    unsafe {
+        if cfg!(feature = "no-ternary-logic") {
+            _mm512_xor_si512(_mm512_xor_si512(x, y), z)
+        } else {
+            _mm512_ternarylogic_epi32(x, y, z, 0x96)
+        }
+    }
+}
+
+/// `Σ0(a)`
+#[inline(always)]
+fn big_sigma0(a: __m512i) -> __m512i {
+    unsafe {
+        xor3(
+            _mm512_ror_epi32(a, 2),
+            _mm512_ror_epi32(a, 13),
+            _mm512_ror_epi32(a, 22),
+        )
+    }
+}
+
+/// `Σ1(e)`
+#[inline(always)]
+fn big_sigma1(e: __m512i) -> __m512i {
+    unsafe {
+        xor3(
+            _mm512_ror_epi32(e, 6),
+            _mm512_ror_epi32(e, 11),
+            _mm512_ror_epi32(e, 25),
+        )
+    }
+}
+
+/// `σ0(w)`
+#[inline(always)]
+fn small_sigma0(w: __m512i) -> __m512i {
+    unsafe {
+        xor3(
+            _mm512_ror_epi32(w, 7),
+            _mm512_ror_epi32(w, 18),
+            _mm512_srli_epi32(w, 3),
+        )
+    }
+}
+
+/// `σ1(w)`
+#[inline(always)]
+fn small_sigma1(w: __m512i) -> __m512i {
+    unsafe {
+        xor3(
+            _mm512_ror_epi32(w, 17),
+            _mm512_ror_epi32(w, 19),
+            _mm512_srli_epi32(w, 10),
+        )
+    }
+}
+
 // disable inline because without hardware AVX-512 this will explode in complexity and cause comptime to skyrocket
 // disable inline for debug_assertions because no one wants to wait for 5 minutes to run a unit test
 #[cfg_attr(
-    all(not(debug_assertions), not(test), target_feature = "avx512f"),
+    all(
+        not(debug_assertions),
+        not(test),
+        any(target_feature = "avx512f", feature = "multiversion")
+    ),
     inline(always)
 )]
 /// Do a 16-way SHA-256 compression function without adding back the saved state, without feedback
@@ -29,42 +126,27 @@ pub(crate) fn multiway_arx<const BEGIN_ROUND: usize>(
                     block[i]
                 } else {
                     let w15 = block[(i - 15) % 16];
-                    let s0 = _mm512_xor_si512(
-                        _mm512_xor_si512(_mm512_ror_epi32(w15, 7), _mm512_ror_epi32(w15, 18)),
-                        _mm512_srli_epi32(w15, 3),
-                    );
+                    let s0 = small_sigma0(w15);
                     let w2 = block[(i - 2) % 16];
-                    let s1 = _mm512_xor_si512(
-                        _mm512_xor_si512(_mm512_ror_epi32(w2, 17), _mm512_ror_epi32(w2, 19)),
-                        _mm512_srli_epi32(w2, 10),
-                    );
+                    let s1 = small_sigma1(w2);
                     block[i % 16] = _mm512_add_epi32(block[i % 16], s0);
                     block[i % 16] = _mm512_add_epi32(block[i % 16], block[(i - 7) % 16]);
                     block[i % 16] = _mm512_add_epi32(block[i % 16], s1);
                     block[i % 16]
                 };
 
-                let s1 = _mm512_xor_si512(
-                    _mm512_xor_si512(_mm512_ror_epi32(*e, 6), _mm512_ror_epi32(*e, 11)),
-                    _mm512_ror_epi32(*e, 25),
-                );
-                let ch = _mm512_xor_si512(_mm512_and_si512(*e, *f), _mm512_andnot_si512(*e, *g));
+                let s1 = big_sigma1(*e);
+                let ch_efg = ch(*e, *f, *g);
                 let mut t1 = s1;
-                t1 = _mm512_add_epi32(t1, ch);
+                t1 = _mm512_add_epi32(t1, ch_efg);
                 t1 = _mm512_add_epi32(t1, _mm512_set1_epi32(K32[i] as _));
                 t1 = _mm512_add_epi32(t1, w);
                 t1 = _mm512_add_epi32(t1, *h);
 
-                let s0 = _mm512_xor_si512(
-                    _mm512_xor_si512(_mm512_ror_epi32(*a, 2), _mm512_ror_epi32(*a, 13)),
-                    _mm512_ror_epi32(*a, 22),
-                );
-                let maj = _mm512_xor_si512(
-                    _mm512_xor_si512(_mm512_and_si512(*a, *b), _mm512_and_si512(*a, *c)),
-                    _mm512_and_si512(*b, *c),
-                );
+                let s0 = big_sigma0(*a);
+                let maj_abc = maj(*a, *b, *c);
                 let mut t2 = s0;
-                t2 = _mm512_add_epi32(t2, maj);
+                t2 = _mm512_add_epi32(t2, maj_abc);
 
                 *h = *g;
                 *g = *f;
@@ -79,11 +161,157 @@ pub(crate) fn multiway_arx<const BEGIN_ROUND: usize>(
     }
 }
 
+/// Prepare the parts of the message schedule words `W[16..32]` that only depend on the block words before `VARYING_WORD_IDX`
+///
+/// The result is passed to [`multiway_arx_cached`] for every block that only differs in words `VARYING_WORD_IDX..16`.
+#[inline(always)]
+pub(crate) fn prepare_schedule_invariant<const VARYING_WORD_IDX: usize>(
+    block: &[__m512i; 16],
+) -> [__m512i; 16] {
+    const { assert!(VARYING_WORD_IDX <= 14) };
+    unsafe {
+        core::array::from_fn(|j| {
+            let i = j + 16;
+            let mut w = _mm512_setzero_si512();
+            if i - 7 < VARYING_WORD_IDX {
+                w = _mm512_add_epi32(w, block[i - 7]);
+            }
+            if i - 15 < VARYING_WORD_IDX {
+                let w15 = block[i - 15];
+                let s0 = small_sigma0(w15);
+                w = _mm512_add_epi32(w, s0);
+            }
+            if i - 16 < VARYING_WORD_IDX {
+                w = _mm512_add_epi32(w, block[i - 16]);
+            }
+            w
+        })
+    }
+}
+
+#[cfg_attr(
+    all(
+        not(debug_assertions),
+        not(test),
+        any(target_feature = "avx512f", feature = "multiversion")
+    ),
+    inline(always)
+)]
+/// Do a 16-way SHA-256 compression function without feedback, where only words `VARYING_WORD_IDX..16` differ from the block given to [`prepare_schedule_invariant`]
+///
+/// The terms of `W[16..32]` coming from the other words are taken from `schedule_invariant` instead of being expanded again.
+pub(crate) fn multiway_arx_cached<const BEGIN_ROUND: usize, const VARYING_WORD_IDX: usize>(
+    state: &mut [__m512i; 8],
+    block: &mut [__m512i; 16],
+    schedule_invariant: &[__m512i; 16],
+) {
+    const { assert!(VARYING_WORD_IDX <= 14) };
+    unsafe {
+        let [a, b, c, d, e, f, g, h] = &mut *state;
+
+        repeat64!(i, {
+            if i >= BEGIN_ROUND {
+                let w = if i < 16 {
+                    block[i]
+                } else {
+                    let w2 = block[(i - 2) % 16];
+                    let s1 = small_sigma1(w2);
+                    if i < 32 {
+                        // every word from W[16] on depends on the varying words, so only the terms of the block words before them are cached
+                        let mut w = _mm512_add_epi32(schedule_invariant[i - 16], s1);
+                        if i - 7 >= VARYING_WORD_IDX {
+                            w = _mm512_add_epi32(w, block[(i - 7) % 16]);
+                        }
+                        if i - 15 >= VARYING_WORD_IDX {
+                            w = _mm512_add_epi32(w, small_sigma0(block[(i - 15) % 16]));
+                        }
+                        if i - 16 >= VARYING_WORD_IDX {
+                            w = _mm512_add_epi32(w, block[i % 16]);
+                        }
+                        block[i % 16] = w;
+                    } else {
+                        block[i % 16] =
+                            _mm512_add_epi32(block[i % 16], small_sigma0(block[(i - 15) % 16]));
+                        block[i % 16] = _mm512_add_epi32(block[i % 16], block[(i - 7) % 16]);
+                        block[i % 16] = _mm512_add_epi32(block[i % 16], s1);
+                    }
+                    block[i % 16]
+                };
+
+                let s1 = big_sigma1(*e);
+                let ch_efg = ch(*e, *f, *g);
+                let mut t1 = s1;
+                t1 = _mm512_add_epi32(t1, ch_efg);
+                t1 = _mm512_add_epi32(t1, _mm512_set1_epi32(K32[i] as _));
+                t1 = _mm512_add_epi32(t1, w);
+                t1 = _mm512_add_epi32(t1, *h);
+
+                let s0 = big_sigma0(*a);
+                let maj_abc = maj(*a, *b, *c);
+                let mut t2 = s0;
+                t2 = _mm512_add_epi32(t2, maj_abc);
+
+                *h = *g;
+                *g = *f;
+                *f = *e;
+                *e = _mm512_add_epi32(*d, t1);
+                *d = *c;
+                *c = *b;
+                *b = *a;
+                *a = _mm512_add_epi32(t1, t2);
+            }
+        });
+    }
+}
+
+/// Do rounds `BEGIN_ROUND..END_ROUND` of a 16-way SHA-256 compression function, within the first 16 rounds
+///
+/// This is useful for hoisting rounds whose message words are shared by a whole batch of candidates out of the inner loop,
+/// the rest of the rounds are then done by [`multiway_arx`] starting at `END_ROUND`
+#[inline(always)]
+pub(crate) fn multiway_arx_partial<const BEGIN_ROUND: usize, const END_ROUND: usize>(
+    state: &mut [__m512i; 8],
+    block: &[__m512i; 16],
+) {
+    const { assert!(BEGIN_ROUND <= END_ROUND && END_ROUND <= 16) };
+    unsafe {
+        let [a, b, c, d, e, f, g, h] = &mut *state;
+
+        for i in BEGIN_ROUND..END_ROUND {
+            let s1 = big_sigma1(*e);
+            let ch_efg = ch(*e, *f, *g);
+            let mut t1 = s1;
+            t1 = _mm512_add_epi32(t1, ch_efg);
+            t1 = _mm512_add_epi32(t1, _mm512_set1_epi32(K32[i] as _));
+            t1 = _mm512_add_epi32(t1, block[i]);
+            t1 = _mm512_add_epi32(t1, *h);
+
+            let s0 = big_sigma0(*a);
+            let maj_abc = maj(*a, *b, *c);
+            let mut t2 = s0;
+            t2 = _mm512_add_epi32(t2, maj_abc);
+
+            *h = *g;
+            *g = *f;
+            *f = *e;
+            *e = _mm512_add_epi32(*d, t1);
+            *d = *c;
+            *c = *b;
+            *b = *a;
+            *a = _mm512_add_epi32(t1, t2);
+        }
+    }
+}
+
 /// Do a 16-way SHA-256 compression function using broadcasted message schedule, without feedback
 ///
 /// You can skip loading the first couple words by passing a non-zero value for `LeadingZeroes`
 #[cfg_attr(
-    all(not(debug_assertions), not(test), target_feature = "avx512f"),
+    all(
+        not(debug_assertions),
+        not(test),
+        any(target_feature = "avx512f", feature = "multiversion")
+    ),
     inline(always)
 )]
 pub(crate) fn bcst_multiway_arx<const LEAD_ZEROES: usize>(
@@ -100,26 +328,17 @@ pub(crate) fn bcst_multiway_arx<const LEAD_ZEROES: usize>(
                 _mm512_set1_epi32(w_k[i] as _)
             };
 
-            let s1 = _mm512_xor_si512(
-                _mm512_xor_si512(_mm512_ror_epi32(*e, 6), _mm512_ror_epi32(*e, 11)),
-                _mm512_ror_epi32(*e, 25),
-            );
-            let ch = _mm512_xor_si512(_mm512_and_si512(*e, *f), _mm512_andnot_si512(*e, *g));
+            let s1 = big_sigma1(*e);
+            let ch_efg = ch(*e, *f, *g);
             let mut t1 = s1;
-            t1 = _mm512_add_epi32(t1, ch);
+            t1 = _mm512_add_epi32(t1, ch_efg);
             t1 = _mm512_add_epi32(t1, w);
             t1 = _mm512_add_epi32(t1, *h);
 
-            let s0 = _mm512_xor_si512(
-                _mm512_xor_si512(_mm512_ror_epi32(*a, 2), _mm512_ror_epi32(*a, 13)),
-                _mm512_ror_epi32(*a, 22),
-            );
-            let maj = _mm512_xor_si512(
-                _mm512_xor_si512(_mm512_and_si512(*a, *b), _mm512_and_si512(*a, *c)),
-                _mm512_and_si512(*b, *c),
-            );
+            let s0 = big_sigma0(*a);
+            let maj_abc = maj(*a, *b, *c);
             let mut t2 = s0;
-            t2 = _mm512_add_epi32(t2, maj);
+            t2 = _mm512_add_epi32(t2, maj_abc);
 
             *h = *g;
             *g = *f;
@@ -295,6 +514,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_multiway_arx_cached() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(3);
+        let state: [__m512i; 8] =
+            core::array::from_fn(|_| unsafe { _mm512_set1_epi32(rng.random::<u32>() as _) });
+        let mut block: [__m512i; 16] =
+            core::array::from_fn(|_| unsafe { _mm512_set1_epi32(rng.random::<u32>() as _) });
+        let schedule_invariant = prepare_schedule_invariant::<13>(&block);
+
+        for _ in 0..4 {
+            for word in &mut block[13..] {
+                *word = unsafe { _mm512_set1_epi32(rng.random::<u32>() as _) };
+            }
+
+            let mut expected_state = state;
+            multiway_arx::<0>(&mut expected_state, &mut block.clone());
+            let mut cached_state = state;
+            multiway_arx_cached::<0, 13>(
+                &mut cached_state,
+                &mut block.clone(),
+                &schedule_invariant,
+            );
+
+            for i in 0..8 {
+                let mut expected = [0u32; 16];
+                let mut cached = [0u32; 16];
+                unsafe {
+                    _mm512_storeu_si512(expected.as_mut_ptr() as *mut _, expected_state[i]);
+                    _mm512_storeu_si512(cached.as_mut_ptr() as *mut _, cached_state[i]);
+                }
+                assert_eq!(cached, expected);
+            }
+        }
+    }
+
     #[test]
     fn test_sha256_avx512_bcst_without_feedback() {
         let mut block = [0; 64];
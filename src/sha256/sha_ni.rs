@@ -1,4 +1,7 @@
-#![cfg_attr(target_feature = "avx512f", allow(dead_code, unused))]
+#![cfg_attr(
+    any(target_feature = "avx512f", not(target_feature = "sha")),
+    allow(dead_code, unused)
+)]
 
 // these are mainly adapted from the sha2 crate as well,
 // the core logic is verbatim, but shuffling and batch message loading overhead is removed
@@ -209,6 +212,40 @@ pub(crate) fn multiway_arx_abef_cdgh<
         });
     }
 }
+
+/// compress whole blocks into the state in a single stream
+#[inline]
+pub(crate) fn digest_blocks(state: &mut [u32; 8], blocks: &[u8]) {
+    let mut prepared_state = prepare_state(&Align16(*state));
+    let mut block = Align16([0u32; 16]);
+
+    for bytes in blocks.chunks_exact(64) {
+        for (word, bytes) in block.iter_mut().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        let feedback = prepared_state;
+        multiway_arx_abef_cdgh::<0, 1, ()>([&mut prepared_state], &block, ());
+        unsafe {
+            prepared_state[0] = _mm_add_epi32(prepared_state[0], feedback[0]);
+            prepared_state[1] = _mm_add_epi32(prepared_state[1], feedback[1]);
+        }
+    }
+
+    // undo the ABEF/CDGH layout
+    unsafe {
+        let [abef, cdgh] = prepared_state;
+        let feba = _mm_shuffle_epi32(abef, 0x1B);
+        let dchg = _mm_shuffle_epi32(cdgh, 0xB1);
+        let dcba = _mm_blend_epi16(feba, dchg, 0xF0);
+        let hgef = _mm_alignr_epi8(dchg, feba, 8);
+
+        let state_ptr = state.as_mut_ptr().cast::<__m128i>();
+        _mm_storeu_si128(state_ptr.add(0), dcba);
+        _mm_storeu_si128(state_ptr.add(1), hgef);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,4 +289,28 @@ mod tests {
         assert_eq!(ab_a, reference_state[0], "a={} b={}", a, b);
         assert_eq!(ab_b, reference_state[1], "a={} b={}", a, b);
     }
+
+    #[test]
+    fn test_digest_blocks() {
+        let blocks: [u8; 64 * 3] = core::array::from_fn(|i| (i * 7 + 3) as u8);
+
+        let mut reference_state = crate::sha256::IV;
+        for block in blocks.chunks_exact(64) {
+            crate::sha256::digest_block(
+                &mut reference_state,
+                &core::array::from_fn(|i| {
+                    u32::from_be_bytes([
+                        block[i * 4],
+                        block[i * 4 + 1],
+                        block[i * 4 + 2],
+                        block[i * 4 + 3],
+                    ])
+                }),
+            );
+        }
+
+        let mut state = crate::sha256::IV;
+        digest_blocks(&mut state, &blocks);
+        assert_eq!(state, reference_state);
+    }
 }
@@ -0,0 +1,254 @@
+//! SHA-256d (double SHA-256) proof-of-work, as used by Bitcoin-style toy PoW gates.
+//!
+//! Construct: nonce := padding || LANE_ID || 8 DECIMAL DIGITS, where LANE_ID is a two digit
+//! decimal in `10..90` and the padding of `'1'`s moves the nonce into a block of its own when the
+//! prefix leaves too little room, so every attempt is a single compression of the final block
+//! followed by a single compression of the digest with a padding block fixed ahead of time.
+use alloc::{string::String, vec::Vec};
+use sha2::Digest;
+
+use crate::{
+    decimal_counter::{self, LaneKernel},
+    sha256,
+};
+
+/// the message words 8..16 of the second hash, the padding of a 32 byte message
+const DIGEST_PADDING: [u32; 8] = [0x80000000, 0, 0, 0, 0, 0, 0, 256];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A SHA-256d PoW whose proof is a decimal nonce appended to a fixed prefix.
+pub struct Sha256dChallenge {
+    /// the prefix the nonce is appended to
+    pub prefix: Vec<u8>,
+    /// the required number of leading zero bits
+    pub bits: u32,
+    /// count the zero bits in Bitcoin's order, the digest read as a little endian integer
+    pub reversed: bool,
+}
+
+impl Sha256dChallenge {
+    /// estimate the workload of solving the challenge
+    pub fn estimated_workload(&self) -> u64 {
+        1u64.checked_shl(self.bits).unwrap_or(u64::MAX)
+    }
+
+    /// solve the challenge
+    pub fn solve(&self) -> (Option<String>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// solve the challenge with a limit
+    ///
+    /// Returns None if the limit or the nonce space is exhausted, or more than 64 bits are required.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<String>, u64) {
+        if self.bits > 64 {
+            return (None, 0);
+        }
+        // the words holding the top 64 bits of the digest in the order the bits are counted
+        let masks = [
+            match self.bits {
+                0 => 0,
+                bits @ 1..32 => !0 << (32 - bits),
+                _ => !0,
+            },
+            match self.bits {
+                0..=32 => 0u32,
+                bits => !0 << (64 - bits),
+            },
+        ];
+        let kernel = if self.reversed {
+            LeadingZeroes {
+                words: [7, 6],
+                masks: masks.map(u32::swap_bytes),
+            }
+        } else {
+            LeadingZeroes {
+                words: [0, 1],
+                masks,
+            }
+        };
+        let (nonce, attempted_nonces) = decimal_counter::search(&kernel, &self.prefix, limit);
+        (
+            nonce.map(|nonce| String::from_utf8(nonce).expect("nonce is ASCII")),
+            attempted_nonces,
+        )
+    }
+
+    /// the number of leading zero bits of the double hash of a nonce
+    pub fn leading_zero_bits(&self, nonce: &str) -> u32 {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(&self.prefix);
+        hasher.update(nonce.as_bytes());
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&sha2::Sha256::digest(hasher.finalize()));
+        if self.reversed {
+            digest.reverse();
+        }
+        let mut bits = 0;
+        for byte in digest {
+            bits += byte.leading_zeros();
+            if byte != 0 {
+                break;
+            }
+        }
+        bits
+    }
+
+    /// check a nonce against the challenge
+    pub fn is_valid(&self, nonce: &str) -> bool {
+        self.leading_zero_bits(nonce) >= self.bits
+    }
+}
+
+/// the words of the double hash must be zero under the masks
+struct LeadingZeroes {
+    words: [usize; 2],
+    masks: [u32; 2],
+}
+
+impl LaneKernel for LeadingZeroes {
+    type Word = u32;
+    type State = [u32; 8];
+
+    const IV: [u32; 8] = sha256::IV;
+    const LENGTH_BYTES: usize = 8;
+    const LANE_ID_BYTES: usize = 2;
+    const LANES: usize = 16;
+    const LANE_ID_SETS: u32 = 5;
+    const PADDING: u8 = b'1';
+
+    fn digest_block(state: &mut [u32; 8], block: &[u32; 16]) {
+        sha256::digest_block(state, block)
+    }
+
+    fn write_lane_id(set: u32, lane: usize, out: &mut [u8]) {
+        decimal_counter::write_decimal_lane_id(Self::LANES, set, lane, out)
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+    fn search_lanes(
+        &self,
+        midstate: &[u32; 8],
+        block: &[u8],
+        lane_index: usize,
+        set: u32,
+    ) -> Option<usize> {
+        use core::arch::x86_64::*;
+
+        let Self { words, masks } = *self;
+        // the lane ID spans at most two words, the others are shared by every lane
+        let first_word = lane_index / 4;
+        let last_word = (lane_index + 1) / 4;
+        let lane_blocks: [[u32; 16]; Self::LANES] = core::array::from_fn(|lane| {
+            decimal_counter::lane_block::<Self>(block, lane_index, set, lane)
+        });
+        let shared = lane_blocks[0];
+
+        unsafe {
+            let mut block: [__m512i; 16] = core::array::from_fn(|i| {
+                if i == first_word || i == last_word {
+                    let lanes: [u32; Self::LANES] =
+                        core::array::from_fn(|lane| lane_blocks[lane][i]);
+                    _mm512_loadu_si512(lanes.as_ptr() as *const _)
+                } else {
+                    _mm512_set1_epi32(shared[i] as _)
+                }
+            });
+            let mut state: [__m512i; 8] =
+                core::array::from_fn(|i| _mm512_set1_epi32(midstate[i] as _));
+            sha256::avx512::multiway_arx::<0>(&mut state, &mut block);
+
+            // the first digest becomes the message of the second hash, the padding is fixed
+            let mut block: [__m512i; 16] = core::array::from_fn(|i| {
+                if i < 8 {
+                    _mm512_add_epi32(state[i], _mm512_set1_epi32(midstate[i] as _))
+                } else {
+                    _mm512_set1_epi32(DIGEST_PADDING[i - 8] as _)
+                }
+            });
+            let mut state: [__m512i; 8] =
+                core::array::from_fn(|i| _mm512_set1_epi32(sha256::IV[i] as _));
+            sha256::avx512::multiway_arx::<0>(&mut state, &mut block);
+
+            let high = _mm512_add_epi32(
+                state[words[0]],
+                _mm512_set1_epi32(sha256::IV[words[0]] as _),
+            );
+            let low = _mm512_add_epi32(
+                state[words[1]],
+                _mm512_set1_epi32(sha256::IV[words[1]] as _),
+            );
+            let met = _mm512_testn_epi32_mask(high, _mm512_set1_epi32(masks[0] as _))
+                & _mm512_testn_epi32_mask(low, _mm512_set1_epi32(masks[1] as _));
+            (met != 0).then(|| met.trailing_zeros() as usize)
+        }
+    }
+
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "avx512f")))]
+    fn search_lanes(
+        &self,
+        midstate: &[u32; 8],
+        block: &[u8],
+        lane_index: usize,
+        set: u32,
+    ) -> Option<usize> {
+        let Self { words, masks } = *self;
+        (0..Self::LANES).find(|&lane| {
+            let mut state = *midstate;
+            sha256::digest_block(
+                &mut state,
+                &decimal_counter::lane_block::<Self>(block, lane_index, set, lane),
+            );
+
+            let mut digest_block = [0; 16];
+            digest_block[..8].copy_from_slice(&state);
+            digest_block[8..].copy_from_slice(&DIGEST_PADDING);
+            let mut state = sha256::IV;
+            sha256::digest_block(&mut state, &digest_block);
+            state[words[0]] & masks[0] == 0 && state[words[1]] & masks[1] == 0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_sha256d() {
+        for reversed in [false, true] {
+            let challenge = |len| Sha256dChallenge {
+                prefix: b"x".repeat(len),
+                bits: 16,
+                reversed,
+            };
+            decimal_counter::tests::test_leading_zero_solver(
+                64,
+                |len, bits| {
+                    let (nonce, attempted) = Sha256dChallenge {
+                        bits,
+                        ..challenge(len)
+                    }
+                    .solve();
+                    if let Some(nonce) = &nonce {
+                        assert!(nonce.bytes().all(|c| c.is_ascii_digit()));
+                        assert!(!nonce.starts_with('0'));
+                    }
+                    (nonce, attempted)
+                },
+                |len, nonce| {
+                    let mut message = b"x".repeat(len);
+                    message.extend_from_slice(nonce.as_bytes());
+                    let digest = sha2::Sha256::digest(sha2::Sha256::digest(&message));
+                    let zero_bytes = if reversed {
+                        &digest[30..]
+                    } else {
+                        &digest[..2]
+                    };
+                    assert_eq!(zero_bytes, [0, 0]);
+                    challenge(len).leading_zero_bits(nonce)
+                },
+            );
+        }
+    }
+}
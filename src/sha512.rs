@@ -0,0 +1,321 @@
+//! SHA-512 leading zero proof-of-work.
+//!
+//! Construct: nonce := padding || LANE_ID || 8 DECIMAL DIGITS, where LANE_ID is a two digit
+//! decimal in `10..90` and the padding of `'1'`s moves the nonce into a block of its own when the
+//! prefix leaves too little room, so every attempt is a single compression of the final block.
+use alloc::{string::String, vec::Vec};
+
+use crate::decimal_counter::{self, LaneKernel, be_words};
+
+#[cfg(all(target_arch = "x86_64", any(doc, target_feature = "avx512f")))]
+pub(crate) mod avx512;
+
+// Initial hash values for SHA-512
+pub(crate) const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+#[rustfmt::skip]
+const K64: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// A reference software implementation of SHA-512 compression function
+#[inline(always)]
+pub(crate) const fn digest_block(state: &mut [u64; 8], block: &[u64; 16]) {
+    let mut w = *block;
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    let mut i = 0;
+    while i < 80 {
+        if i >= 16 {
+            let w15 = w[(i - 15) % 16];
+            let s0 = w15.rotate_right(1) ^ w15.rotate_right(8) ^ (w15 >> 7);
+            let w2 = w[(i - 2) % 16];
+            let s1 = w2.rotate_right(19) ^ w2.rotate_right(61) ^ (w2 >> 6);
+            w[i % 16] = w[i % 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[(i - 7) % 16])
+                .wrapping_add(s1);
+        }
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K64[i])
+            .wrapping_add(w[i % 16]);
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+        i += 1;
+    }
+
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+    state[4] = state[4].wrapping_add(e);
+    state[5] = state[5].wrapping_add(f);
+    state[6] = state[6].wrapping_add(g);
+    state[7] = state[7].wrapping_add(h);
+}
+
+/// hash a whole message with the reference implementation
+pub(crate) fn digest(message: &[u8]) -> [u64; 8] {
+    let mut state = IV;
+    let mut chunks = message.chunks_exact(128);
+    for chunk in &mut chunks {
+        digest_block(&mut state, &be_words(chunk));
+    }
+
+    let remainder = chunks.remainder();
+    let mut tail = [0u8; 256];
+    tail[..remainder.len()].copy_from_slice(remainder);
+    tail[remainder.len()] = 0x80;
+    let tail_len = if remainder.len() + 17 > 128 { 256 } else { 128 };
+    tail[tail_len - 16..tail_len].copy_from_slice(&(message.len() as u128 * 8).to_be_bytes());
+    for chunk in tail[..tail_len].chunks_exact(128) {
+        digest_block(&mut state, &be_words(chunk));
+    }
+    state
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A SHA-512 PoW whose proof is a decimal nonce appended to a fixed prefix.
+pub struct Sha512Challenge {
+    /// the prefix the nonce is appended to
+    pub prefix: Vec<u8>,
+    /// the required number of leading zero bits
+    pub bits: u32,
+}
+
+impl Sha512Challenge {
+    /// estimate the workload of solving the challenge
+    pub fn estimated_workload(&self) -> u64 {
+        1u64.checked_shl(self.bits).unwrap_or(u64::MAX)
+    }
+
+    /// solve the challenge
+    pub fn solve(&self) -> (Option<String>, u64) {
+        self.solve_with_limit(u64::MAX)
+    }
+
+    /// solve the challenge with a limit
+    ///
+    /// Returns None if the limit or the nonce space is exhausted, or more than 64 bits are required.
+    pub fn solve_with_limit(&self, limit: u64) -> (Option<String>, u64) {
+        if self.bits > 64 {
+            return (None, 0);
+        }
+        let kernel = LeadingZeroes {
+            mask: match self.bits {
+                0 => 0,
+                bits => !0 << (64 - bits),
+            },
+        };
+        let (nonce, attempted_nonces) = decimal_counter::search(&kernel, &self.prefix, limit);
+        (
+            nonce.map(|nonce| String::from_utf8(nonce).expect("nonce is ASCII")),
+            attempted_nonces,
+        )
+    }
+
+    /// the number of leading zero bits of the hash of a nonce
+    pub fn leading_zero_bits(&self, nonce: &str) -> u32 {
+        let mut message = self.prefix.clone();
+        message.extend_from_slice(nonce.as_bytes());
+        let mut bits = 0;
+        for word in digest(&message) {
+            bits += word.leading_zeros();
+            if word != 0 {
+                break;
+            }
+        }
+        bits
+    }
+
+    /// check a nonce against the challenge
+    pub fn is_valid(&self, nonce: &str) -> bool {
+        self.leading_zero_bits(nonce) >= self.bits
+    }
+}
+
+/// the top 64 bits of the hash must be zero under the mask
+struct LeadingZeroes {
+    mask: u64,
+}
+
+impl LaneKernel for LeadingZeroes {
+    type Word = u64;
+    type State = [u64; 8];
+
+    const IV: [u64; 8] = IV;
+    const LENGTH_BYTES: usize = 16;
+    const LANE_ID_BYTES: usize = 2;
+    const LANES: usize = 8;
+    const LANE_ID_SETS: u32 = 10;
+    const PADDING: u8 = b'1';
+
+    fn digest_block(state: &mut [u64; 8], block: &[u64; 16]) {
+        digest_block(state, block)
+    }
+
+    fn write_lane_id(set: u32, lane: usize, out: &mut [u8]) {
+        decimal_counter::write_decimal_lane_id(Self::LANES, set, lane, out)
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+    fn search_lanes(
+        &self,
+        midstate: &[u64; 8],
+        block: &[u8],
+        lane_index: usize,
+        set: u32,
+    ) -> Option<usize> {
+        use core::arch::x86_64::*;
+
+        // the lane ID spans at most two words, the others are shared by every lane
+        let first_word = lane_index / 8;
+        let last_word = (lane_index + 1) / 8;
+        let lane_blocks: [[u64; 16]; Self::LANES] = core::array::from_fn(|lane| {
+            decimal_counter::lane_block::<Self>(block, lane_index, set, lane)
+        });
+        let shared = lane_blocks[0];
+
+        unsafe {
+            let mut block: [__m512i; 16] = core::array::from_fn(|i| {
+                if i == first_word || i == last_word {
+                    let lanes: [u64; Self::LANES] =
+                        core::array::from_fn(|lane| lane_blocks[lane][i]);
+                    _mm512_loadu_si512(lanes.as_ptr() as *const _)
+                } else {
+                    _mm512_set1_epi64(shared[i] as _)
+                }
+            });
+            let mut state: [__m512i; 8] =
+                core::array::from_fn(|i| _mm512_set1_epi64(midstate[i] as _));
+            avx512::multiway_arx(&mut state, &mut block);
+
+            let a = _mm512_add_epi64(state[0], _mm512_set1_epi64(midstate[0] as _));
+            let met = _mm512_testn_epi64_mask(a, _mm512_set1_epi64(self.mask as _));
+            (met != 0).then(|| met.trailing_zeros() as usize)
+        }
+    }
+
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "avx512f")))]
+    fn search_lanes(
+        &self,
+        midstate: &[u64; 8],
+        block: &[u8],
+        lane_index: usize,
+        set: u32,
+    ) -> Option<usize> {
+        (0..Self::LANES).find(|&lane| {
+            let mut state = *midstate;
+            digest_block(
+                &mut state,
+                &decimal_counter::lane_block::<Self>(block, lane_index, set, lane),
+            );
+            state[0] & self.mask == 0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha512_digest() {
+        // Test vectors from NIST FIPS 180-2
+        assert_eq!(
+            digest(b"abc"),
+            [
+                0xddaf35a193617aba,
+                0xcc417349ae204131,
+                0x12e6fa4e89a97ea2,
+                0x0a9eeee64b55d39a,
+                0x2192992a274fc1a8,
+                0x36ba3c23a3feebbd,
+                0x454d4423643ce80e,
+                0x2a9ac94fa54ca49f,
+            ]
+        );
+        assert_eq!(
+            digest(b""),
+            [
+                0xcf83e1357eefb8bd,
+                0xf1542850d66d8007,
+                0xd620e4050b5715dc,
+                0x83f4a921d36ce9ce,
+                0x47d0d13c5d85f2b0,
+                0xff8318d2877eec2f,
+                0x63b931bd47417a81,
+                0xa538327af927da3e,
+            ]
+        );
+        assert_eq!(digest(&[b'a'; 200])[0], 0x4b11459c33f52a22);
+    }
+
+    #[test]
+    fn test_solve_sha512() {
+        decimal_counter::tests::test_leading_zero_solver(
+            128,
+            |len, bits| {
+                let challenge = Sha512Challenge {
+                    prefix: b"x".repeat(len),
+                    bits,
+                };
+                let (nonce, attempted) = challenge.solve();
+                if let Some(nonce) = &nonce {
+                    assert!(nonce.bytes().all(|c| c.is_ascii_digit()));
+                    assert!(!nonce.starts_with('0'));
+                }
+                (nonce, attempted)
+            },
+            |len, nonce| {
+                Sha512Challenge {
+                    prefix: b"x".repeat(len),
+                    bits: 16,
+                }
+                .leading_zero_bits(nonce)
+            },
+        );
+    }
+}
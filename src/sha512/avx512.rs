@@ -0,0 +1,108 @@
+//! Multi-way SHA-512 implementation for AVX-512.
+use core::arch::x86_64::*;
+
+use super::*;
+
+// disable inline because without hardware AVX-512 this will explode in complexity and cause comptime to skyrocket
+#[cfg_attr(
+    all(not(debug_assertions), not(test), target_feature = "avx512f"),
+    inline(always)
+)]
+/// Do an 8-way SHA-512 compression function without adding back the saved state, one message per 64-bit lane
+///
+/// Ch, Maj and the three-way XORs of the sigma functions are each a single ternary logic instruction.
+pub(crate) fn multiway_arx(state: &mut [__m512i; 8], block: &mut [__m512i; 16]) {
+    unsafe {
+        let [a, b, c, d, e, f, g, h] = &mut *state;
+
+        repeat80!(i, {
+            let w = if i < 16 {
+                block[i]
+            } else {
+                let w15 = block[(i - 15) % 16];
+                let s0 = _mm512_ternarylogic_epi64(
+                    _mm512_ror_epi64(w15, 1),
+                    _mm512_ror_epi64(w15, 8),
+                    _mm512_srli_epi64(w15, 7),
+                    0x96,
+                );
+                let w2 = block[(i - 2) % 16];
+                let s1 = _mm512_ternarylogic_epi64(
+                    _mm512_ror_epi64(w2, 19),
+                    _mm512_ror_epi64(w2, 61),
+                    _mm512_srli_epi64(w2, 6),
+                    0x96,
+                );
+                block[i % 16] = _mm512_add_epi64(block[i % 16], s0);
+                block[i % 16] = _mm512_add_epi64(block[i % 16], block[(i - 7) % 16]);
+                block[i % 16] = _mm512_add_epi64(block[i % 16], s1);
+                block[i % 16]
+            };
+
+            let s1 = _mm512_ternarylogic_epi64(
+                _mm512_ror_epi64(*e, 14),
+                _mm512_ror_epi64(*e, 18),
+                _mm512_ror_epi64(*e, 41),
+                0x96,
+            );
+            let ch = _mm512_ternarylogic_epi64(*e, *f, *g, 0xca);
+            let mut t1 = _mm512_add_epi64(s1, ch);
+            t1 = _mm512_add_epi64(t1, _mm512_set1_epi64(K64[i] as _));
+            t1 = _mm512_add_epi64(t1, w);
+            t1 = _mm512_add_epi64(t1, *h);
+
+            let s0 = _mm512_ternarylogic_epi64(
+                _mm512_ror_epi64(*a, 28),
+                _mm512_ror_epi64(*a, 34),
+                _mm512_ror_epi64(*a, 39),
+                0x96,
+            );
+            let maj = _mm512_ternarylogic_epi64(*a, *b, *c, 0xe8);
+            let t2 = _mm512_add_epi64(s0, maj);
+
+            *h = *g;
+            *g = *f;
+            *f = *e;
+            *e = _mm512_add_epi64(*d, t1);
+            *d = *c;
+            *c = *b;
+            *b = *a;
+            *a = _mm512_add_epi64(t1, t2);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiway_arx_matches_reference() {
+        let blocks: [[u64; 16]; 8] = core::array::from_fn(|lane| {
+            core::array::from_fn(|i| {
+                (lane as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15) ^ (i as u64).rotate_left(29)
+            })
+        });
+        let midstate = digest(b"midstate");
+
+        unsafe {
+            let mut block: [__m512i; 16] = core::array::from_fn(|i| {
+                let lanes: [u64; 8] = core::array::from_fn(|lane| blocks[lane][i]);
+                _mm512_loadu_si512(lanes.as_ptr() as *const _)
+            });
+            let mut state: [__m512i; 8] =
+                core::array::from_fn(|i| _mm512_set1_epi64(midstate[i] as _));
+            multiway_arx(&mut state, &mut block);
+
+            for (i, word) in state.iter().enumerate() {
+                let mut lanes = [0u64; 8];
+                _mm512_storeu_si512(lanes.as_mut_ptr() as *mut _, *word);
+                for (lane, value) in lanes.iter().enumerate() {
+                    let mut expected = midstate;
+                    digest_block(&mut expected, &blocks[lane]);
+                    assert_eq!(value.wrapping_add(midstate[i]), expected[i]);
+                }
+            }
+        }
+    }
+}
@@ -2,16 +2,33 @@ use alloc::string::ToString;
 use sha2::Digest;
 
 /// AVX-512 solver
-#[cfg(all(target_arch = "x86_64", any(doc, target_feature = "avx512f")))]
+///
+/// With the `multiversion` feature this is compiled regardless of the build target,
+/// constructing a solver then panics if the CPU lacks the target features, [`autotune::AnySolver`] only picks supported backends.
+#[cfg(all(
+    target_arch = "x86_64",
+    any(doc, target_feature = "avx512f", feature = "multiversion")
+))]
 pub mod avx512;
 
 #[cfg(all(
     any(target_arch = "x86_64", target_arch = "x86"),
-    any(doc, target_feature = "sha")
+    any(doc, target_feature = "sha", feature = "multiversion")
 ))]
 /// SHA-NI solver
+///
+/// With the `multiversion` feature this is compiled regardless of the build target,
+/// constructing a solver then panics if the CPU lacks the target features, [`autotune::AnySolver`] only picks supported backends.
 pub mod sha_ni;
 
+/// Hybrid AVX-512 and SHA-NI solver
+#[cfg(all(
+    target_arch = "x86_64",
+    feature = "hybrid-sha-ni",
+    any(doc, all(target_feature = "avx512f", target_feature = "sha"))
+))]
+pub mod hybrid;
+
 /// SIMD128 solver
 #[cfg(target_arch = "wasm32")]
 pub mod simd128;
@@ -19,6 +36,9 @@ pub mod simd128;
 /// Safe solver
 pub mod safe;
 
+/// Runtime backend selection
+pub mod autotune;
+
 /// Less than test (such as Anubis and GoAway)
 pub const SOLVE_TYPE_LT: u8 = 1;
 /// Greater than test (such as mCaptcha)
@@ -0,0 +1,627 @@
+//! Runtime selection of the decimal solver backend.
+//!
+//! Every backend compiled into the binary and supported by the CPU is available at runtime, [`AnySolver`] dispatches to the selected one.
+//! Without calibration the selection follows the static preference order of [`Backend::AVAILABLE`].
+//!
+//! With the `multiversion` feature the AVX-512 and SHA-NI kernels are compiled for their target features
+//! regardless of the build target and only used if the CPU supports them,
+//! so a single portable binary still runs at full speed on newer CPUs.
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::message::DecimalMessage;
+
+/// A decimal solver backend compiled into this binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// AVX-512 solver
+    #[cfg(all(
+        target_arch = "x86_64",
+        any(target_feature = "avx512f", feature = "multiversion")
+    ))]
+    Avx512,
+    /// SHA-NI solver
+    #[cfg(all(
+        any(target_arch = "x86_64", target_arch = "x86"),
+        any(target_feature = "sha", feature = "multiversion")
+    ))]
+    ShaNi,
+    /// SIMD128 solver
+    #[cfg(target_arch = "wasm32")]
+    Simd128,
+    /// Safe solver
+    Safe,
+}
+
+impl Backend {
+    /// The available backends, in order of static preference
+    pub const AVAILABLE: &'static [Backend] = &[
+        #[cfg(all(
+            target_arch = "x86_64",
+            any(target_feature = "avx512f", feature = "multiversion")
+        ))]
+        Backend::Avx512,
+        #[cfg(all(
+            any(target_arch = "x86_64", target_arch = "x86"),
+            any(target_feature = "sha", feature = "multiversion")
+        ))]
+        Backend::ShaNi,
+        #[cfg(target_arch = "wasm32")]
+        Backend::Simd128,
+        Backend::Safe,
+    ];
+
+    /// The name of the backend
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(all(
+                target_arch = "x86_64",
+                any(target_feature = "avx512f", feature = "multiversion")
+            ))]
+            Backend::Avx512 => "avx512",
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                any(target_feature = "sha", feature = "multiversion")
+            ))]
+            Backend::ShaNi => "sha-ni",
+            #[cfg(target_arch = "wasm32")]
+            Backend::Simd128 => "simd128",
+            Backend::Safe => "safe",
+        }
+    }
+
+    /// Look up an available backend by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::AVAILABLE
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == name)
+    }
+
+    /// Whether the CPU supports the backend
+    ///
+    /// This is always true without the `multiversion` feature, as the backends are then selected at compile time.
+    pub fn is_supported(self) -> bool {
+        match self {
+            #[cfg(all(
+                target_arch = "x86_64",
+                any(target_feature = "avx512f", feature = "multiversion")
+            ))]
+            Backend::Avx512 => {
+                #[cfg(feature = "multiversion")]
+                {
+                    std::arch::is_x86_feature_detected!("avx512f")
+                        && std::arch::is_x86_feature_detected!("avx512vl")
+                        && std::arch::is_x86_feature_detected!("avx512bw")
+                        && std::arch::is_x86_feature_detected!("avx512dq")
+                        && std::arch::is_x86_feature_detected!("bmi1")
+                        && std::arch::is_x86_feature_detected!("bmi2")
+                }
+                #[cfg(not(feature = "multiversion"))]
+                true
+            }
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                any(target_feature = "sha", feature = "multiversion")
+            ))]
+            Backend::ShaNi => {
+                #[cfg(feature = "multiversion")]
+                {
+                    std::arch::is_x86_feature_detected!("sha")
+                        && std::arch::is_x86_feature_detected!("sse2")
+                        && std::arch::is_x86_feature_detected!("ssse3")
+                        && std::arch::is_x86_feature_detected!("sse4.1")
+                }
+                #[cfg(not(feature = "multiversion"))]
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// The available backends supported by the CPU, in order of static preference
+    pub fn supported() -> impl Iterator<Item = Backend> {
+        Self::AVAILABLE
+            .iter()
+            .copied()
+            .filter(|backend| backend.is_supported())
+    }
+
+    fn index(self) -> u8 {
+        Self::AVAILABLE
+            .iter()
+            .position(|&backend| backend == self)
+            .expect("backend is available") as u8
+    }
+}
+
+/// the index of the selected backend in [`Backend::AVAILABLE`], out of bounds if none was selected
+static SELECTED: AtomicU8 = AtomicU8::new(u8::MAX);
+
+/// The backend [`AnySolver`] uses when constructed from a message
+///
+/// Unless another backend was selected this is the first supported one in [`Backend::AVAILABLE`].
+pub fn selected() -> Backend {
+    match Backend::AVAILABLE.get(SELECTED.load(Ordering::Relaxed) as usize) {
+        Some(&backend) => backend,
+        None => Backend::supported()
+            .next()
+            .expect("the safe backend is always supported"),
+    }
+}
+
+/// Select the backend [`AnySolver`] uses when constructed from a message
+///
+/// Panics if the backend is not supported by the CPU.
+pub fn select(backend: Backend) {
+    assert!(
+        backend.is_supported(),
+        "{} is not supported",
+        backend.name()
+    );
+    SELECTED.store(backend.index(), Ordering::Relaxed);
+}
+
+/// Solves expected to take fewer attempts than this skip the wide backends, whose setup cost dominates
+pub const LOW_DIFFICULTY_ATTEMPTS: u64 = 1 << 13;
+
+/// The expected number of attempts to satisfy a target
+pub fn expected_attempts(ty: u8, target: u64, mask: u64) -> u64 {
+    // the number of 64-bit values that satisfy the target
+    let satisfying = match ty {
+        crate::solver::SOLVE_TYPE_LT => target,
+        crate::solver::SOLVE_TYPE_GT => !target,
+        _ => return 1u64.checked_shl(mask.count_ones()).unwrap_or(u64::MAX),
+    };
+    ((1u128 << 64) / satisfying.max(1) as u128).min(u64::MAX as u128) as u64
+}
+
+/// The backend [`AnySolver::for_target`] uses for a target
+///
+/// Below [`LOW_DIFFICULTY_ATTEMPTS`] this is the single stream SHA-NI backend if available,
+/// otherwise the [`selected`] backend.
+pub fn select_for_target(ty: u8, target: u64, mask: u64) -> Backend {
+    #[cfg(all(
+        any(target_arch = "x86_64", target_arch = "x86"),
+        any(target_feature = "sha", feature = "multiversion")
+    ))]
+    if expected_attempts(ty, target, mask) < LOW_DIFFICULTY_ATTEMPTS
+        && Backend::ShaNi.is_supported()
+    {
+        return Backend::ShaNi;
+    }
+    #[cfg(not(all(
+        any(target_arch = "x86_64", target_arch = "x86"),
+        any(target_feature = "sha", feature = "multiversion")
+    )))]
+    let _ = (ty, target, mask);
+
+    selected()
+}
+
+/// A decimal solver on any available backend
+pub enum AnySolver {
+    /// AVX-512 solver
+    #[cfg(all(
+        target_arch = "x86_64",
+        any(target_feature = "avx512f", feature = "multiversion")
+    ))]
+    Avx512(crate::solver::avx512::DecimalSolver),
+    /// SHA-NI solver
+    #[cfg(all(
+        any(target_arch = "x86_64", target_arch = "x86"),
+        any(target_feature = "sha", feature = "multiversion")
+    ))]
+    ShaNi(crate::solver::sha_ni::DecimalSolver),
+    /// SIMD128 solver
+    #[cfg(target_arch = "wasm32")]
+    Simd128(crate::solver::simd128::DecimalSolver),
+    /// Safe solver
+    Safe(crate::solver::safe::DecimalSolver),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $solver:ident => $body:expr) => {
+        match $self {
+            #[cfg(all(
+                target_arch = "x86_64",
+                any(target_feature = "avx512f", feature = "multiversion")
+            ))]
+            AnySolver::Avx512($solver) => $body,
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                any(target_feature = "sha", feature = "multiversion")
+            ))]
+            AnySolver::ShaNi($solver) => $body,
+            #[cfg(target_arch = "wasm32")]
+            AnySolver::Simd128($solver) => $body,
+            AnySolver::Safe($solver) => $body,
+        }
+    };
+}
+
+impl AnySolver {
+    /// Create a solver on a specific backend
+    ///
+    /// Panics if the backend is not supported by the CPU.
+    pub fn new(backend: Backend, message: DecimalMessage) -> Self {
+        assert!(
+            backend.is_supported(),
+            "{} is not supported",
+            backend.name()
+        );
+        match backend {
+            #[cfg(all(
+                target_arch = "x86_64",
+                any(target_feature = "avx512f", feature = "multiversion")
+            ))]
+            Backend::Avx512 => Self::Avx512(message.into()),
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                any(target_feature = "sha", feature = "multiversion")
+            ))]
+            Backend::ShaNi => Self::ShaNi(message.into()),
+            #[cfg(target_arch = "wasm32")]
+            Backend::Simd128 => Self::Simd128(message.into()),
+            Backend::Safe => Self::Safe(message.into()),
+        }
+    }
+
+    /// Create a solver on the backend best suited for a target, see [`select_for_target`]
+    pub fn for_target(message: DecimalMessage, ty: u8, target: u64, mask: u64) -> Self {
+        Self::new(select_for_target(ty, target, mask), message)
+    }
+
+    /// The backend of the solver
+    pub fn backend(&self) -> Backend {
+        match self {
+            #[cfg(all(
+                target_arch = "x86_64",
+                any(target_feature = "avx512f", feature = "multiversion")
+            ))]
+            Self::Avx512(_) => Backend::Avx512,
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                any(target_feature = "sha", feature = "multiversion")
+            ))]
+            Self::ShaNi(_) => Backend::ShaNi,
+            #[cfg(target_arch = "wasm32")]
+            Self::Simd128(_) => Backend::Simd128,
+            Self::Safe(_) => Backend::Safe,
+        }
+    }
+
+    /// Get the attempted nonces.
+    pub fn get_attempted_nonces(&self) -> u64 {
+        dispatch!(self, solver => solver.get_attempted_nonces())
+    }
+
+    /// Set the limit.
+    pub fn set_limit(&mut self, limit: u64) {
+        dispatch!(self, solver => solver.set_limit(limit))
+    }
+}
+
+impl From<DecimalMessage> for AnySolver {
+    fn from(message: DecimalMessage) -> Self {
+        Self::new(selected(), message)
+    }
+}
+
+/// Solve on the AVX-512 backend, with multiversion the kernels are compiled for these target features
+///
+/// The double block solver is inlined here, the single block kernels carry the same target features themselves.
+#[cfg(all(
+    target_arch = "x86_64",
+    any(target_feature = "avx512f", feature = "multiversion")
+))]
+#[cfg_attr(
+    feature = "multiversion",
+    target_feature(enable = "avx512f,avx512vl,avx512bw,avx512dq,bmi1,bmi2")
+)]
+fn solve_avx512<const TYPE: u8>(
+    solver: &mut crate::solver::avx512::DecimalSolver,
+    target: u64,
+    mask: u64,
+) -> Option<(u64, [u32; 8])> {
+    use crate::solver::{Solver, avx512::DecimalSolver};
+    match solver {
+        DecimalSolver::SingleBlock(solver) => solver.solve::<TYPE>(target, mask),
+        DecimalSolver::DoubleBlock(solver) => solver.solve::<TYPE>(target, mask),
+    }
+}
+
+/// Solve on the SHA-NI backend, with multiversion the kernels are compiled for these target features
+///
+/// The double block solver is inlined here, the single block kernels carry the same target features themselves.
+#[cfg(all(
+    any(target_arch = "x86_64", target_arch = "x86"),
+    any(target_feature = "sha", feature = "multiversion")
+))]
+#[cfg_attr(
+    feature = "multiversion",
+    target_feature(enable = "sha,sse2,ssse3,sse4.1")
+)]
+fn solve_sha_ni<const TYPE: u8>(
+    solver: &mut crate::solver::sha_ni::DecimalSolver,
+    target: u64,
+    mask: u64,
+) -> Option<(u64, [u32; 8])> {
+    use crate::solver::{Solver, sha_ni::DecimalSolver};
+    match solver {
+        DecimalSolver::SingleBlock(solver) => solver.solve::<TYPE>(target, mask),
+        DecimalSolver::DoubleBlock(solver) => solver.solve::<TYPE>(target, mask),
+    }
+}
+
+impl crate::solver::Solver for AnySolver {
+    // SAFETY: a solver is only constructed on a backend supported by the CPU
+    #[allow(unused_unsafe)]
+    fn solve<const TYPE: u8>(&mut self, target: u64, mask: u64) -> Option<(u64, [u32; 8])> {
+        match self {
+            #[cfg(all(
+                target_arch = "x86_64",
+                any(target_feature = "avx512f", feature = "multiversion")
+            ))]
+            AnySolver::Avx512(solver) => unsafe { solve_avx512::<TYPE>(solver, target, mask) },
+            #[cfg(all(
+                any(target_arch = "x86_64", target_arch = "x86"),
+                any(target_feature = "sha", feature = "multiversion")
+            ))]
+            AnySolver::ShaNi(solver) => unsafe { solve_sha_ni::<TYPE>(solver, target, mask) },
+            #[cfg(target_arch = "wasm32")]
+            AnySolver::Simd128(solver) => solver.solve::<TYPE>(target, mask),
+            AnySolver::Safe(solver) => solver.solve::<TYPE>(target, mask),
+        }
+    }
+}
+
+/// Measure the hash rate of a backend in hashes per second, spending about `budget`
+///
+/// The calibration searches an unreachable target so every run uses up its limit.
+#[cfg(feature = "std")]
+pub fn measure(backend: Backend, budget: std::time::Duration) -> f64 {
+    let mut limit = 1 << 16;
+    loop {
+        let message = DecimalMessage::new(b"pow-buster autotune calibration", 0)
+            .expect("calibration prefix is valid");
+        let mut solver = AnySolver::new(backend, message);
+        solver.set_limit(limit);
+        let start = std::time::Instant::now();
+        let result = crate::solver::Solver::solve_nonce_only::<{ crate::solver::SOLVE_TYPE_LT }>(
+            &mut solver,
+            core::hint::black_box(0),
+            !0,
+        );
+        let elapsed = start.elapsed();
+        debug_assert!(result.is_none());
+        if elapsed >= budget || limit >= 1 << 40 {
+            return solver.get_attempted_nonces() as f64 / elapsed.as_secs_f64().max(1e-9);
+        }
+        limit *= 2;
+    }
+}
+
+/// Measure every supported backend within about `budget` in total, returning their hash rates in hashes per second
+#[cfg(feature = "std")]
+pub fn calibrate(budget: std::time::Duration) -> Vec<(Backend, f64)> {
+    let per_backend = budget / Backend::supported().count() as u32;
+    Backend::supported()
+        .map(|backend| (backend, measure(backend, per_backend)))
+        .collect()
+}
+
+/// The CPU model, a winner measured on one model says nothing about another with the same features
+///
+/// This is the CPUID vendor and signature (family, model and stepping) on x86, empty elsewhere.
+#[cfg(feature = "std")]
+pub fn cpu_fingerprint() -> String {
+    #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+    {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::__cpuid;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::__cpuid;
+
+        // SAFETY: leaves 0 and 1 are implemented by every CPU that can run this binary
+        #[allow(unused_unsafe)]
+        let (vendor, signature) = unsafe { (__cpuid(0), __cpuid(1).eax) };
+        let vendor: Vec<u8> = [vendor.ebx, vendor.edx, vendor.ecx]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .filter(u8::is_ascii_alphanumeric)
+            .collect();
+        format!("{}-{:08x}", String::from_utf8_lossy(&vendor), signature)
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "x86")))]
+    String::new()
+}
+
+/// The cache file of [`autotune`]
+///
+/// `POW_BUSTER_AUTOTUNE_CACHE` if set, otherwise `pow-buster/autotune-<cpu fingerprint>` under `XDG_CACHE_HOME` or `~/.cache`,
+/// so CPUs sharing a home directory do not overwrite each other's winner.
+#[cfg(feature = "std")]
+pub fn cache_path() -> Option<std::path::PathBuf> {
+    if let Some(path) = std::env::var_os("POW_BUSTER_AUTOTUNE_CACHE") {
+        return Some(path.into());
+    }
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".cache"))
+        })?;
+    Some(
+        cache_dir
+            .join("pow-buster")
+            .join(format!("autotune-{}", cpu_fingerprint())),
+    )
+}
+
+/// the lines before the winner in the cache file, a cached winner is only valid if they all match:
+/// the crate version, the CPU model, the backends compiled into this build and those the CPU supports
+#[cfg(feature = "std")]
+fn cache_header() -> String {
+    let names = |backends: &mut dyn Iterator<Item = Backend>| {
+        backends.map(Backend::name).collect::<Vec<_>>().join(",")
+    };
+    format!(
+        "version {}\ncpu {}\navailable {}\nsupported {}\n",
+        env!("CARGO_PKG_VERSION"),
+        cpu_fingerprint(),
+        names(&mut Backend::AVAILABLE.iter().copied()),
+        names(&mut Backend::supported()),
+    )
+}
+
+/// Find the empirically fastest backend, reading and recording the winner at `cache`
+///
+/// The winner is read from `cache` if it was recorded by the same build on the same CPU model,
+/// otherwise every backend is calibrated for about 50 ms in total and the winner is recorded.
+/// Failing to read or write the cache is not an error, the calibration result is used as is.
+///
+/// Unlike [`autotune`] this does not [`select`] the winner.
+#[cfg(feature = "std")]
+pub fn autotune_with_cache(cache: Option<&std::path::Path>) -> Backend {
+    let header = cache_header();
+
+    let cached = cache
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| {
+            let name = content.strip_prefix(&header)?.strip_prefix("winner ")?;
+            Backend::from_name(name.trim_end())
+        })
+        .filter(|backend| backend.is_supported());
+    if let Some(backend) = cached {
+        return backend;
+    }
+
+    let winner = calibrate(std::time::Duration::from_millis(50))
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(backend, _)| backend)
+        .unwrap_or_else(selected);
+
+    if let Some(path) = cache {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, format!("{}winner {}\n", header, winner.name()));
+    }
+
+    winner
+}
+
+/// Select the empirically fastest backend for [`AnySolver`], cached at [`cache_path`]
+///
+/// See [`autotune_with_cache`].
+#[cfg(feature = "std")]
+pub fn autotune() -> Backend {
+    let winner = autotune_with_cache(cache_path().as_deref());
+    select(winner);
+    winner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_solver() {
+        crate::solver::tests::test_decimal_validator::<AnySolver, _>(|prefix, search_space| {
+            DecimalMessage::new(prefix, search_space)
+                .map(|message| AnySolver::new(Backend::supported().last().unwrap(), message))
+        });
+    }
+
+    #[test]
+    fn test_low_difficulty_fast_path() {
+        use crate::solver::{SOLVE_TYPE_GT, SOLVE_TYPE_LT, SOLVE_TYPE_MASK};
+
+        assert_eq!(expected_attempts(SOLVE_TYPE_LT, 1 << 52, !0), 1 << 12);
+        assert_eq!(
+            expected_attempts(SOLVE_TYPE_GT, u64::MAX - (1 << 52), !0),
+            1 << 12
+        );
+        assert_eq!(expected_attempts(SOLVE_TYPE_MASK, 0, !0 << 52), 1 << 12);
+
+        #[cfg(all(
+            any(target_arch = "x86_64", target_arch = "x86"),
+            any(target_feature = "sha", feature = "multiversion")
+        ))]
+        if Backend::ShaNi.is_supported() {
+            assert_eq!(
+                select_for_target(SOLVE_TYPE_LT, 1 << 52, !0),
+                Backend::ShaNi
+            );
+        }
+    }
+
+    #[test]
+    fn test_autotune_cache() {
+        let path =
+            std::env::temp_dir().join(format!("pow-buster-autotune-test-{}", std::process::id()));
+        let header = cache_header();
+
+        let winner = autotune_with_cache(Some(&path));
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("cache is written"),
+            format!("{}winner {}\n", header, winner.name())
+        );
+
+        // a cached winner is used as is
+        let cached = Backend::supported().last().unwrap();
+        std::fs::write(&path, format!("{}winner {}\n", header, cached.name())).unwrap();
+        assert_eq!(autotune_with_cache(Some(&path)), cached);
+
+        // a winner recorded by another build or on another CPU model, or not compiled into this build, is ignored
+        for stale in [
+            format!(
+                "{}winner {}\n",
+                header.replace(env!("CARGO_PKG_VERSION"), "0.0.0"),
+                cached.name()
+            ),
+            format!(
+                "{}winner {}\n",
+                header.replace(&cpu_fingerprint(), "OtherCPU-00000000"),
+                cached.name()
+            ),
+            format!("{}winner gpu\n", header),
+        ] {
+            std::fs::write(&path, &stale).unwrap();
+            let _ = autotune_with_cache(Some(&path));
+            assert_ne!(std::fs::read_to_string(&path).unwrap(), stale);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_for_target_follows_winner() {
+        use crate::solver::SOLVE_TYPE_LT;
+
+        let path = std::env::temp_dir().join(format!(
+            "pow-buster-autotune-winner-test-{}",
+            std::process::id()
+        ));
+        let previous = selected();
+
+        for recorded in Backend::supported() {
+            std::fs::write(
+                &path,
+                format!("{}winner {}\n", cache_header(), recorded.name()),
+            )
+            .unwrap();
+            let winner = autotune_with_cache(Some(&path));
+            assert_eq!(winner, recorded);
+            select(winner);
+
+            // too hard for the low difficulty fast path
+            let message = DecimalMessage::new(b"autotune winner", 0).unwrap();
+            let solver = AnySolver::for_target(message, SOLVE_TYPE_LT, 1 << 20, !0);
+            assert_eq!(solver.backend(), recorded);
+        }
+
+        select(previous);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    Align16, PREFIX_OFFSET_TO_LANE_POSITION, SWAP_DWORD_BYTE_ORDER, decompose_blocks_mut,
+    Align16, Align64, PREFIX_OFFSET_TO_LANE_POSITION, SWAP_DWORD_BYTE_ORDER, decompose_blocks_mut,
     is_supported_lane_position,
     message::{DecimalMessage, DoubleBlockMessage, GoAwayMessage, SingleBlockMessage},
 };
@@ -18,7 +18,8 @@ static LANE_ID_LSB_STR_0: Align16<[u8; 6 * 16]> =
     Align16(*b"012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345");
 
 #[cfg(feature = "compare-64bit")]
-const INDEX_REMAP_PUNPCKLDQ: [usize; 16] = [0, 1, 4, 5, 8, 9, 12, 13, 2, 3, 6, 7, 10, 11, 14, 15];
+pub(super) const INDEX_REMAP_PUNPCKLDQ: [usize; 16] =
+    [0, 1, 4, 5, 8, 9, 12, 13, 2, 3, 6, 7, 10, 11, 14, 15];
 
 #[inline(always)]
 fn load_lane_id_epi32<const N: usize>(src: &Align16<[u8; N]>, set_idx: usize) -> __m512i {
@@ -26,6 +27,96 @@ fn load_lane_id_epi32<const N: usize>(src: &Align16<[u8; N]>, set_idx: usize) ->
     unsafe { _mm512_cvtepi8_epi32(_mm_load_si128(src.as_ptr().add(set_idx * 16).cast())) }
 }
 
+#[inline(always)]
+fn extract_lane_epi32(v: __m512i, lane: usize) -> u32 {
+    unsafe {
+        _mm_cvtsi128_si32(_mm512_castsi512_si128(_mm512_maskz_compress_epi32(
+            1 << lane,
+            v,
+        ))) as u32
+    }
+}
+
+/// multipliers and shifts dividing an inner key below 2^27 exactly by the powers of ten
+const DIV_POW10: [(u64, u64); 8] = {
+    let mut table = [(0, 0); 8];
+    let mut i = 0;
+    while i < 8 {
+        let d = 10u64.pow(i as u32);
+        let shift = 27 + (u64::BITS - (d - 1).leading_zeros()) as u64;
+        table[i] = ((1u64 << shift).div_ceil(d), shift);
+        i += 1;
+    }
+    table
+};
+
+/// The lowest 8 ASCII digits of an inner key below 10^8, the least significant digit in the lowest byte
+///
+/// Octal digits range from '1' to '8'.
+/// Only AVX-512F is used, the decimal solvers do not require AVX-512BW or AVX-512VL.
+#[inline(always)]
+fn inner_key_digits_epi8<const OCTAL: bool>(key: u32) -> __m128i {
+    unsafe {
+        if OCTAL {
+            let digits = _mm512_srlv_epi32(
+                _mm512_set1_epi32(key as _),
+                _mm512_setr_epi32(0, 3, 6, 9, 12, 15, 18, 21, 0, 0, 0, 0, 0, 0, 0, 0),
+            );
+            _mm512_cvtepi32_epi8(_mm512_maskz_add_epi32(
+                0xFF,
+                _mm512_and_si512(digits, _mm512_set1_epi32(7)),
+                _mm512_set1_epi32(b'1' as _),
+            ))
+        } else {
+            // every digit is the quotient by its power of ten less ten times the next quotient
+            let quotients = _mm512_srlv_epi64(
+                _mm512_mul_epu32(
+                    _mm512_set1_epi64(key as _),
+                    _mm512_setr_epi64(
+                        DIV_POW10[0].0 as _,
+                        DIV_POW10[1].0 as _,
+                        DIV_POW10[2].0 as _,
+                        DIV_POW10[3].0 as _,
+                        DIV_POW10[4].0 as _,
+                        DIV_POW10[5].0 as _,
+                        DIV_POW10[6].0 as _,
+                        DIV_POW10[7].0 as _,
+                    ),
+                ),
+                _mm512_setr_epi64(
+                    DIV_POW10[0].1 as _,
+                    DIV_POW10[1].1 as _,
+                    DIV_POW10[2].1 as _,
+                    DIV_POW10[3].1 as _,
+                    DIV_POW10[4].1 as _,
+                    DIV_POW10[5].1 as _,
+                    DIV_POW10[6].1 as _,
+                    DIV_POW10[7].1 as _,
+                ),
+            );
+            let next_quotients = _mm512_alignr_epi64(_mm512_setzero_si512(), quotients, 1);
+            let digits = _mm512_sub_epi64(
+                quotients,
+                _mm512_mul_epu32(next_quotients, _mm512_set1_epi64(10)),
+            );
+            _mm512_cvtepi64_epi8(_mm512_or_si512(digits, _mm512_set1_epi64(b'0' as _)))
+        }
+    }
+}
+
+/// Panic unless the CPU supports the kernels of this module
+///
+/// With `multiversion` the kernels are compiled for their target features regardless of the build target,
+/// so every solver checks the CPU when it is constructed, before any kernel can run.
+#[inline(always)]
+fn assert_cpu_supported() {
+    #[cfg(feature = "multiversion")]
+    assert!(
+        super::autotune::Backend::Avx512.is_supported(),
+        "AVX-512 is not supported by the CPU"
+    );
+}
+
 /// AVX-512 decimal nonce single block solver.
 ///
 ///
@@ -40,6 +131,7 @@ pub struct SingleBlockSolver {
 
 impl From<super::safe::SingleBlockSolver> for SingleBlockSolver {
     fn from(solver: super::safe::SingleBlockSolver) -> Self {
+        assert_cpu_supported();
         Self {
             message: solver.message,
             attempted_nonces: solver.attempted_nonces,
@@ -50,6 +142,7 @@ impl From<super::safe::SingleBlockSolver> for SingleBlockSolver {
 
 impl From<SingleBlockMessage> for SingleBlockSolver {
     fn from(message: SingleBlockMessage) -> Self {
+        assert_cpu_supported();
         Self {
             message,
             attempted_nonces: 0,
@@ -97,16 +190,31 @@ impl crate::solver::Solver for SingleBlockSolver {
 
         // make sure there are no runtime "register indexing" logic
         #[inline(never)]
+        #[cfg_attr(
+            feature = "multiversion",
+            target_feature(enable = "avx512f,avx512vl,avx512bw,avx512dq,bmi1,bmi2")
+        )]
         fn solve_inner<
             const DIGIT_WORD_IDX0: usize,
             const DIGIT_WORD_IDX1_INCREMENT: bool,
             const TYPE: u8,
             const MUTATION_TYPE: u8,
+            const HOTSTART_ROUND: usize,
         >(
             this: &mut SingleBlockSolver,
             target: u64,
             mask: u64,
         ) -> Option<u64> {
+            // rounds can only be hoisted past the lane ID word if no inner key digit shares it
+            const {
+                assert!(
+                    HOTSTART_ROUND == DIGIT_WORD_IDX0
+                        || (HOTSTART_ROUND == DIGIT_WORD_IDX0 + 1
+                            && (MUTATION_TYPE & MUTATION_TYPE_ALIGNED != 0
+                                || DIGIT_WORD_IDX1_INCREMENT))
+                )
+            };
+
             let mut partial_state = this.message.prefix_state;
             crate::sha256::ingest_message_prefix::<DIGIT_WORD_IDX0>(
                 &mut partial_state,
@@ -136,6 +244,17 @@ impl crate::solver::Solver for SingleBlockSolver {
             let lane_id_0_byte_idx = this.message.digit_index % 4;
             let lane_id_1_byte_idx = (this.message.digit_index + 1) % 4;
 
+            // the unaligned inner key digits are shuffled into the three words from the lane ID word on,
+            // the first inner key digit is in byte 2 counting from the first lane ID digit
+            let mut inner_key_shuffle = Align16([0x80u8; 16]);
+            let mut inner_key_keep = Align16([0xFFu8; 16]);
+            for digit in 0..7 {
+                let byte = lane_id_0_byte_idx + 2 + digit;
+                let lane_byte = byte / 4 * 4 + 3 - byte % 4;
+                inner_key_shuffle[lane_byte] = 6 - digit as u8;
+                inner_key_keep[lane_byte] = 0;
+            }
+
             for prefix_set_index in 0..(if MUTATION_TYPE & MUTATION_TYPE_OCTAL != 0 {
                 6
             } else {
@@ -148,6 +267,17 @@ impl crate::solver::Solver for SingleBlockSolver {
                 };
 
                 unsafe {
+                    let digit_words = _mm_setr_epi32(
+                        this.message.message[DIGIT_WORD_IDX0] as _,
+                        this.message.message[DIGIT_WORD_IDX0 + 1] as _,
+                        this.message.message[DIGIT_WORD_IDX0 + 2] as _,
+                        0,
+                    );
+                    let inner_key_shuffle = _mm_load_si128(inner_key_shuffle.as_ptr().cast());
+                    // the shuffle zeroes every byte outside the inner key, so it is merged with an OR
+                    let digit_words_without_inner_key =
+                        _mm_and_si128(digit_words, _mm_load_si128(inner_key_keep.as_ptr().cast()));
+
                     let (lane_id_0_or_value, lane_id_1_or_value) =
                         if MUTATION_TYPE & MUTATION_TYPE_OCTAL != 0 {
                             let lane_id_0_or_value = _mm512_sll_epi32(
@@ -179,13 +309,29 @@ impl crate::solver::Solver for SingleBlockSolver {
                         lane_id_0_or_value
                     };
 
+                    // the lane ID word is shared by every inner key of the prefix set, so its round is done once here
+                    let mut prefix_set_state: [__m512i; 8] =
+                        core::array::from_fn(|i| _mm512_set1_epi32(partial_state[i] as _));
+                    crate::sha256::avx512::multiway_arx_partial::<DIGIT_WORD_IDX0, HOTSTART_ROUND>(
+                        &mut prefix_set_state,
+                        &core::array::from_fn(|i| {
+                            let word = _mm512_set1_epi32(this.message.message[i] as _);
+                            if i == DIGIT_WORD_IDX0 {
+                                _mm512_or_epi32(word, lane_id_0_or_value_v)
+                            } else {
+                                word
+                            }
+                        }),
+                    );
+
                     let inner_iteration_end = if MUTATION_TYPE & MUTATION_TYPE_OCTAL != 0 {
                         0o10_000_000
                     } else {
                         10_000_000
                     };
-                    let max_iterations = inner_iteration_end;
-                    remaining_limit -= max_iterations as u64;
+                    let max_iterations =
+                        remaining_limit.div_ceil(16).min(inner_iteration_end as u64) as _;
+                    remaining_limit = remaining_limit.saturating_sub(max_iterations as u64 * 16);
 
                     // soft pipeline this to compute the new message after the hash
                     // LLVM seems to handle cases where high register pressure work happens first better
@@ -193,10 +339,43 @@ impl crate::solver::Solver for SingleBlockSolver {
                     // doesn't seem to affect performance on my Zen4 but dirty so avoid
                     // on the last iteration simd_itoa(10_000_000) is unit-tested to convert to 0000\x80000
                     // so no fixup is needed-saves a branch on LLVM codegen
-                    for next_inner_key in 1..=inner_iteration_end {
+                    for next_inner_key in 1..=max_iterations {
+                        // the words are computed from the inner key on their own, so no iteration waits for the last one
+                        let inner_key_words = if MUTATION_TYPE & MUTATION_TYPE_ALIGNED == 0 {
+                            let digits = if MUTATION_TYPE & MUTATION_TYPE_OCTAL != 0 {
+                                inner_key_digits_epi8::<true>(next_inner_key - 1)
+                            } else {
+                                inner_key_digits_epi8::<false>(next_inner_key - 1)
+                            };
+                            _mm_or_si128(
+                                digit_words_without_inner_key,
+                                _mm_shuffle_epi8(digits, inner_key_shuffle),
+                            )
+                        } else {
+                            digit_words
+                        };
+
                         macro_rules! fetch_msg {
                             ($idx:expr) => {
-                                if $idx == DIGIT_WORD_IDX0 {
+                                if MUTATION_TYPE & MUTATION_TYPE_ALIGNED == 0
+                                    && (DIGIT_WORD_IDX0..=DIGIT_WORD_IDX0 + 2).contains(&$idx)
+                                {
+                                    let word = _mm512_permutexvar_epi32(
+                                        _mm512_set1_epi32(
+                                            ($idx as usize).wrapping_sub(DIGIT_WORD_IDX0) as _,
+                                        ),
+                                        _mm512_castsi128_si512(inner_key_words),
+                                    );
+                                    if $idx == DIGIT_WORD_IDX0 {
+                                        _mm512_or_epi32(word, lane_id_0_or_value_v)
+                                    } else if DIGIT_WORD_IDX1_INCREMENT
+                                        && $idx == DIGIT_WORD_IDX0 + 1
+                                    {
+                                        _mm512_or_epi32(word, lane_id_1_or_value)
+                                    } else {
+                                        word
+                                    }
+                                } else if $idx == DIGIT_WORD_IDX0 {
                                     _mm512_or_epi32(
                                         _mm512_set1_epi32(this.message.message[$idx] as _),
                                         lane_id_0_or_value_v,
@@ -242,12 +421,11 @@ impl crate::solver::Solver for SingleBlockSolver {
                             fetch_msg!(15),
                         ];
 
-                        let mut state =
-                            core::array::from_fn(|i| _mm512_set1_epi32(partial_state[i] as _));
+                        let mut state = prefix_set_state;
 
                         // do 16-way SHA-256 without feedback so as not to force the compiler to save 8 registers
                         // we already have them in scalar form, this allows more registers to be reused in the next iteration
-                        crate::sha256::avx512::multiway_arx::<DIGIT_WORD_IDX0>(
+                        crate::sha256::avx512::multiway_arx::<HOTSTART_ROUND>(
                             &mut state,
                             &mut blocks,
                         );
@@ -348,18 +526,21 @@ impl crate::solver::Solver for SingleBlockSolver {
                                     inner_key_buf.as_ptr().cast::<u32>().read();
                                 this.message.message[DIGIT_WORD_IDX0 + 2] =
                                     inner_key_buf.as_ptr().add(4).cast::<u32>().read();
+                            } else {
+                                this.message.message[DIGIT_WORD_IDX0] =
+                                    _mm_cvtsi128_si32(inner_key_words) as u32;
+                                this.message.message[DIGIT_WORD_IDX0 + 1] =
+                                    _mm_extract_epi32::<1>(inner_key_words) as u32;
+                                this.message.message[DIGIT_WORD_IDX0 + 2] =
+                                    _mm_extract_epi32::<2>(inner_key_words) as u32;
                             }
 
-                            // stamp the lane ID back onto the message
-                            {
-                                let message_bytes = decompose_blocks_mut(&mut this.message.message);
-                                *message_bytes.get_unchecked_mut(
-                                    *SWAP_DWORD_BYTE_ORDER.get_unchecked(this.message.digit_index),
-                                ) = (nonce_prefix / 10) as u8 + b'0';
-                                *message_bytes.get_unchecked_mut(
-                                    *SWAP_DWORD_BYTE_ORDER
-                                        .get_unchecked(this.message.digit_index + 1),
-                                ) = (nonce_prefix % 10) as u8 + b'0';
+                            // stamp the lane ID of the winning lane back onto the message
+                            this.message.message[DIGIT_WORD_IDX0] |=
+                                extract_lane_epi32(lane_id_0_or_value_v, success_lane_idx);
+                            if DIGIT_WORD_IDX1_INCREMENT {
+                                this.message.message[DIGIT_WORD_IDX0 + 1] |=
+                                    extract_lane_epi32(lane_id_1_or_value, success_lane_idx);
                             }
 
                             let mut decimal_inner_key = next_inner_key as u64 - 1;
@@ -371,11 +552,6 @@ impl crate::solver::Solver for SingleBlockSolver {
                                     key_octal /= 8;
                                     decimal_inner_key += output as u64 * m;
                                 }
-                                let mut message_be = [0u8; 64];
-                                for i in 0..16 {
-                                    message_be[i * 4..][..4]
-                                        .copy_from_slice(&this.message.message[i].to_be_bytes());
-                                }
                             }
 
                             // the nonce is the 7 digits in the message, plus the first two digits recomputed from the lane index
@@ -394,32 +570,12 @@ impl crate::solver::Solver for SingleBlockSolver {
                                 &mut inner_key_buf,
                                 next_inner_key,
                             );
-                        } else if MUTATION_TYPE == MUTATION_TYPE_UNALIGNED_OCTAL {
-                            let message_bytes = decompose_blocks_mut(&mut this.message.message);
-                            let mut key_copy = next_inner_key;
-
-                            for i in (0..7).rev() {
-                                let output = key_copy % 8;
-                                key_copy /= 8;
-                                *message_bytes.get_unchecked_mut(
-                                    *SWAP_DWORD_BYTE_ORDER
-                                        .get_unchecked(this.message.digit_index + i + 2),
-                                ) = output as u8 + b'1';
-                            }
-                        } else {
-                            let message_bytes = decompose_blocks_mut(&mut this.message.message);
-                            let mut key_copy = next_inner_key;
-
-                            for i in (0..7).rev() {
-                                let output = key_copy % 10;
-                                key_copy /= 10;
-                                *message_bytes.get_unchecked_mut(
-                                    *SWAP_DWORD_BYTE_ORDER
-                                        .get_unchecked(this.message.digit_index + i + 2),
-                                ) = output as u8 + b'0';
-                            }
                         }
                     }
+
+                    if max_iterations < inner_iteration_end {
+                        return None;
+                    }
                 }
             }
 
@@ -427,57 +583,72 @@ impl crate::solver::Solver for SingleBlockSolver {
             None
         }
 
+        // the lane ID digits straddle two words only at byte offset 3, and the aligned kernels only exist at byte offset 2,
+        // so key on the byte offset first to only instantiate the combinations that can occur
         macro_rules! dispatch {
-            ($idx0:literal, $idx1_inc:literal) => {
-                if self.message.digit_index % 4 == 2 {
+            ($idx0:literal) => {
+                if !const { is_supported_lane_position($idx0) } {
+                    // not instantiated at all when the lane position is compiled out
+                    None
+                } else if self.message.digit_index % 4 == 2 {
                     // if we have to much search space it doesn't matter
                     // use the octal kernel
                     if self.message.no_trailing_zeros
                         || self.message.approx_working_set_count.get() >= 100
                     {
-                        solve_inner::<$idx0, $idx1_inc, TYPE, MUTATION_TYPE_ALIGNED_OCTAL>(
+                        solve_inner::<$idx0, false, TYPE, MUTATION_TYPE_ALIGNED_OCTAL, { $idx0 + 1 }>(
                             self, target, mask,
                         )
                     } else {
-                        solve_inner::<$idx0, $idx1_inc, TYPE, MUTATION_TYPE_ALIGNED>(
+                        solve_inner::<$idx0, false, TYPE, MUTATION_TYPE_ALIGNED, { $idx0 + 1 }>(
+                            self, target, mask,
+                        )
+                    }
+                } else if self.message.digit_index % 4 == 3 {
+                    debug_assert_ne!(lane_id_0_word_idx, lane_id_1_word_idx);
+                    if self.message.no_trailing_zeros {
+                        solve_inner::<$idx0, true, TYPE, MUTATION_TYPE_UNALIGNED_OCTAL, { $idx0 + 1 }>(
+                            self, target, mask,
+                        )
+                    } else {
+                        solve_inner::<$idx0, true, TYPE, MUTATION_TYPE_UNALIGNED, { $idx0 + 1 }>(
                             self, target, mask,
                         )
                     }
-                } else if self.message.no_trailing_zeros {
-                    solve_inner::<$idx0, $idx1_inc, TYPE, MUTATION_TYPE_UNALIGNED_OCTAL>(
-                        self, target, mask,
-                    )
-                } else {
-                    solve_inner::<$idx0, $idx1_inc, TYPE, MUTATION_TYPE_UNALIGNED>(
-                        self, target, mask,
-                    )
-                }
-            };
-            ($idx0:literal) => {
-                if lane_id_0_word_idx == lane_id_1_word_idx {
-                    dispatch!($idx0, false)
                 } else {
-                    dispatch!($idx0, true)
+                    debug_assert_eq!(lane_id_0_word_idx, lane_id_1_word_idx);
+                    if self.message.no_trailing_zeros {
+                        solve_inner::<$idx0, false, TYPE, MUTATION_TYPE_UNALIGNED_OCTAL, $idx0>(
+                            self, target, mask,
+                        )
+                    } else {
+                        solve_inner::<$idx0, false, TYPE, MUTATION_TYPE_UNALIGNED, $idx0>(
+                            self, target, mask,
+                        )
+                    }
                 }
             };
         }
 
-        let nonce = match lane_id_0_word_idx {
-            0 => dispatch!(0),
-            1 => dispatch!(1),
-            2 => dispatch!(2),
-            3 => dispatch!(3),
-            4 => dispatch!(4),
-            5 => dispatch!(5),
-            6 => dispatch!(6),
-            7 => dispatch!(7),
-            8 => dispatch!(8),
-            9 => dispatch!(9),
-            10 => dispatch!(10),
-            11 => dispatch!(11),
-            12 => dispatch!(12),
-            13 => dispatch!(13),
-            _ => unsafe { core::hint::unreachable_unchecked() },
+        // SAFETY: with multiversion the constructors assert the CPU supports the kernels
+        let nonce = unsafe {
+            match lane_id_0_word_idx {
+                0 => dispatch!(0),
+                1 => dispatch!(1),
+                2 => dispatch!(2),
+                3 => dispatch!(3),
+                4 => dispatch!(4),
+                5 => dispatch!(5),
+                6 => dispatch!(6),
+                7 => dispatch!(7),
+                8 => dispatch!(8),
+                9 => dispatch!(9),
+                10 => dispatch!(10),
+                11 => dispatch!(11),
+                12 => dispatch!(12),
+                13 => dispatch!(13),
+                _ => core::hint::unreachable_unchecked(),
+            }
         }?;
 
         Some(nonce + self.message.nonce_addend)
@@ -508,6 +679,7 @@ pub struct DoubleBlockSolver {
 
 impl From<super::safe::DoubleBlockSolver> for DoubleBlockSolver {
     fn from(solver: super::safe::DoubleBlockSolver) -> Self {
+        assert_cpu_supported();
         Self {
             message: solver.message,
             attempted_nonces: solver.attempted_nonces,
@@ -518,6 +690,7 @@ impl From<super::safe::DoubleBlockSolver> for DoubleBlockSolver {
 
 impl From<DoubleBlockMessage> for DoubleBlockSolver {
     fn from(message: DoubleBlockMessage) -> Self {
+        assert_cpu_supported();
         Self {
             message,
             attempted_nonces: 0,
@@ -539,6 +712,8 @@ impl DoubleBlockSolver {
 }
 
 impl crate::solver::Solver for DoubleBlockSolver {
+    // with multiversion this is inlined into the runtime dispatched entry point to get its target features
+    #[cfg_attr(feature = "multiversion", inline(always))]
     fn solve<const TYPE: u8>(&mut self, target: u64, mask: u64) -> Option<(u64, [u32; 8])> {
         if !is_supported_lane_position(DoubleBlockMessage::DIGIT_IDX as usize / 4) {
             return None;
@@ -567,6 +742,13 @@ impl crate::solver::Solver for DoubleBlockSolver {
         terminal_message_schedule[14] = ((self.message.message_length * 8) >> 32) as u32;
         terminal_message_schedule[15] = (self.message.message_length * 8) as u32;
         crate::sha256::do_message_schedule_k_w(&mut terminal_message_schedule);
+        // no terminal round can be precomputed even though the schedule is fixed:
+        // the terminal state is the first block's output, which differs per lane, and round 0 already reads all of it.
+        // the H register of the first block is only read by the first terminal round, as a plain addend of T1,
+        // so its feedback is folded into that round's constant instead of added per lane.
+        // the other registers either pass through Ch/Maj/Σ or are shifted into later rounds, so their feedback stays.
+        terminal_message_schedule[0] =
+            terminal_message_schedule[0].wrapping_add(self.message.prefix_state[7]);
 
         let mut itoa_buf = Align16(*b"1111\x80111");
         // the addend is definitely not zero for double block solver, so we can start at 0
@@ -582,12 +764,31 @@ impl crate::solver::Solver for DoubleBlockSolver {
                     _mm512_or_epi32(lane_id_0_or_value, lane_id_1_or_value),
                 );
 
+                let prefix_set_block: [__m512i; 16] = core::array::from_fn(|i| {
+                    if i == 13 {
+                        lane_index_value_v
+                    } else {
+                        _mm512_set1_epi32(self.message.message[i] as _)
+                    }
+                });
+
+                // the lane ID word is shared by every inner key of the prefix set, so its round is done once here
+                let mut prefix_set_state: [__m512i; 8] =
+                    core::array::from_fn(|i| _mm512_set1_epi32(partial_state[i] as _));
+                crate::sha256::avx512::multiway_arx_partial::<13, 14>(
+                    &mut prefix_set_state,
+                    &prefix_set_block,
+                );
+
+                // only the last 2 words change per inner key, so the rest of their message schedule terms are cached
+                let schedule_invariant =
+                    crate::sha256::avx512::prepare_schedule_invariant::<14>(&prefix_set_block);
+
                 for next_inner_key in 1..=0o10_000_000 {
                     let cum0 = itoa_buf.as_ptr().cast::<u32>().read();
                     let cum1 = itoa_buf.as_ptr().add(4).cast::<u32>().read();
 
-                    let mut state =
-                        core::array::from_fn(|i| _mm512_set1_epi32(partial_state[i] as _));
+                    let mut state = prefix_set_state;
 
                     {
                         let mut blocks = [
@@ -609,10 +810,14 @@ impl crate::solver::Solver for DoubleBlockSolver {
                             _mm512_set1_epi32(cum1 as _),
                         ];
 
-                        crate::sha256::avx512::multiway_arx::<13>(&mut state, &mut blocks);
+                        crate::sha256::avx512::multiway_arx_cached::<14, 14>(
+                            &mut state,
+                            &mut blocks,
+                            &schedule_invariant,
+                        );
 
-                        // we have to do feedback now
-                        state
+                        // we have to do feedback now, except for H which is folded into the terminal schedule
+                        state[..7]
                             .iter_mut()
                             .zip(self.message.prefix_state.iter())
                             .for_each(|(state, prefix_state)| {
@@ -627,7 +832,7 @@ impl crate::solver::Solver for DoubleBlockSolver {
                     #[cfg(feature = "compare-64bit")]
                     let save_b = state[1];
 
-                    crate::sha256::avx512::bcst_multiway_arx::<14>(
+                    crate::sha256::avx512::bcst_multiway_arx::<0>(
                         &mut state,
                         &terminal_message_schedule,
                     );
@@ -701,19 +906,10 @@ impl crate::solver::Solver for DoubleBlockSolver {
 
                         let nonce_prefix = 10 + 16 * prefix_set_index + success_lane_idx;
 
+                        self.message.message[13] =
+                            extract_lane_epi32(lane_index_value_v, success_lane_idx);
                         self.message.message[14] = cum0;
                         self.message.message[15] = cum1;
-                        {
-                            let message_bytes = decompose_blocks_mut(&mut self.message.message);
-                            *message_bytes.get_unchecked_mut(
-                                *SWAP_DWORD_BYTE_ORDER
-                                    .get_unchecked(DoubleBlockMessage::DIGIT_IDX as usize),
-                            ) = (nonce_prefix / 10) as u8 + b'0';
-                            *message_bytes.get_unchecked_mut(
-                                *SWAP_DWORD_BYTE_ORDER
-                                    .get_unchecked(DoubleBlockMessage::DIGIT_IDX as usize + 1),
-                            ) = (nonce_prefix % 10) as u8 + b'0';
-                        }
 
                         // recompute the hash from the beginning
                         // this prevents the compiler from having to compute the final B-H registers alive in tight loops
@@ -777,6 +973,7 @@ pub struct GoAwaySolver {
 
 impl From<super::safe::GoAwaySolver> for GoAwaySolver {
     fn from(solver: super::safe::GoAwaySolver) -> Self {
+        assert_cpu_supported();
         Self {
             challenge: solver.challenge,
             attempted_nonces: solver.attempted_nonces,
@@ -787,6 +984,7 @@ impl From<super::safe::GoAwaySolver> for GoAwaySolver {
 
 impl From<GoAwayMessage> for GoAwaySolver {
     fn from(challenge: GoAwayMessage) -> Self {
+        assert_cpu_supported();
         Self {
             challenge: challenge.challenge,
             attempted_nonces: 0,
@@ -962,10 +1160,387 @@ impl crate::solver::Solver for GoAwaySolver {
     }
 }
 
+/// The number of inner keys every lane of [`MultiChallengeSolver`] can search
+const MULTI_CHALLENGE_KEYSPACE: u32 = 100_000_000;
+
+/// Shuffle a leading '1' and the 8 inner key digits into the words of a message,
+/// 128-bit lane `r` holds the words for a mutating part starting at byte `r` of a word
+static MULTI_CHALLENGE_WINDOW_SHUFFLE: Align64<[u8; 64]> = Align64({
+    let mut table = [0x80; 64];
+    let mut r = 0;
+    while r < 4 {
+        let mut p = r;
+        while p < r + 9 {
+            // the leading '1' is byte 8 of the digits, the inner key digits count down from byte 7
+            table[r * 16 + p / 4 * 4 + 3 - p % 4] = (8 - (p - r)) as u8;
+            p += 1;
+        }
+        r += 1;
+    }
+    table
+});
+
+/// A lane of [`MultiChallengeSolver`] that finished
+#[derive(Debug, Clone, Copy)]
+pub struct MultiChallengeOutcome {
+    /// the lane, which is free again
+    pub lane: usize,
+    /// the nonce and hash if the challenge was solved
+    pub solution: Option<(u64, [u32; 8])>,
+    /// the number of nonces attempted for the challenge
+    pub attempted_nonces: u64,
+}
+
+/// A challenge on a lane of [`MultiChallengeSolver`]
+#[derive(Debug, Clone, Copy, Default)]
+struct MultiChallengeLane {
+    target: u64,
+    mask: u64,
+    nonce_addend: u64,
+    digit_index: usize,
+    /// the number of inner keys the lane may search
+    limit: u32,
+}
+
+/// AVX-512 solver working on up to 16 single block challenges at once, one per lane.
+///
+/// Every lane has its own midstate, message template and target, and all lanes count up the same inner key,
+/// so a batch of low difficulty challenges is solved in about the time of the hardest one instead of one after another.
+/// The targets are compared as a vector, so the challenges of a batch can have different difficulties.
+/// A lane that finishes is freed for the next challenge right away.
+///
+/// The mutating part of a lane is a '1' followed by 8 inner key digits.
+pub struct MultiChallengeSolver {
+    /// the message templates word by word, with the mutating part cleared
+    templates: Align64<[[u32; 16]; 16]>,
+    /// the lane of [`MULTI_CHALLENGE_WINDOW_SHUFFLE`] every lane takes each word from
+    word_index: Align64<[[u32; 16]; 16]>,
+    /// the lanes taking each word from [`MULTI_CHALLENGE_WINDOW_SHUFFLE`]
+    word_mask: [u16; 16],
+    /// the midstates register by register
+    prefix_states: Align64<[[u32; 16]; 8]>,
+    /// the top 32 bits of the target and mask of every lane
+    targets: Align64<[u32; 16]>,
+    masks: Align64<[u32; 16]>,
+    /// the inner keys every lane has left
+    remaining: Align64<[u32; 16]>,
+    lanes: [MultiChallengeLane; 16],
+    active: u16,
+    next_inner_key: u32,
+}
+
+impl Default for MultiChallengeSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiChallengeSolver {
+    /// Create a solver with every lane free
+    pub fn new() -> Self {
+        assert_cpu_supported();
+        Self {
+            templates: Align64([[0; 16]; 16]),
+            word_index: Align64([[0; 16]; 16]),
+            word_mask: [0; 16],
+            prefix_states: Align64([[0; 16]; 8]),
+            targets: Align64([0; 16]),
+            masks: Align64([0; 16]),
+            remaining: Align64([0; 16]),
+            lanes: [MultiChallengeLane::default(); 16],
+            active: 0,
+            next_inner_key: 0,
+        }
+    }
+
+    /// Whether every lane is taken
+    pub fn is_full(&self) -> bool {
+        self.active == u16::MAX
+    }
+
+    /// Whether every lane is free
+    pub fn is_empty(&self) -> bool {
+        self.active == 0
+    }
+
+    /// Put a challenge on a free lane, searching at most `limit` nonces
+    ///
+    /// Returns the lane, or None if every lane is taken or the message rejects nonces with trailing zeros.
+    pub fn push(
+        &mut self,
+        message: SingleBlockMessage,
+        target: u64,
+        mask: u64,
+        limit: u64,
+    ) -> Option<usize> {
+        if self.is_full() || message.no_trailing_zeros || limit == 0 {
+            return None;
+        }
+        let lane = (!self.active).trailing_zeros() as usize;
+
+        let mut template = *message.message;
+        {
+            let message_bytes = decompose_blocks_mut(&mut template);
+            for i in message.digit_index..message.digit_index + 9 {
+                message_bytes[SWAP_DWORD_BYTE_ORDER[i]] = 0;
+            }
+        }
+        let first_word = message.digit_index / 4;
+        let window = message.digit_index % 4;
+        for (i, &word) in template.iter().enumerate() {
+            self.templates[i][lane] = word;
+            if (first_word..first_word + 3).contains(&i) {
+                self.word_index[i][lane] = (window * 4 + i - first_word) as u32;
+                self.word_mask[i] |= 1 << lane;
+            } else {
+                self.word_mask[i] &= !(1 << lane);
+            }
+        }
+        for i in 0..8 {
+            self.prefix_states[i][lane] = message.prefix_state[i];
+        }
+
+        let target = target & mask;
+        self.targets[lane] = (target >> 32) as u32;
+        self.masks[lane] = (mask >> 32) as u32;
+        let limit = limit.min(MULTI_CHALLENGE_KEYSPACE as u64) as u32;
+        self.remaining[lane] = limit;
+        self.lanes[lane] = MultiChallengeLane {
+            target,
+            mask,
+            nonce_addend: message.nonce_addend,
+            digit_index: message.digit_index,
+            limit,
+        };
+        self.active |= 1 << lane;
+
+        Some(lane)
+    }
+
+    /// The message of a lane with the mutating part filled in for an inner key
+    fn lane_message(&self, lane: usize, inner_key: u32) -> [u32; 16] {
+        let mut message = core::array::from_fn(|i| self.templates[i][lane]);
+        let digit_index = self.lanes[lane].digit_index;
+        let message_bytes = decompose_blocks_mut(&mut message);
+        message_bytes[SWAP_DWORD_BYTE_ORDER[digit_index]] = b'1';
+        let mut key = inner_key;
+        for i in (1..9).rev() {
+            message_bytes[SWAP_DWORD_BYTE_ORDER[digit_index + i]] = (key % 10) as u8 + b'0';
+            key /= 10;
+        }
+        message
+    }
+
+    /// Search until a lane finishes, which frees the lane
+    ///
+    /// Returns None if every lane is free.
+    pub fn solve_next<const TYPE: u8>(&mut self) -> Option<MultiChallengeOutcome> {
+        // SAFETY: with multiversion the constructor asserts the CPU supports the kernels
+        #[allow(unused_unsafe)]
+        unsafe {
+            self.solve_next_inner::<TYPE>()
+        }
+    }
+
+    #[cfg_attr(
+        feature = "multiversion",
+        target_feature(enable = "avx512f,avx512vl,avx512bw,avx512dq,bmi1,bmi2")
+    )]
+    fn solve_next_inner<const TYPE: u8>(&mut self) -> Option<MultiChallengeOutcome> {
+        if self.is_empty() {
+            return None;
+        }
+
+        unsafe {
+            let window_shuffle: [__m128i; 4] = core::array::from_fn(|r| {
+                _mm_load_si128(MULTI_CHALLENGE_WINDOW_SHUFFLE.as_ptr().add(r * 16).cast())
+            });
+            let targets = _mm512_load_si512(self.targets.as_ptr().cast());
+            let masks = _mm512_load_si512(self.masks.as_ptr().cast());
+            let mut remaining = _mm512_load_si512(self.remaining.as_ptr().cast());
+
+            loop {
+                // checked before searching, as lanes exhausted together are returned by consecutive calls
+                let exhausted =
+                    self.active & _mm512_cmpeq_epi32_mask(remaining, _mm512_setzero_si512());
+                if exhausted != 0 {
+                    _mm512_store_si512(self.remaining.as_mut_ptr().cast(), remaining);
+                    let lane = exhausted.trailing_zeros() as usize;
+                    self.active &= !(1 << lane);
+                    return Some(MultiChallengeOutcome {
+                        lane,
+                        solution: None,
+                        attempted_nonces: self.lanes[lane].limit as u64,
+                    });
+                }
+
+                let inner_key = self.next_inner_key;
+
+                // every lane takes the same digits, shifted to where its mutating part starts
+                let digits = _mm_or_si128(
+                    inner_key_digits_epi8::<false>(inner_key),
+                    _mm_setr_epi8(0, 0, 0, 0, 0, 0, 0, 0, b'1' as _, 0, 0, 0, 0, 0, 0, 0),
+                );
+                // one pshufb per 128-bit lane, a single 512-bit vpshufb would need AVX-512BW
+                let windows = _mm512_inserti32x4::<3>(
+                    _mm512_inserti32x4::<2>(
+                        _mm512_inserti32x4::<1>(
+                            _mm512_castsi128_si512(_mm_shuffle_epi8(digits, window_shuffle[0])),
+                            _mm_shuffle_epi8(digits, window_shuffle[1]),
+                        ),
+                        _mm_shuffle_epi8(digits, window_shuffle[2]),
+                    ),
+                    _mm_shuffle_epi8(digits, window_shuffle[3]),
+                );
+
+                let mut blocks: [__m512i; 16] = core::array::from_fn(|i| {
+                    _mm512_or_epi32(
+                        _mm512_load_si512(self.templates[i].as_ptr().cast()),
+                        _mm512_maskz_permutexvar_epi32(
+                            self.word_mask[i],
+                            _mm512_load_si512(self.word_index[i].as_ptr().cast()),
+                            windows,
+                        ),
+                    )
+                });
+                let mut state: [__m512i; 8] = core::array::from_fn(|i| {
+                    _mm512_load_si512(self.prefix_states[i].as_ptr().cast())
+                });
+                crate::sha256::avx512::multiway_arx::<0>(&mut state, &mut blocks);
+                let a = _mm512_add_epi32(
+                    state[0],
+                    _mm512_load_si512(self.prefix_states[0].as_ptr().cast()),
+                );
+
+                let met_target = self.active
+                    & if TYPE == crate::solver::SOLVE_TYPE_GT {
+                        _mm512_cmpge_epu32_mask(a, targets)
+                    } else if TYPE == crate::solver::SOLVE_TYPE_LT {
+                        _mm512_cmple_epu32_mask(a, targets)
+                    } else {
+                        _mm512_cmpeq_epu32_mask(_mm512_and_si512(a, masks), targets)
+                    };
+
+                self.next_inner_key = (inner_key + 1) % MULTI_CHALLENGE_KEYSPACE;
+                remaining =
+                    _mm512_mask_sub_epi32(remaining, self.active, remaining, _mm512_set1_epi32(1));
+
+                let mut candidates = met_target;
+                while candidates != 0 {
+                    crate::unlikely();
+                    let lane = candidates.trailing_zeros() as usize;
+                    candidates &= candidates - 1;
+
+                    // the top 32 bits are compared inclusively, so every lane's 64 bit target is exact after checking the candidate in full
+                    let mut hash = self.prefix_states.map(|register| register[lane]);
+                    crate::sha256::digest_block(&mut hash, &self.lane_message(lane, inner_key));
+                    let value = (hash[0] as u64) << 32 | hash[1] as u64;
+                    let MultiChallengeLane {
+                        target,
+                        mask,
+                        nonce_addend,
+                        limit,
+                        ..
+                    } = self.lanes[lane];
+                    let valid = if TYPE == crate::solver::SOLVE_TYPE_GT {
+                        value > target
+                    } else if TYPE == crate::solver::SOLVE_TYPE_LT {
+                        value < target
+                    } else {
+                        value & mask == target
+                    };
+                    if valid {
+                        _mm512_store_si512(self.remaining.as_mut_ptr().cast(), remaining);
+                        self.active &= !(1 << lane);
+                        let nonce =
+                            MULTI_CHALLENGE_KEYSPACE as u64 + inner_key as u64 + nonce_addend;
+                        return Some(MultiChallengeOutcome {
+                            lane,
+                            solution: Some((nonce, hash)),
+                            attempted_nonces: (limit - self.remaining[lane]) as u64,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_inner_key_digits() {
+        let digits = |v: __m128i| {
+            let mut bytes = [0u8; 16];
+            unsafe { _mm_storeu_si128(bytes.as_mut_ptr().cast(), v) };
+            bytes[..7].iter().rev().copied().collect::<Vec<u8>>()
+        };
+        for key in (0..10_000_000).step_by(9_973).chain([9_999_999]) {
+            assert_eq!(
+                digits(inner_key_digits_epi8::<false>(key)),
+                format!("{:07}", key).into_bytes()
+            );
+        }
+        for key in (0..0o10_000_000).step_by(7_919).chain([0o7_777_777]) {
+            let expected: Vec<u8> = format!("{:07o}", key).bytes().map(|b| b + 1).collect();
+            assert_eq!(digits(inner_key_digits_epi8::<true>(key)), expected);
+        }
+    }
+
+    #[test]
+    fn test_multi_challenge() {
+        use sha2::Digest;
+
+        // every challenge gets its own target and mask
+        fn check<const TYPE: u8>(target: impl Fn(usize) -> u64, mask: impl Fn(usize) -> u64) {
+            // prefixes that need a double block are not packed
+            let messages: Vec<(Vec<u8>, SingleBlockMessage)> = (0..40u8)
+                .map(|i| vec![b'a' + i % 26; i as usize * 3])
+                .filter_map(|prefix| SingleBlockMessage::new(&prefix, 0).map(|m| (prefix, m)))
+                .collect();
+            assert!(messages.len() > 16);
+            let mut solver = MultiChallengeSolver::new();
+            let mut lanes = [0; 16];
+            let mut pending = messages.iter().enumerate();
+            let mut solved = 0;
+            loop {
+                while !solver.is_full() {
+                    let Some((index, (_, message))) = pending.next() else {
+                        break;
+                    };
+                    let lane = solver.push(message.clone(), target(index), mask(index), u64::MAX);
+                    lanes[lane.unwrap()] = index;
+                }
+                let Some(outcome) = solver.solve_next::<TYPE>() else {
+                    break;
+                };
+                let (nonce, hash) = outcome.solution.expect("solver failed");
+                assert!(outcome.attempted_nonces > 0);
+
+                let prefix = &messages[lanes[outcome.lane]].0;
+                let expected =
+                    sha2::Sha256::digest([&prefix[..], nonce.to_string().as_bytes()].concat());
+                let hash_bytes: Vec<u8> = hash.iter().flat_map(|w| w.to_be_bytes()).collect();
+                assert_eq!(&expected[..], &hash_bytes[..]);
+                let value = u64::from_be_bytes(hash_bytes[..8].try_into().unwrap());
+                let (target, mask) = (target(lanes[outcome.lane]), mask(lanes[outcome.lane]));
+                match TYPE {
+                    crate::solver::SOLVE_TYPE_GT => assert!(value > target),
+                    crate::solver::SOLVE_TYPE_LT => assert!(value < target),
+                    _ => assert_eq!(value & mask, target & mask),
+                }
+                solved += 1;
+            }
+            assert_eq!(solved, messages.len());
+        }
+
+        check::<{ crate::solver::SOLVE_TYPE_GT }>(|i| u64::MAX - (1 << (48 + i % 8)), |_| !0);
+        check::<{ crate::solver::SOLVE_TYPE_LT }>(|i| 1 << (48 + i % 8), |_| !0);
+        check::<{ crate::solver::SOLVE_TYPE_MASK }>(|i| (i as u64) << 52, |i| !0 << (52 - i % 8));
+    }
+
     #[test]
     fn test_solve_decimal() {
         crate::solver::tests::test_decimal_validator::<DecimalSolver, _>(|prefix, search_space| {
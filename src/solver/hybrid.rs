@@ -0,0 +1,266 @@
+use crate::{PREFIX_OFFSET_TO_LANE_POSITION, is_supported_lane_position, message::GoAwayMessage};
+use core::arch::x86_64::*;
+
+/// the number of SHA-NI lanes hashed alongside every 16-lane AVX-512 batch
+const SHA_NI_LANES: usize = 2;
+
+/// the bit of the high word that moves a nonce into the SHA-NI half of the search space
+const SHA_NI_HIGH_WORD_BIT: u32 = 1 << 31;
+
+/// Hybrid AVX-512 and SHA-NI go-away solver.
+///
+/// Current implementation: 16 way SIMD with a 2 lane SHA-NI stream interleaved into every iteration,
+/// the SHA-NI rounds run on ports the AVX-512 rounds leave idle.
+/// The SHA-NI stream searches nonces with the top bit set, the AVX-512 stream the rest.
+pub struct GoAwaySolver {
+    challenge: [u32; 8],
+    attempted_nonces: u64,
+    limit: u64,
+}
+
+impl From<GoAwayMessage> for GoAwaySolver {
+    fn from(challenge: GoAwayMessage) -> Self {
+        Self {
+            challenge: challenge.challenge,
+            attempted_nonces: 0,
+            limit: u64::MAX,
+        }
+    }
+}
+
+impl GoAwaySolver {
+    const MSG_LEN: u32 = 10 * 4 * 8;
+
+    /// Set the limit.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+
+    /// Get the attempted nonces.
+    pub fn get_attempted_nonces(&self) -> u64 {
+        self.attempted_nonces
+    }
+}
+
+impl crate::solver::Solver for GoAwaySolver {
+    fn solve_nonce_only<const TYPE: u8>(&mut self, target: u64, mask: u64) -> Option<u64> {
+        unsafe {
+            let lane_id_v = _mm512_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+
+            if !is_supported_lane_position(PREFIX_OFFSET_TO_LANE_POSITION[0]) {
+                return None;
+            }
+
+            let target = target & mask;
+
+            let mut prefix_state = crate::Align16(crate::sha256::IV);
+            crate::sha256::ingest_message_prefix(&mut prefix_state, self.challenge);
+            let prepared_state = crate::sha256::sha_ni::prepare_state(&prefix_state);
+
+            let feedback_ab = {
+                let lows = _mm_cvtsi64x_si128(
+                    ((crate::sha256::IV[0] as u64) << 32 | crate::sha256::IV[1] as u64) as _,
+                );
+
+                _mm_shuffle_epi32(lows, 0b01001010)
+            };
+
+            struct LaneIdPlucker;
+            impl crate::sha256::sha_ni::Plucker for LaneIdPlucker {
+                #[inline(always)]
+                fn pluck_qword2(&mut self, lane: usize, w: &mut __m128i) {
+                    *w = unsafe { _mm_or_si128(*w, _mm_setr_epi32(0, lane as _, 0, 0)) };
+                }
+            }
+
+            // the SHA-NI stream always has A and B at hand, but compares as many bits as the AVX-512 stream
+            // so a nonce is accepted the same way regardless of which stream found it
+            let sha_ni_cmp_fn = |x: u64, y: u64| {
+                #[cfg(not(feature = "compare-64bit"))]
+                let (x, y, mask) = (x >> 32, y >> 32, mask >> 32);
+                if TYPE == crate::solver::SOLVE_TYPE_GT {
+                    x > y
+                } else if TYPE == crate::solver::SOLVE_TYPE_LT {
+                    x < y
+                } else {
+                    x & mask == y & mask
+                }
+            };
+
+            let high_limit = (self.limit >> 32) as u32;
+
+            for high_word in 0..=high_limit.min(SHA_NI_HIGH_WORD_BIT - 1) {
+                let mut partial_state = *prefix_state;
+                crate::sha256::sha2_arx::<8>(&mut partial_state, &[high_word]);
+
+                let mut sha_ni_msg = crate::Align16([0; 16]);
+                sha_ni_msg[0..8].copy_from_slice(&self.challenge);
+                sha_ni_msg[8] = high_word | SHA_NI_HIGH_WORD_BIT;
+                sha_ni_msg[10] = u32::from_be_bytes([0x80, 0, 0, 0]);
+                sha_ni_msg[15] = Self::MSG_LEN as _;
+
+                for low_word in (0..=u32::MAX).step_by(16) {
+                    // the SHA-NI stream is issued first so its rounds overlap with the AVX-512 rounds
+                    let sha_ni_low_word = low_word / 16 * SHA_NI_LANES as u32;
+                    sha_ni_msg[9] = sha_ni_low_word;
+                    let mut sha_ni_states = [prepared_state; SHA_NI_LANES];
+                    crate::sha256::sha_ni::multiway_arx_abef_cdgh::<2, SHA_NI_LANES, _>(
+                        sha_ni_states.each_mut(),
+                        &sha_ni_msg,
+                        LaneIdPlucker,
+                    );
+
+                    let mut state =
+                        core::array::from_fn(|i| _mm512_set1_epi32(partial_state[i] as _));
+
+                    let mut msg = [
+                        _mm512_set1_epi32(self.challenge[0] as _),
+                        _mm512_set1_epi32(self.challenge[1] as _),
+                        _mm512_set1_epi32(self.challenge[2] as _),
+                        _mm512_set1_epi32(self.challenge[3] as _),
+                        _mm512_set1_epi32(self.challenge[4] as _),
+                        _mm512_set1_epi32(self.challenge[5] as _),
+                        _mm512_set1_epi32(self.challenge[6] as _),
+                        _mm512_set1_epi32(self.challenge[7] as _),
+                        _mm512_set1_epi32(high_word as _),
+                        _mm512_or_epi32(_mm512_set1_epi32(low_word as _), lane_id_v),
+                        _mm512_set1_epi32(u32::from_be_bytes([0x80, 0, 0, 0]) as _),
+                        _mm512_setzero_epi32(),
+                        _mm512_setzero_epi32(),
+                        _mm512_setzero_epi32(),
+                        _mm512_setzero_epi32(),
+                        _mm512_set1_epi32(Self::MSG_LEN as _),
+                    ];
+                    crate::sha256::avx512::multiway_arx::<9>(&mut state, &mut msg);
+
+                    state[0] =
+                        _mm512_add_epi32(state[0], _mm512_set1_epi32(crate::sha256::IV[0] as _));
+
+                    #[cfg(feature = "compare-64bit")]
+                    {
+                        state[1] = _mm512_add_epi32(
+                            state[1],
+                            _mm512_set1_epi32(crate::sha256::IV[1] as _),
+                        );
+                    }
+
+                    #[cfg(not(feature = "compare-64bit"))]
+                    let cmp_fn = |x: __m512i, y: __m512i| {
+                        if TYPE == crate::solver::SOLVE_TYPE_GT {
+                            _mm512_cmpgt_epu32_mask(x, y)
+                        } else if TYPE == crate::solver::SOLVE_TYPE_LT {
+                            _mm512_cmplt_epu32_mask(x, y)
+                        } else {
+                            _mm512_cmpeq_epu32_mask(
+                                _mm512_and_si512(x, _mm512_set1_epi32((mask >> 32) as _)),
+                                y,
+                            )
+                        }
+                    };
+
+                    #[cfg(feature = "compare-64bit")]
+                    let cmp64_fn = |x: __m512i, y: __m512i| {
+                        if TYPE == crate::solver::SOLVE_TYPE_GT {
+                            _mm512_cmpgt_epu64_mask(x, y)
+                        } else if TYPE == crate::solver::SOLVE_TYPE_LT {
+                            _mm512_cmplt_epu64_mask(x, y)
+                        } else {
+                            _mm512_cmpeq_epu64_mask(
+                                _mm512_and_si512(x, _mm512_set1_epi64(mask as _)),
+                                y,
+                            )
+                        }
+                    };
+
+                    #[cfg(not(feature = "compare-64bit"))]
+                    let met_target = cmp_fn(state[0], _mm512_set1_epi32((target >> 32) as _));
+
+                    #[cfg(feature = "compare-64bit")]
+                    let (met_target_high, met_target_lo) = {
+                        let result_ab_lo = _mm512_unpacklo_epi32(state[1], state[0]);
+                        let result_ab_hi = _mm512_unpackhi_epi32(state[1], state[0]);
+                        (
+                            cmp64_fn(result_ab_hi, _mm512_set1_epi64(target as _)) as u16,
+                            cmp64_fn(result_ab_lo, _mm512_set1_epi64(target as _)) as u16,
+                        )
+                    };
+
+                    #[cfg(feature = "compare-64bit")]
+                    let met_target_test = met_target_high != 0 || met_target_lo != 0;
+                    #[cfg(not(feature = "compare-64bit"))]
+                    let met_target_test = met_target != 0;
+
+                    self.attempted_nonces += 16 + SHA_NI_LANES as u64;
+
+                    if met_target_test {
+                        crate::unlikely();
+
+                        #[cfg(not(feature = "compare-64bit"))]
+                        let success_lane_idx = _tzcnt_u16(met_target);
+
+                        #[cfg(feature = "compare-64bit")]
+                        let success_lane_idx = super::avx512::INDEX_REMAP_PUNPCKLDQ
+                            [_tzcnt_u16(met_target_high << 8 | met_target_lo) as usize];
+
+                        let final_low_word = low_word | success_lane_idx as u32;
+
+                        return Some((high_word as u64) << 32 | final_low_word as u64);
+                    }
+
+                    if let Some(success_lane_idx) = sha_ni_states.iter().position(|state| {
+                        let ab = _mm_extract_epi64(_mm_add_epi32(state[0], feedback_ab), 1) as u64;
+                        sha_ni_cmp_fn(ab, target)
+                    }) {
+                        crate::unlikely();
+
+                        let final_low_word = sha_ni_low_word | success_lane_idx as u32;
+
+                        return Some(
+                            ((high_word | SHA_NI_HIGH_WORD_BIT) as u64) << 32
+                                | final_low_word as u64,
+                        );
+                    }
+
+                    if self.attempted_nonces >= self.limit {
+                        return None;
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn solve<const TYPE: u8>(&mut self, target: u64, mask: u64) -> Option<(u64, [u32; 8])> {
+        let mut output_msg = [0; 16];
+        let nonce = self.solve_nonce_only::<TYPE>(target, mask)?;
+        output_msg[..8].copy_from_slice(&self.challenge);
+        output_msg[8] = (nonce >> 32) as u32;
+        output_msg[9] = nonce as u32;
+        output_msg[10] = u32::from_be_bytes([0x80, 0, 0, 0]);
+        output_msg[15] = Self::MSG_LEN as _;
+
+        let mut final_sha_state = crate::sha256::IV;
+        crate::sha256::digest_block(&mut final_sha_state, &output_msg);
+
+        Some((nonce, final_sha_state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_goaway() {
+        crate::solver::tests::test_goaway_validator::<GoAwaySolver, _>(|prefix| {
+            GoAwaySolver::from(GoAwayMessage::new(core::array::from_fn(|i| {
+                u32::from_be_bytes([
+                    prefix[i * 4],
+                    prefix[i * 4 + 1],
+                    prefix[i * 4 + 2],
+                    prefix[i * 4 + 3],
+                ])
+            })))
+        });
+    }
+}
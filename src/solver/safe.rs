@@ -47,6 +47,9 @@ impl SingleBlockSolver {
         for i in 0..16 {
             message_be.0[i * 4..i * 4 + 4].copy_from_slice(&self.message.message[i].to_be_bytes());
         }
+        if self.attempted_nonces >= self.limit {
+            return None;
+        }
         let target = target & mask;
 
         for nonzero_digit in 1..=9 {
@@ -88,9 +91,17 @@ impl SingleBlockSolver {
                     }
                     return Some((transformed_key + self.message.nonce_addend, state));
                 }
+
+                self.attempted_nonces += 1;
+
+                if self.attempted_nonces >= self.limit {
+                    return None;
+                }
             }
         }
 
+        crate::unlikely();
+
         None
     }
 }
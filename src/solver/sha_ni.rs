@@ -10,6 +10,19 @@ use crate::{
     message::{DecimalMessage, DoubleBlockMessage, GoAwayMessage, SingleBlockMessage},
 };
 
+/// Panic unless the CPU supports the kernels of this module
+///
+/// With `multiversion` the kernels are compiled for their target features regardless of the build target,
+/// so every solver checks the CPU when it is constructed, before any kernel can run.
+#[inline(always)]
+fn assert_cpu_supported() {
+    #[cfg(feature = "multiversion")]
+    assert!(
+        super::autotune::Backend::ShaNi.is_supported(),
+        "SHA-NI is not supported by the CPU"
+    );
+}
+
 /// SHA-NI decimal nonce single block solver.
 ///
 ///
@@ -24,6 +37,7 @@ pub struct SingleBlockSolver {
 
 impl From<super::safe::SingleBlockSolver> for SingleBlockSolver {
     fn from(solver: super::safe::SingleBlockSolver) -> Self {
+        assert_cpu_supported();
         Self {
             message: solver.message,
             attempted_nonces: solver.attempted_nonces,
@@ -34,6 +48,7 @@ impl From<super::safe::SingleBlockSolver> for SingleBlockSolver {
 
 impl From<SingleBlockMessage> for SingleBlockSolver {
     fn from(message: SingleBlockMessage) -> Self {
+        assert_cpu_supported();
         Self {
             message,
             attempted_nonces: 0,
@@ -89,6 +104,11 @@ impl SingleBlockSolver {
         let lane_id_1_word_idx = (self.message.digit_index + 1) / 4;
 
         #[inline(never)]
+        #[cfg_attr(
+            feature = "multiversion",
+            target_feature(enable = "sha,sse2,ssse3,sse4.1"),
+            allow(unused_unsafe)
+        )]
         fn solve_inner<
             const DIGIT_WORD_IDX0_DIV_4_TIMES_4: usize,
             const DIGIT_WORD_IDX0_DIV_4: usize,
@@ -399,55 +419,68 @@ impl SingleBlockSolver {
             None
         }
 
+        // the lane ID digits straddle two words only at byte offset 3, and the aligned kernel only exists at byte offset 2,
+        // so key on the byte offset first to only instantiate the combinations that can occur
         macro_rules! dispatch {
-            ($idx0_0:literal, $idx0_1:literal, $idx0_2:literal, $lane_id_1_word_idx_inc:literal) => {
-                if self.message.digit_index % 4 == 2 {
+            ($idx0_0:literal, $idx0_1:literal, $idx0_2:literal) => {
+                if !const { is_supported_lane_position($idx0_0 + $idx0_2) } {
+                    // not instantiated at all when the lane position is compiled out
+                    None
+                } else if self.message.digit_index % 4 == 2 {
                     solve_inner::<
                         { $idx0_0 },
                         { $idx0_1 },
                         { $idx0_2 },
-                        { $lane_id_1_word_idx_inc },
+                        false,
                         TYPE,
                         NO_TRAILING_ZEROS,
                         true,
                     >(self, target, mask)
-                } else {
+                } else if self.message.digit_index % 4 == 3 {
+                    debug_assert_ne!(lane_id_0_word_idx, lane_id_1_word_idx);
                     solve_inner::<
                         { $idx0_0 },
                         { $idx0_1 },
                         { $idx0_2 },
-                        { $lane_id_1_word_idx_inc },
+                        true,
                         TYPE,
                         NO_TRAILING_ZEROS,
                         false,
                     >(self, target, mask)
-                }
-            };
-            ($idx0_0:literal, $idx0_1:literal, $idx0_2:literal) => {
-                if lane_id_1_word_idx == lane_id_0_word_idx {
-                    dispatch!($idx0_0, $idx0_1, $idx0_2, false)
                 } else {
-                    dispatch!($idx0_0, $idx0_1, $idx0_2, true)
+                    debug_assert_eq!(lane_id_0_word_idx, lane_id_1_word_idx);
+                    solve_inner::<
+                        { $idx0_0 },
+                        { $idx0_1 },
+                        { $idx0_2 },
+                        false,
+                        TYPE,
+                        NO_TRAILING_ZEROS,
+                        false,
+                    >(self, target, mask)
                 }
             };
         }
 
-        let nonce = match lane_id_0_word_idx {
-            0 => dispatch!(0, 0, 0),
-            1 => dispatch!(0, 0, 1),
-            2 => dispatch!(0, 0, 2),
-            3 => dispatch!(0, 0, 3),
-            4 => dispatch!(4, 1, 0),
-            5 => dispatch!(4, 1, 1),
-            6 => dispatch!(4, 1, 2),
-            7 => dispatch!(4, 1, 3),
-            8 => dispatch!(8, 2, 0),
-            9 => dispatch!(8, 2, 1),
-            10 => dispatch!(8, 2, 2),
-            11 => dispatch!(8, 2, 3),
-            12 => dispatch!(12, 3, 0),
-            13 => dispatch!(12, 3, 1),
-            _ => unsafe { core::hint::unreachable_unchecked() },
+        // SAFETY: with multiversion the constructors assert the CPU supports the kernels
+        let nonce = unsafe {
+            match lane_id_0_word_idx {
+                0 => dispatch!(0, 0, 0),
+                1 => dispatch!(0, 0, 1),
+                2 => dispatch!(0, 0, 2),
+                3 => dispatch!(0, 0, 3),
+                4 => dispatch!(4, 1, 0),
+                5 => dispatch!(4, 1, 1),
+                6 => dispatch!(4, 1, 2),
+                7 => dispatch!(4, 1, 3),
+                8 => dispatch!(8, 2, 0),
+                9 => dispatch!(8, 2, 1),
+                10 => dispatch!(8, 2, 2),
+                11 => dispatch!(8, 2, 3),
+                12 => dispatch!(12, 3, 0),
+                13 => dispatch!(12, 3, 1),
+                _ => core::hint::unreachable_unchecked(),
+            }
         }?;
 
         let mut final_sha_state = self.message.prefix_state;
@@ -470,6 +503,7 @@ pub struct DoubleBlockSolver {
 
 impl From<super::safe::DoubleBlockSolver> for DoubleBlockSolver {
     fn from(solver: super::safe::DoubleBlockSolver) -> Self {
+        assert_cpu_supported();
         Self {
             message: solver.message,
             attempted_nonces: solver.attempted_nonces,
@@ -480,6 +514,7 @@ impl From<super::safe::DoubleBlockSolver> for DoubleBlockSolver {
 
 impl From<DoubleBlockMessage> for DoubleBlockSolver {
     fn from(message: DoubleBlockMessage) -> Self {
+        assert_cpu_supported();
         Self {
             message,
             attempted_nonces: 0,
@@ -501,6 +536,8 @@ impl DoubleBlockSolver {
 }
 
 impl crate::solver::Solver for DoubleBlockSolver {
+    // with multiversion this is inlined into the runtime dispatched entry point to get its target features
+    #[cfg_attr(feature = "multiversion", inline(always))]
     fn solve<const TYPE: u8>(&mut self, target: u64, mask: u64) -> Option<(u64, [u32; 8])> {
         if !is_supported_lane_position(DoubleBlockMessage::DIGIT_IDX as usize / 4) {
             return None;
@@ -702,6 +739,7 @@ pub struct GoAwaySolver {
 
 impl From<super::safe::GoAwaySolver> for GoAwaySolver {
     fn from(solver: super::safe::GoAwaySolver) -> Self {
+        assert_cpu_supported();
         Self {
             challenge: solver.challenge,
             attempted_nonces: solver.attempted_nonces,
@@ -712,6 +750,7 @@ impl From<super::safe::GoAwaySolver> for GoAwaySolver {
 
 impl From<GoAwayMessage> for GoAwaySolver {
     fn from(challenge: GoAwayMessage) -> Self {
+        assert_cpu_supported();
         Self {
             challenge: challenge.challenge,
             attempted_nonces: 0,
@@ -1,3 +1,9 @@
+// with multiversion the vectorized paths are compiled out and only the scalar fallbacks are used
+#![cfg_attr(
+    not(any(target_feature = "avx512f", target_feature = "avx2")),
+    allow(dead_code)
+)]
+
 use core::num::NonZeroU32;
 
 use crate::Align16;
@@ -0,0 +1,205 @@
+//! CPU frequency and package temperature sampling for benchmarks, read from Linux cpufreq and hwmon.
+//!
+//! On other platforms, or when sysfs is not readable, no sensors are found and no report is produced.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// how often sensors are read while a benchmark runs
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// hwmon drivers whose `temp1` is the CPU package (or Tctl) temperature
+const CPU_HWMON_NAMES: &[&str] = &["coretemp", "k10temp", "zenpower", "cpu_thermal"];
+
+fn read_number(path: &Path) -> Option<i64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// sysfs files describing the CPU
+struct Sensors {
+    /// `scaling_cur_freq` of every CPU, in kHz
+    frequencies: Vec<PathBuf>,
+    /// `base_frequency` in kHz, a loaded core running below it is being throttled
+    base_khz: Option<i64>,
+    /// package temperature in millidegrees Celsius and its high threshold
+    temperature: Option<(PathBuf, Option<i64>)>,
+    /// `package_throttle_count` of every CPU
+    throttle_counters: Vec<PathBuf>,
+}
+
+impl Sensors {
+    fn discover() -> Option<Self> {
+        let mut frequencies = Vec::new();
+        let mut throttle_counters = Vec::new();
+        let mut base_khz = None;
+        for entry in fs::read_dir("/sys/devices/system/cpu").ok()?.flatten() {
+            let name = entry.file_name();
+            let Some(index) = name.to_str().and_then(|name| name.strip_prefix("cpu")) else {
+                continue;
+            };
+            if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            let cpu = entry.path();
+            let frequency = cpu.join("cpufreq/scaling_cur_freq");
+            if frequency.exists() {
+                frequencies.push(frequency);
+            }
+            let counter = cpu.join("thermal_throttle/package_throttle_count");
+            if counter.exists() {
+                throttle_counters.push(counter);
+            }
+            base_khz = base_khz.or_else(|| read_number(&cpu.join("cpufreq/base_frequency")));
+        }
+
+        let temperature = Self::package_temperature();
+        (!frequencies.is_empty() || temperature.is_some()).then_some(Self {
+            frequencies,
+            base_khz,
+            temperature,
+            throttle_counters,
+        })
+    }
+
+    fn package_temperature() -> Option<(PathBuf, Option<i64>)> {
+        for hwmon in fs::read_dir("/sys/class/hwmon").ok()?.flatten() {
+            let dir = hwmon.path();
+            let Ok(name) = fs::read_to_string(dir.join("name")) else {
+                continue;
+            };
+            let input = dir.join("temp1_input");
+            if CPU_HWMON_NAMES.contains(&name.trim()) && input.exists() {
+                let limit = read_number(&dir.join("temp1_max"))
+                    .filter(|max| *max > 0)
+                    .or_else(|| read_number(&dir.join("temp1_crit")));
+                return Some((input, limit));
+            }
+        }
+        None
+    }
+
+    fn throttle_count(&self) -> i64 {
+        self.throttle_counters
+            .iter()
+            .filter_map(|counter| read_number(counter))
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// CPU conditions observed during a benchmark
+pub struct Report {
+    /// mean frequency of the fastest core in MHz, approximating the core running the benchmark
+    pub mean_mhz: Option<u64>,
+    /// lowest frequency of the fastest core in MHz
+    pub min_mhz: Option<u64>,
+    /// highest package temperature in degrees Celsius
+    pub peak_celsius: Option<f64>,
+    /// the throttle counters increased, the package reached its high temperature threshold,
+    /// or the fastest core dropped below the base frequency
+    pub throttled: bool,
+}
+
+#[derive(Default)]
+struct Samples {
+    count: u64,
+    sum_khz: i64,
+    min_khz: Option<i64>,
+    peak_millicelsius: Option<i64>,
+}
+
+impl Samples {
+    fn sample(&mut self, sensors: &Sensors) {
+        if let Some(khz) = sensors
+            .frequencies
+            .iter()
+            .filter_map(|frequency| read_number(frequency))
+            .max()
+        {
+            self.count += 1;
+            self.sum_khz += khz;
+            self.min_khz = Some(self.min_khz.map_or(khz, |min| min.min(khz)));
+        }
+        if let Some((input, _)) = &sensors.temperature
+            && let Some(millicelsius) = read_number(input)
+        {
+            self.peak_millicelsius = Some(
+                self.peak_millicelsius
+                    .map_or(millicelsius, |peak| peak.max(millicelsius)),
+            );
+        }
+    }
+
+    fn report(&self, sensors: &Sensors, throttle_events: i64) -> Report {
+        let below_base = sensors
+            .base_khz
+            .zip(self.min_khz)
+            .is_some_and(|(base, min)| min < base);
+        let too_hot = sensors
+            .temperature
+            .as_ref()
+            .and_then(|(_, limit)| *limit)
+            .zip(self.peak_millicelsius)
+            .is_some_and(|(limit, peak)| peak >= limit);
+        Report {
+            mean_mhz: (self.count > 0).then(|| (self.sum_khz / self.count as i64 / 1000) as u64),
+            min_mhz: self.min_khz.map(|khz| (khz / 1000) as u64),
+            peak_celsius: self.peak_millicelsius.map(|m| m as f64 / 1000.0),
+            throttled: throttle_events > 0 || below_base || too_hot,
+        }
+    }
+}
+
+/// Samples CPU sensors on a background thread until finished
+pub struct Sampler {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Report>>,
+}
+
+impl Sampler {
+    /// starts sampling, does nothing if no sensors are available
+    pub fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = Sensors::discover().map(|sensors| {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let throttle_count = sensors.throttle_count();
+                let mut samples = Samples::default();
+                // the first sample waits so the benchmark is already loading a core
+                loop {
+                    std::thread::park_timeout(SAMPLE_INTERVAL);
+                    samples.sample(&sensors);
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                }
+                samples.report(&sensors, sensors.throttle_count() - throttle_count)
+            })
+        });
+        Self { stop, handle }
+    }
+
+    /// takes a final sample and returns the report
+    pub fn finish(mut self) -> Option<Report> {
+        let handle = self.handle.take()?;
+        self.stop.store(true, Ordering::Relaxed);
+        handle.thread().unpark();
+        handle.join().ok()
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = &self.handle {
+            handle.thread().unpark();
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Hash rate capping for emulating slower client hardware.
+//!
+//! The solvers cannot be paused in the middle of a search, so the cap is applied by duty cycling:
+//! every solve runs at full speed and is followed by an idle period long enough
+//! for the attempted nonces over the whole period to match the capped hash rate.
+use std::time::{Duration, Instant};
+
+/// A cap on the effective hash rate of a solver
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HashrateCap {
+    hashes_per_second: f64,
+}
+
+impl HashrateCap {
+    /// Create a cap, or None if the hash rate is not positive and finite
+    pub fn new(hashes_per_second: f64) -> Option<Self> {
+        (hashes_per_second.is_finite() && hashes_per_second > 0.0)
+            .then_some(Self { hashes_per_second })
+    }
+
+    /// The capped hash rate in hashes per second
+    pub fn hashes_per_second(self) -> f64 {
+        self.hashes_per_second
+    }
+
+    /// How long a client at the capped hash rate would take to attempt `attempted_nonces` nonces
+    pub fn duration_for(self, attempted_nonces: u64) -> Duration {
+        Duration::from_secs_f64(attempted_nonces as f64 / self.hashes_per_second)
+    }
+
+    /// The idle time after a solve that attempted `attempted_nonces` nonces in `elapsed`
+    pub fn idle_time(self, attempted_nonces: u64, elapsed: Duration) -> Duration {
+        self.duration_for(attempted_nonces).saturating_sub(elapsed)
+    }
+
+    /// Run a solve returning its result and attempted nonces, then idle until the cap is met
+    pub fn run<T>(self, solve: impl FnOnce() -> (T, u64)) -> (T, u64) {
+        let start = Instant::now();
+        let (result, attempted_nonces) = solve();
+        std::thread::sleep(self.idle_time(attempted_nonces, start.elapsed()));
+        (result, attempted_nonces)
+    }
+}
+
+impl std::str::FromStr for HashrateCap {
+    type Err = &'static str;
+
+    /// Parse a hash rate in hashes per second with an optional `k`, `M` or `G` suffix, such as `2M`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, scale) = match s.as_bytes().last() {
+            Some(b'k' | b'K') => (&s[..s.len() - 1], 1e3),
+            Some(b'M') => (&s[..s.len() - 1], 1e6),
+            Some(b'G') => (&s[..s.len() - 1], 1e9),
+            _ => (s, 1.0),
+        };
+        number
+            .parse::<f64>()
+            .ok()
+            .and_then(|number| Self::new(number * scale))
+            .ok_or(
+                "invalid hash rate, expected a positive number with an optional k, M or G suffix",
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashrate_cap() {
+        assert_eq!(
+            "2M".parse::<HashrateCap>().unwrap().hashes_per_second(),
+            2e6
+        );
+        assert_eq!(
+            "1.5k".parse::<HashrateCap>().unwrap().hashes_per_second(),
+            1.5e3
+        );
+        assert_eq!(
+            "300".parse::<HashrateCap>().unwrap().hashes_per_second(),
+            300.0
+        );
+        assert!("0".parse::<HashrateCap>().is_err());
+        assert!("-1M".parse::<HashrateCap>().is_err());
+        assert!("M".parse::<HashrateCap>().is_err());
+
+        let cap = HashrateCap::new(1e6).unwrap();
+        assert_eq!(cap.duration_for(500_000), Duration::from_millis(500));
+        assert_eq!(
+            cap.idle_time(500_000, Duration::from_millis(100)),
+            Duration::from_millis(400)
+        );
+        assert_eq!(
+            cap.idle_time(500_000, Duration::from_secs(1)),
+            Duration::ZERO
+        );
+
+        let start = Instant::now();
+        let (result, attempted_nonces) = cap.run(|| ("done", 20_000));
+        assert_eq!((result, attempted_nonces), ("done", 20_000));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
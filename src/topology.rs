@@ -0,0 +1,129 @@
+//! CPU topology detection for choosing the default number of solver threads.
+//!
+//! Two AVX-512 SHA-256 streams on the SMT siblings of one core contend for the same execution ports,
+//! so their aggregate hash rate is lower than a single stream per core.
+//! The narrower backends leave enough ports idle for a sibling to make use of them.
+//!
+//! The policy can be overridden with the `POW_BUSTER_SMT` environment variable (`logical` or `physical`).
+use std::{collections::BTreeSet, fs};
+
+use crate::solver::autotune::{self, Backend};
+
+/// How many solver threads to run on a core with simultaneous multithreading (SMT) siblings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtPolicy {
+    /// One thread per logical CPU
+    Logical,
+    /// One thread per physical core
+    Physical,
+}
+
+impl SmtPolicy {
+    /// The name of the policy
+    pub fn name(self) -> &'static str {
+        match self {
+            SmtPolicy::Logical => "logical",
+            SmtPolicy::Physical => "physical",
+        }
+    }
+
+    /// The preferred policy for a backend
+    pub fn for_backend(backend: Backend) -> Self {
+        match backend {
+            #[cfg(all(
+                target_arch = "x86_64",
+                any(target_feature = "avx512f", feature = "multiversion")
+            ))]
+            Backend::Avx512 => SmtPolicy::Physical,
+            _ => SmtPolicy::Logical,
+        }
+    }
+
+    /// The policy set by `POW_BUSTER_SMT`, otherwise the preferred policy of the selected backend
+    pub fn current() -> Self {
+        std::env::var("POW_BUSTER_SMT")
+            .ok()
+            .and_then(|policy| policy.parse().ok())
+            .unwrap_or_else(|| Self::for_backend(autotune::selected()))
+    }
+
+    /// The number of threads to run under this policy
+    pub fn threads(self) -> usize {
+        match self {
+            SmtPolicy::Logical => logical_cpus(),
+            SmtPolicy::Physical => physical_cores().unwrap_or_else(logical_cpus),
+        }
+    }
+}
+
+impl std::str::FromStr for SmtPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "logical" => Ok(SmtPolicy::Logical),
+            "physical" => Ok(SmtPolicy::Physical),
+            _ => Err("invalid SMT policy, expected logical or physical"),
+        }
+    }
+}
+
+/// The number of logical CPUs this process may run on
+pub fn logical_cpus() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// The number of physical cores this process may run on, or None if the topology is unknown
+///
+/// The SMT siblings are read from Linux sysfs, the usable logical CPUs are assumed to be spread evenly across cores.
+pub fn physical_cores() -> Option<usize> {
+    let mut cpus = 0usize;
+    let mut cores = BTreeSet::new();
+    for entry in fs::read_dir("/sys/devices/system/cpu").ok()?.flatten() {
+        let name = entry.file_name();
+        let Some(index) = name.to_str().and_then(|name| name.strip_prefix("cpu")) else {
+            continue;
+        };
+        if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let topology = entry.path().join("topology");
+        let Ok(siblings) = fs::read_to_string(topology.join("core_cpus_list"))
+            .or_else(|_| fs::read_to_string(topology.join("thread_siblings_list")))
+        else {
+            continue;
+        };
+        cpus += 1;
+        cores.insert(siblings.trim().to_string());
+    }
+    if cores.is_empty() {
+        return None;
+    }
+    let threads_per_core = cpus.div_ceil(cores.len());
+    Some(logical_cpus().div_ceil(threads_per_core))
+}
+
+/// The default number of solver threads, see [`SmtPolicy::current`]
+pub fn default_threads() -> usize {
+    SmtPolicy::current().threads()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_counts() {
+        let logical = SmtPolicy::Logical.threads();
+        let physical = SmtPolicy::Physical.threads();
+        assert!(physical >= 1);
+        assert!(physical <= logical);
+        assert_eq!("physical".parse(), Ok(SmtPolicy::Physical));
+        assert_eq!(
+            SmtPolicy::Logical.name().parse::<SmtPolicy>(),
+            Ok(SmtPolicy::Logical)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Live terminal dashboard for the bench and live subcommands.
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Cell, Paragraph, Row, Table},
+};
+
+/// number of recent latencies kept for percentiles
+const WINDOW: usize = 4096;
+
+/// counters of one dashboard row
+pub struct Stats {
+    label: String,
+    started: Option<Instant>,
+    finished: Option<Duration>,
+    solves: u64,
+    errors: u64,
+    attempted_nonces: Option<u64>,
+    solve_time: Duration,
+    latencies: VecDeque<Duration>,
+    http_latencies: VecDeque<Duration>,
+}
+
+fn push_window(window: &mut VecDeque<Duration>, value: Duration) {
+    if window.len() == WINDOW {
+        window.pop_front();
+    }
+    window.push_back(value);
+}
+
+fn percentile(window: &VecDeque<Duration>, q: f64) -> Option<Duration> {
+    if window.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<_> = window.iter().copied().collect();
+    sorted.sort_unstable();
+    Some(sorted[((sorted.len() - 1) as f64 * q).round() as usize])
+}
+
+impl Stats {
+    /// creates a new row
+    pub fn new(label: impl Into<String>) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            label: label.into(),
+            started: None,
+            finished: None,
+            solves: 0,
+            errors: 0,
+            attempted_nonces: None,
+            solve_time: Duration::ZERO,
+            latencies: VecDeque::new(),
+            http_latencies: VecDeque::new(),
+        }))
+    }
+
+    /// marks the row as running
+    pub fn start(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+    }
+
+    /// freezes the elapsed time of the row
+    pub fn finish(&mut self) {
+        self.finished = Some(self.elapsed());
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.finished
+            .or_else(|| self.started.map(|s| s.elapsed()))
+            .unwrap_or_default()
+    }
+
+    /// records a local solve and the nonces it took
+    pub fn record_solve(&mut self, latency: Duration, attempted_nonces: u64) {
+        self.start();
+        self.solves += 1;
+        self.solve_time += latency;
+        *self.attempted_nonces.get_or_insert(0) += attempted_nonces;
+        push_window(&mut self.latencies, latency);
+    }
+
+    /// records an end-to-end attempt against a server and the time spent waiting on HTTP
+    #[cfg_attr(not(feature = "live-throughput-test"), allow(dead_code))]
+    pub fn record_request(&mut self, ok: bool, latency: Duration, http_latency: Duration) {
+        self.start();
+        match ok {
+            true => self.solves += 1,
+            false => self.errors += 1,
+        }
+        push_window(&mut self.latencies, latency);
+        push_window(&mut self.http_latencies, http_latency);
+    }
+
+    fn row(&self) -> Row<'static> {
+        let ms = |d: Option<Duration>| match d {
+            Some(d) => format!("{:.1}", d.as_secs_f64() * 1000.0),
+            None => "-".to_string(),
+        };
+        let elapsed = self.elapsed().as_secs_f64();
+        let rate = |n: u64| match elapsed > 0.0 {
+            true => format!("{:.1}", n as f64 / elapsed),
+            false => "-".to_string(),
+        };
+        let hashrate = match (self.attempted_nonces, self.solve_time.is_zero()) {
+            (Some(attempted), false) => format!(
+                "{:.2}",
+                attempted as f64 / self.solve_time.as_secs_f64() / 1024.0 / 1024.0
+            ),
+            _ => "-".to_string(),
+        };
+        let total = self.solves + self.errors;
+        let error_rate = match total {
+            0 => "-".to_string(),
+            _ => format!("{:.1}%", self.errors as f64 / total as f64 * 100.0),
+        };
+        let style = match (self.started, self.finished) {
+            (None, _) => Style::new().fg(Color::DarkGray),
+            (Some(_), None) => Style::new().fg(Color::Yellow),
+            (Some(_), Some(_)) => Style::new(),
+        };
+        Row::new([
+            Cell::from(self.label.clone()),
+            Cell::from(format!("{:.1}", elapsed)),
+            Cell::from(self.solves.to_string()),
+            Cell::from(rate(self.solves)),
+            Cell::from(hashrate),
+            Cell::from(ms(percentile(&self.latencies, 0.5))),
+            Cell::from(ms(percentile(&self.latencies, 0.9))),
+            Cell::from(ms(percentile(&self.latencies, 0.99))),
+            Cell::from(ms(percentile(&self.http_latencies, 0.5))),
+            Cell::from(ms(percentile(&self.http_latencies, 0.99))),
+            Cell::from(self.errors.to_string()),
+            Cell::from(error_rate),
+        ])
+        .style(style)
+    }
+}
+
+/// redraws `rows` until `done` returns true or the user presses `q`, returns false if the user quit
+pub fn run(
+    title: &str,
+    rows: &[Arc<Mutex<Stats>>],
+    done: impl Fn() -> bool,
+) -> std::io::Result<bool> {
+    let mut terminal = ratatui::init();
+    let result = draw_loop(&mut terminal, title, rows, done);
+    ratatui::restore();
+    result
+}
+
+fn draw_loop(
+    terminal: &mut ratatui::DefaultTerminal,
+    title: &str,
+    rows: &[Arc<Mutex<Stats>>],
+    done: impl Fn() -> bool,
+) -> std::io::Result<bool> {
+    loop {
+        terminal.draw(|frame| {
+            let [table_area, help_area] =
+                Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+            let header = Row::new([
+                "name", "time (s)", "solves", "solves/s", "MH/s", "p50 (ms)", "p90 (ms)",
+                "p99 (ms)", "http p50", "http p99", "errors", "err %",
+            ])
+            .bold();
+            let widths = [Constraint::Min(24)]
+                .into_iter()
+                .chain(std::iter::repeat_n(Constraint::Length(9), 11));
+            let table = Table::new(rows.iter().map(|row| row.lock().unwrap().row()), widths)
+                .header(header)
+                .block(Block::bordered().title(Line::from(title.to_string()).bold()));
+            frame.render_widget(table, table_area);
+            frame.render_widget(
+                Paragraph::new("q: quit").style(Style::new().fg(Color::DarkGray)),
+                help_area,
+            );
+        })?;
+
+        if done() {
+            return Ok(true);
+        }
+        if event::poll(Duration::from_millis(250))?
+            && let Event::Key(key) = event::read()?
+            && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL)))
+        {
+            return Ok(false);
+        }
+    }
+}